@@ -0,0 +1,47 @@
+//! This module defines the error type used throughout the library.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// The errors that can occur while reading a workbook (or writing its contents somewhere else).
+#[derive(Debug)]
+pub enum XlError {
+    /// A part of the xlsx file (e.g., `xl/worksheets/sheet1.xml`) that the workbook refers to does
+    /// not exist in the archive. This normally means the file is corrupt or was edited by hand.
+    MissingPart(String),
+    /// Reading from or writing to an underlying stream failed.
+    Io(io::Error),
+    /// A column (e.g., `C`) or cell reference (e.g., `C3`) passed in by the caller is not valid.
+    InvalidReference(String),
+    /// Reading stopped because the workbook went past one of the limits set with
+    /// `WorkbookOptions::max_cells` or `WorkbookOptions::max_decompressed_bytes`. The message says
+    /// which limit and where.
+    LimitExceeded(String),
+}
+
+impl fmt::Display for XlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            XlError::MissingPart(part) => write!(f, "could not find part in workbook: {}", part),
+            XlError::Io(e) => write!(f, "i/o error: {}", e),
+            XlError::InvalidReference(r) => write!(f, "invalid column or cell reference: {}", r),
+            XlError::LimitExceeded(limit) => write!(f, "limit exceeded: {}", limit),
+        }
+    }
+}
+
+impl Error for XlError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            XlError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for XlError {
+    fn from(e: io::Error) -> Self {
+        XlError::Io(e)
+    }
+}
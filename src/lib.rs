@@ -22,20 +22,32 @@
 //!
 //!     use xl::Workbook;
 //!
-//!     fn main () {
-//!         let mut wb = xl::Workbook::open("tests/data/Book1.xlsx").unwrap();
-//!         let sheets = wb.sheets();
-//!         let sheet = sheets.get("Sheet1");
-//!     }
+//!     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+//!     let sheets = wb.sheets();
+//!     let sheet = sheets.get("Sheet1");
 
+mod error;
 mod utils;
 mod wb;
+mod writer;
 mod ws;
 
 use std::fmt;
-pub use utils::{col2num, excel_number_to_date, num2col};
-pub use wb::Workbook;
-pub use ws::{ExcelValue, Worksheet};
+pub use error::XlError;
+pub use utils::{
+    col2num, excel_number_to_date, make_reference, num2col, num2col_unchecked, parse_reference,
+};
+pub use wb::{
+    sanitize_sheet_name, Alignment, CalcSettings, DateSystem, PartInfo, Workbook, WorkbookOptions,
+};
+pub use writer::WorkbookWriter;
+#[cfg(feature = "serde")]
+pub use ws::JsonLinesOptions;
+pub use ws::{
+    Cell, CellType, ColumnInfo, CsvOptions, Dimension, ExcelError, ExcelValue, FormatCategory,
+    FormulaType, Orientation, PageSetup, Row, Sheet2D, SheetKind, SheetVisibility, ViewSettings,
+    Worksheet, PROGRESS_INTERVAL,
+};
 
 enum SheetNameOrNum {
     Name(String),
@@ -81,7 +93,7 @@ impl<'a> fmt::Display for ConfigError<'a> {
 }
 
 impl Config {
-    pub fn new(args: &[String]) -> Result<Config, ConfigError> {
+    pub fn new(args: &[String]) -> Result<Config, ConfigError<'_>> {
         if args.len() < 2 {
             return Err(ConfigError::NeedPathAndTab(&args[0]));
         } else if args.len() < 3 {
@@ -146,7 +158,7 @@ pub fn run(config: Config) -> Result<(), String> {
         } else {
             1048576 // max number of rows in an Excel worksheet
         };
-        for row in ws.rows(&mut wb).take(nrows) {
+        for row in ws.rows(&mut wb).map_err(|e| e.to_string())?.take(nrows) {
             println!("{}", row);
         }
         Ok(())
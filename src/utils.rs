@@ -5,10 +5,18 @@ use crate::wb::DateSystem;
 
 const XL_MAX_COL: u16 = 16384;
 const XL_MIN_COL: u16 = 1;
+const XL_MAX_ROW: u32 = 1048576;
 
 /// Return column letter for column number `n`
 pub fn num2col(n: u16) -> Option<String> {
     if !(XL_MIN_COL..=XL_MAX_COL).contains(&n) { return None }
+    Some(num2col_unchecked(n as u32))
+}
+
+/// Return column letter for column number `n`, without checking that the column exists in Excel
+/// (i.e., `n` may be past `XFD`). This is useful for generating references outside of Excel's
+/// limits; use `num2col` to validate. A column number of 0 has no letter, so gives "".
+pub fn num2col_unchecked(n: u32) -> String {
     let mut s = String::new();
     let mut n = n;
     while n > 0 {
@@ -16,23 +24,135 @@ pub fn num2col(n: u16) -> Option<String> {
         n = (n - 1) / 26;
         s.push((65 + r) as char)
     }
-    Some(s.chars().rev().collect::<String>())
+    s.chars().rev().collect::<String>()
 }
 
 /// Return column number for column letter `letter`
 pub fn col2num(letter: &str) -> Option<u16> {
     let letter = letter.to_uppercase();
-    let mut num: u16 = 0;
+    // accumulate in a u32 and stop as soon as we are past the last column, so long strings of
+    // letters are rejected instead of overflowing
+    let mut num: u32 = 0;
     for c in letter.chars() {
-        if !('A'..='Z').contains(&c) { return None }
-        num = num * 26 + ((c as u16) - ('A' as u16)) + 1;
+        if !c.is_ascii_uppercase() { return None }
+        num = num * 26 + ((c as u32) - ('A' as u32)) + 1;
+        if num > XL_MAX_COL as u32 { return None }
+    }
+    if num < XL_MIN_COL as u32 { return None }
+    Some(num as u16)
+}
+
+/// Move every relative cell reference in `formula` by `rows` rows and `cols` columns, the way
+/// Excel does when a formula is copied from one cell to another. Absolute parts of a reference
+/// (e.g., the column in `$A1`) stay put, text in string literals and quoted sheet names is left
+/// alone, and references that would move off the sheet become `#REF!`.
+///
+/// Whole-row and whole-column ranges (`1:1`, `A:A`) and R1C1-style references are not adjusted.
+pub(crate) fn shift_formula(formula: &str, rows: i64, cols: i64) -> String {
+    let chars: Vec<char> = formula.chars().collect();
+    let mut out = String::with_capacity(formula.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let ch = chars[i];
+        // copy string literals ("...") and quoted sheet names ('...') as-is
+        if ch == '"' || ch == '\'' {
+            out.push(ch);
+            i += 1;
+            while i < chars.len() {
+                out.push(chars[i]);
+                i += 1;
+                if chars[i - 1] == ch { break }
+            }
+            continue
+        }
+        let starts_token = i == 0 || !is_name_char(chars[i - 1]);
+        if starts_token && (ch == '$' || ch.is_ascii_alphabetic()) {
+            if let Some((len, shifted)) = shift_reference(&chars[i..], rows, cols) {
+                out.push_str(&shifted);
+                i += len;
+                continue
+            }
+        }
+        out.push(ch);
+        i += 1;
+    }
+    out
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.'
+}
+
+/// If `chars` starts with an A1-style cell reference, return how many characters it takes up
+/// and the shifted reference.
+fn shift_reference(chars: &[char], rows: i64, cols: i64) -> Option<(usize, String)> {
+    let mut j = 0;
+    let col_abs = chars.first() == Some(&'$');
+    if col_abs { j += 1 }
+    let col_start = j;
+    while j < chars.len() && chars[j].is_ascii_alphabetic() { j += 1 }
+    if j == col_start || j - col_start > 3 { return None }
+    let letters: String = chars[col_start..j].iter().collect();
+    let row_abs = chars.get(j) == Some(&'$');
+    if row_abs { j += 1 }
+    let row_start = j;
+    while j < chars.len() && chars[j].is_ascii_digit() { j += 1 }
+    if j == row_start { return None }
+    // function names (LOG10), defined names (Q1_TOTAL), and sheet names (Q1!A1) can look like
+    // references too
+    if let Some(&next) = chars.get(j) {
+        if is_name_char(next) || next == '(' || next == '!' { return None }
+    }
+    let col = col2num(&letters)? as i64;
+    let row: i64 = chars[row_start..j].iter().collect::<String>().parse().ok()?;
+    let new_col = if col_abs { col } else { col + cols };
+    let new_row = if row_abs { row } else { row + rows };
+    if !(XL_MIN_COL as i64..=XL_MAX_COL as i64).contains(&new_col) || !(1..=XL_MAX_ROW as i64).contains(&new_row) {
+        return Some((j, "#REF!".to_string()))
     }
-    if !(XL_MIN_COL..=XL_MAX_COL).contains(&num) { return None }
-    Some(num)
+    let shifted = format!(
+        "{}{}{}{}",
+        if col_abs { "$" } else { "" },
+        num2col(new_col as u16)?,
+        if row_abs { "$" } else { "" },
+        new_row
+    );
+    Some((j, shifted))
 }
 
+/// Split a cell reference like `B3` into its column and row numbers (here `(2, 3)`). Absolute
+/// references like `$B$3` are accepted too. Returns `None` if `reference` is not a valid cell
+/// reference.
+pub fn parse_reference(reference: &str) -> Option<(u16, u32)> {
+    let reference = reference.strip_prefix('$').unwrap_or(reference);
+    let split = reference.find(|c: char| !c.is_ascii_alphabetic())?;
+    let (col, row) = reference.split_at(split);
+    let row = row.strip_prefix('$').unwrap_or(row);
+    if col.is_empty() || col.len() > 3 { return None }
+    if row.is_empty() || !row.bytes().all(|b| b.is_ascii_digit()) { return None }
+    let col = col2num(col)?;
+    let row: u32 = row.parse().ok()?;
+    if !(1..=XL_MAX_ROW).contains(&row) { return None }
+    Some((col, row))
+}
+
+/// Build a cell reference (e.g., `B3`) from a column and row number. This is the inverse of
+/// `parse_reference`.
+///
+/// # Panics
+///
+/// Panics if `col` is not a valid column number (1 through 16384).
+pub fn make_reference(col: u16, row: u32) -> String {
+    let letters = num2col(col).unwrap_or_else(|| panic!("invalid column number: {}", col));
+    format!("{}{}", letters, row)
+}
+
+/// Return the value of attribute `a` with any xml escapes (e.g., `&amp;`) resolved.
 pub fn attr_value(a: &Attribute) -> String {
-    String::from_utf8(a.value.to_vec()).unwrap()
+    match a.unescaped_value() {
+        Ok(value) => String::from_utf8(value.into_owned()).unwrap(),
+        Err(_) => String::from_utf8(a.value.to_vec()).unwrap(),
+    }
 }
 
 pub fn get(attrs: Attributes, which: &[u8]) -> Option<String> {
@@ -99,9 +219,116 @@ pub fn excel_number_to_date(number: f64, date_system: &DateSystem) -> DateConver
     }
 }
 
+/// Return the number Excel uses to represent `date` under the 1900 date system (the inverse of
+/// `excel_number_to_date`). Dates from March 1, 1900 on are shifted by a day to account for the
+/// 2/29/1900 that Excel thinks exists.
+pub(crate) fn date_to_excel_number(date: NaiveDateTime) -> f64 {
+    let base = NaiveDate::from_ymd(1899, 12, 31);
+    let mut days = (date.date() - base).num_days();
+    if days >= 60 { days += 1 }
+    let seconds = (date.time() - NaiveTime::from_hms(0, 0, 0)).num_milliseconds() as f64 / 1000.0;
+    days as f64 + seconds / 86400.0
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use quick_xml::events::BytesStart;
+
+    #[test]
+    fn date_round_trip() {
+        for (y, m, d, number) in [(1900, 1, 1, 1.0), (1900, 2, 28, 59.0), (1900, 3, 1, 61.0), (2020, 6, 18, 44000.0)] {
+            let date = NaiveDate::from_ymd(y, m, d).and_hms(0, 0, 0);
+            assert_eq!(date_to_excel_number(date), number);
+            match excel_number_to_date(number, &DateSystem::V1900) {
+                DateConversion::Date(back) => assert_eq!(back, date.date()),
+                _ => panic!("{} is not a date", number),
+            }
+        }
+        let noon = NaiveDate::from_ymd(2020, 6, 18).and_hms(12, 0, 0);
+        assert_eq!(date_to_excel_number(noon), 44000.5);
+    }
+
+    #[test]
+    fn reference_round_trip() {
+        for (reference, coords) in [("A1", (1, 1)), ("Z100", (26, 100)), ("AA1", (27, 1))] {
+            assert_eq!(parse_reference(reference), Some(coords));
+            assert_eq!(make_reference(coords.0, coords.1), reference);
+        }
+        assert_eq!(parse_reference("XFD1048576"), Some((16384, 1048576)));
+        assert_eq!(parse_reference("$B$3"), Some((2, 3)));
+        assert_eq!(parse_reference("b3"), Some((2, 3)));
+    }
+
+    #[test]
+    fn unchecked_columns() {
+        assert_eq!(num2col_unchecked(1), "A");
+        assert_eq!(num2col_unchecked(26), "Z");
+        assert_eq!(num2col_unchecked(27), "AA");
+        assert_eq!(num2col_unchecked(702), "ZZ");
+        assert_eq!(num2col_unchecked(703), "AAA");
+        assert_eq!(num2col_unchecked(16384), "XFD");
+        assert_eq!(num2col_unchecked(16385), "XFE");
+        assert_eq!(num2col_unchecked(u32::MAX), "MWLQKWU");
+        assert_eq!(num2col_unchecked(0), "");
+        assert_eq!(num2col(702), Some("ZZ".to_string()));
+        assert_eq!(num2col(703), Some("AAA".to_string()));
+    }
+
+    #[test]
+    fn column_limits() {
+        assert_eq!(col2num("XFD"), Some(16384));
+        assert_eq!(num2col(16384), Some("XFD".to_string()));
+        assert_eq!(col2num("XFE"), None);
+        assert_eq!(num2col(16385), None);
+        assert_eq!(num2col(0), None);
+        assert_eq!(col2num("ZZZZZZZ"), None);
+        assert_eq!(col2num(""), None);
+    }
+
+    #[test]
+    fn lowercase_references() {
+        assert_eq!(col2num("b"), Some(2));
+        assert_eq!(col2num("aa"), Some(27));
+        assert_eq!(parse_reference("b3"), Some((2, 3)));
+        assert_eq!(parse_reference("aa10"), Some((27, 10)));
+        assert_eq!(parse_reference("$xfd$1"), Some((16384, 1)));
+    }
+
+    #[test]
+    fn reference_garbage() {
+        for garbage in ["1A", "", "A", "12", "A0", "A1B", "A-1", "$$A1", "XFE1", "A1048577", "AAAA1"] {
+            assert_eq!(parse_reference(garbage), None, "{}", garbage);
+        }
+    }
+
+    #[test]
+    fn shift_formula_relative_and_absolute() {
+        assert_eq!(shift_formula("A1*2+$A$1", 1, 0), "A2*2+$A$1");
+        assert_eq!(shift_formula("SUM($A$1:A2)", 3, 1), "SUM($A$1:B5)");
+        assert_eq!(shift_formula("$B1+B$1", 2, 2), "$B3+D$1");
+    }
+
+    #[test]
+    fn shift_formula_leaves_other_text_alone() {
+        assert_eq!(shift_formula("LOG10(A1)&\"A1\"", 1, 0), "LOG10(A2)&\"A1\"");
+        assert_eq!(shift_formula("'Sheet A1'!B2+Sheet2!C3", 1, 0), "'Sheet A1'!B3+Sheet2!C4");
+        assert_eq!(shift_formula("Q1_TOTAL*A1", 0, 1), "Q1_TOTAL*B1");
+        assert_eq!(shift_formula("Q1!A1", 0, 1), "Q1!B1");
+    }
+
+    #[test]
+    fn shift_formula_off_sheet() {
+        assert_eq!(shift_formula("A2-A1", -1, 0), "A1-#REF!");
+    }
+
+    #[test]
+    fn attr_value_unescapes() {
+        let e = BytesStart::borrowed(br#"sheet name="P&amp;L &quot;Q1&quot;" id="&bogus;""#, 5);
+        let values: Vec<String> = e.attributes().map(|a| attr_value(&a.unwrap())).collect();
+        // values that cannot be unescaped are returned as they are
+        assert_eq!(values, vec!["P&L \"Q1\"", "&bogus;"]);
+    }
 
     #[test]
     fn num_to_letter_w() {
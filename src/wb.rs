@@ -1,15 +1,21 @@
 //! This module provides the functionality necessary to interact with an Excel workbook (i.e., the
 //! entire file).
 
+use crate::error::XlError;
 use crate::utils;
-use crate::ws::{SheetReader, Worksheet};
-use log::info;
+use crate::ws::{ExcelValue, SheetKind, SheetReader, SheetVisibility, Worksheet};
+use chrono::NaiveDate;
+use log::{info, warn};
 use quick_xml::events::Event;
 use quick_xml::Reader;
 use std::collections::HashMap;
 use std::fs;
+use std::mem;
 
-use std::io::{BufReader, Cursor, Read, Seek};
+use std::io::{self, BufRead, BufReader, BufWriter, Cursor, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use zip::read::ZipFile;
 use zip::ZipArchive;
 
 /// Excel spreadsheets support two different date systems:
@@ -23,15 +29,220 @@ use zip::ZipArchive;
 /// number represents unless you also know the date system the spreadsheet uses.
 ///
 /// See <https://tinyurl.com/4syjy6cw> for more information.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DateSystem {
     V1900,
     V1904,
 }
 
+/// The alignment settings of a cell, taken from the `<alignment>` child of the cell's `cellXfs`
+/// entry in `xl/styles.xml`. Cells without an explicit alignment get the default value (no
+/// horizontal or vertical alignment, no wrapping, and no rotation).
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Alignment {
+    /// Horizontal alignment (e.g., "left", "center", "centerContinuous")
+    pub horizontal: Option<String>,
+    /// Vertical alignment (e.g., "top", "center", "bottom")
+    pub vertical: Option<String>,
+    /// Whether the text in the cell wraps onto multiple lines
+    pub wrap_text: bool,
+    /// Rotation of the text in degrees (see section 18.8.1 of ISO/IEC 29500 for the meaning of
+    /// values above 90)
+    pub text_rotation: i16,
+}
+
+/// Size and compression information about one entry of the xlsx zip, as returned by
+/// `Workbook::part_info`. This is meant for diagnostics, e.g., to see which part of a workbook is
+/// taking up all the space.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartInfo {
+    /// The name of the entry (e.g., `xl/sharedStrings.xml`)
+    pub name: String,
+    /// The number of bytes the entry takes up in the archive
+    pub compressed_size: u64,
+    /// The number of bytes the entry takes up once it is decompressed
+    pub uncompressed_size: u64,
+    /// How the entry is compressed, e.g., "Deflated" or "Stored" (i.e., not compressed at all)
+    pub compression: String,
+}
+
+/// How Excel should calculate the workbook, taken from the `<calcPr>` element of
+/// `xl/workbook.xml` (see `Workbook::calc_settings`). Attributes that are missing from the file get
+/// Excel's defaults.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalcSettings {
+    /// The version of the calculation engine that last calculated the workbook. Excel recalculates
+    /// a workbook on open when this is older than its own engine. `None` if it is not recorded,
+    /// which is typical of files that were not written by Excel.
+    pub calc_id: Option<u32>,
+    /// Whether the workbook asks to be fully recalculated when it is opened. When this is set, the
+    /// cached values of formulas should not be trusted. Defaults to false.
+    pub full_calc_on_load: bool,
+    /// Whether iterative calculation (which allows circular references) is turned on. Defaults to
+    /// false.
+    pub iterate: bool,
+    /// The maximum number of iterations when iterative calculation is on. Defaults to 100.
+    pub iterate_count: u32,
+}
+
+impl Default for CalcSettings {
+    fn default() -> Self {
+        CalcSettings {
+            calc_id: None,
+            full_calc_on_load: false,
+            iterate: false,
+            iterate_count: 100,
+        }
+    }
+}
+
+/// How many date cells `Workbook::validate_date_system` looks at (at most).
+const DATE_SAMPLES: usize = 100;
+/// How many rows of each sheet `Workbook::validate_date_system` reads (at most) looking for dates,
+/// so that a big sheet without dates is not read all the way through.
+const DATE_SAMPLE_ROWS: usize = 1000;
+
+/// Options that change how a workbook is read. Options are set with builder methods, starting
+/// from `WorkbookOptions::new()` (which gives the defaults), and passed to `Workbook::open_with` or
+/// `Workbook::from_reader_with`.
+///
+/// # Example usage:
+///
+///     use xl::{Workbook, WorkbookOptions};
+///
+///     let options = WorkbookOptions::new().recompute_dimensions(true);
+///     let mut wb = Workbook::open_with("tests/data/Book1.xlsx", options).unwrap();
+#[derive(Debug, Default, Clone)]
+pub struct WorkbookOptions {
+    pub(crate) recompute_dimensions: bool,
+    pub(crate) lenient_xml: bool,
+    pub(crate) limits: Limits,
+}
+
+impl WorkbookOptions {
+    /// Create the default options.
+    pub fn new() -> Self {
+        WorkbookOptions::default()
+    }
+
+    /// Ignore the `<dimension>` element of each worksheet and instead compute the used area by
+    /// scanning the sheet before iterating over it. The `<dimension>` element can be stale (e.g.,
+    /// after rows are deleted), which affects how many empty rows and columns are simulated.
+    /// Computing it requires reading the worksheet twice, so this roughly doubles the time it
+    /// takes to iterate a sheet (and you can no longer start seeing rows immediately on very large
+    /// sheets). Off by default.
+    pub fn recompute_dimensions(mut self, recompute: bool) -> Self {
+        self.recompute_dimensions = recompute;
+        self
+    }
+
+    /// Accept end tags that do not match their start tags (e.g., `<extLst></extlst>`) in the
+    /// worksheets and the shared strings, instead of panicking. Some tools write files with this
+    /// kind of minor damage, and this lets you read them on a best-effort basis. The risk is that
+    /// the parser no longer notices when the structure of the xml is wrong, so a badly damaged
+    /// file may silently give you missing or misplaced values rather than an error. Off by
+    /// default.
+    pub fn lenient_xml(mut self, lenient: bool) -> Self {
+        self.lenient_xml = lenient;
+        self
+    }
+
+    /// Stop reading a worksheet with `XlError::LimitExceeded` once more than `max` cells have been
+    /// read from it. The count includes the empty cells that are filled in to make every row as
+    /// wide as the sheet, since a tiny file can declare a huge used area just as easily as it can
+    /// hold a huge number of cells. Use this (together with `max_decompressed_bytes`) to guard a
+    /// service that reads untrusted uploads. No limit by default.
+    ///
+    /// The limit applies to every `Worksheet::rows` iterator and `Worksheet::write_csv` call (and
+    /// the methods built on them). A `RowIter` that hits it simply ends, so check
+    /// `RowIter::error` when it matters whether you saw the whole sheet.
+    ///
+    /// # Example usage:
+    ///
+    ///     use xl::{Workbook, WorkbookOptions, XlError};
+    ///
+    ///     let options = WorkbookOptions::new().max_cells(10);
+    ///     let mut wb = Workbook::open_with("tests/data/Book1.xlsx", options).unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     assert!(matches!(ws.to_vec(&mut wb), Err(XlError::LimitExceeded(_))));
+    pub fn max_cells(mut self, max: u64) -> Self {
+        self.limits.max_cells = Some(max);
+        self
+    }
+
+    /// Refuse to decompress more than `max` bytes of any one part of the workbook (the worksheets,
+    /// shared strings, styles, `xl/workbook.xml`, relationships and document properties),
+    /// returning `XlError::LimitExceeded` instead. Parts whose size in the zip's directory is
+    /// already too big are rejected before anything is decompressed, and the bytes are also
+    /// counted as they are read, since the directory can lie. For the parts that are read when the
+    /// workbook is opened (the shared strings, the styles and `xl/workbook.xml`), the error comes
+    /// back from `Workbook::open_with` (as a message). Methods that cannot return an error, such
+    /// as `Workbook::sheets`, log a warning and stop at the limit as if the part ended there. No
+    /// limit by default.
+    pub fn max_decompressed_bytes(mut self, max: u64) -> Self {
+        self.limits.max_decompressed_bytes = Some(max);
+        self
+    }
+}
+
+/// The limits set in `WorkbookOptions`, which are copied into everything that reads a part so it
+/// can check them as it goes.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct Limits {
+    pub(crate) max_cells: Option<u64>,
+    pub(crate) max_decompressed_bytes: Option<u64>,
+}
+
+impl Limits {
+    /// Fail if `cells` cells is more than allowed.
+    pub(crate) fn check_cells(&self, cells: u64) -> Result<(), XlError> {
+        match self.max_cells {
+            Some(max) if cells > max => Err(XlError::LimitExceeded(format!(
+                "more than {} cells in a worksheet",
+                max
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Is `bytes` decompressed bytes of one part more than allowed?
+    pub(crate) fn too_many_bytes(&self, bytes: u64) -> bool {
+        self.max_decompressed_bytes.is_some_and(|max| bytes > max)
+    }
+
+    /// Fail if `bytes` decompressed bytes of the part `part` is more than allowed.
+    pub(crate) fn check_bytes(&self, bytes: u64, part: &str) -> Result<(), XlError> {
+        match self.max_decompressed_bytes {
+            Some(max) if bytes > max => Err(XlError::LimitExceeded(format!(
+                "more than {} decompressed bytes in {}",
+                max, part
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Like `check_bytes`, for parts read by methods that cannot return an error: going past the
+    /// limit is logged as a warning, and `false` tells the caller to stop reading as if the part
+    /// ended there.
+    pub(crate) fn allow_bytes(&self, bytes: u64, part: &str) -> bool {
+        match self.check_bytes(bytes, part) {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("{}", e);
+                false
+            }
+        }
+    }
+}
+
 /// The Workbook is the primary object you will use in this module. The public interface allows you
 /// to see the path of the workbook as well as its date system.
 ///
+/// A `Workbook` owns all of its data and is `Send` whenever its reader `T` is (e.g., `File` or
+/// `Cursor<Vec<u8>>`), so it can be opened on one thread and handed to another. The `RowIter`s
+/// it hands out borrow it mutably, so those stay on whichever thread currently has the workbook.
+///
 /// # Example usage:
 ///
 ///     use xl::Workbook;
@@ -43,10 +254,15 @@ where
     T: Read + Seek,
 {
     xls: ZipArchive<T>,
+    #[allow(dead_code)]
     encoding: String,
     pub date_system: DateSystem,
     strings: Vec<String>,
     styles: Vec<String>,
+    alignments: Vec<Alignment>,
+    /// the `numFmtId` of each style, indexed the same way as the `styles`
+    format_ids: Vec<u32>,
+    options: WorkbookOptions,
 }
 
 /// A `SheetMap` is an object containing all the sheets in a given workbook. The only way to obtain
@@ -100,17 +316,17 @@ pub enum SheetNameOrNum<'a> {
 /// Trait to make it easy to use `get` when trying to get a sheet. You will probably not use this
 /// struct directly.
 pub trait SheetAccessTrait {
-    fn go(&self) -> SheetNameOrNum;
+    fn go(&self) -> SheetNameOrNum<'_>;
 }
 
 impl SheetAccessTrait for &str {
-    fn go(&self) -> SheetNameOrNum {
-        SheetNameOrNum::Name(*self)
+    fn go(&self) -> SheetNameOrNum<'_> {
+        SheetNameOrNum::Name(self)
     }
 }
 
 impl SheetAccessTrait for usize {
-    fn go(&self) -> SheetNameOrNum {
+    fn go(&self) -> SheetNameOrNum<'_> {
         SheetNameOrNum::Pos(*self)
     }
 }
@@ -165,6 +381,12 @@ impl SheetMap {
     pub fn len(&self) -> u8 {
         (self.sheets_by_num.len() - 1) as u8
     }
+
+    /// Does the workbook have no sheets at all? This should not happen in a file written by
+    /// Excel, but it does in files that are damaged or produced by other tools.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 impl<T> Workbook<T>
@@ -173,12 +395,42 @@ where
 {
     /// xlsx zips contain an xml file that has a mapping of "ids" to "targets." The ids are used
     /// to uniquely identify sheets within the file. The targets have information on where the
-    /// sheets can be found within the zip. This function returns a hashmap of id -> target so that
-    /// you can quickly determine the name of the sheet xml file within the zip.
-    fn rels(&mut self) -> HashMap<String, String> {
-        let mut map = HashMap::new();
-        match self.xls.by_name("xl/_rels/workbook.xml.rels") {
-            Ok(rels) => {
+    /// sheets can be found within the zip. This function returns a hashmap of id -> (target, type)
+    /// so that you can quickly determine the name of the sheet xml file within the zip (and
+    /// whether it is a worksheet or a chartsheet).
+    fn rels(&mut self) -> HashMap<String, (String, String)> {
+        self.read_rels("xl/_rels/workbook.xml.rels")
+            .into_iter()
+            .map(|(id, target, rel_type)| (id, (target, rel_type)))
+            .collect()
+    }
+
+    /// Return the relationships (as type -> target pairs, in document order) of the sheet stored
+    /// at `sheet_target` (a target from the workbook's relationships, e.g.,
+    /// `worksheets/sheet1.xml`). They are read from the sheet's own relationships part (e.g.,
+    /// `xl/worksheets/_rels/sheet1.xml.rels`), which does not exist for sheets without any
+    /// drawings, hyperlinks, comments, tables, etc.
+    pub(crate) fn sheet_rels(&mut self, sheet_target: &str) -> Vec<(String, String)> {
+        let part = part_name(sheet_target);
+        let rels_part = match part.rfind('/') {
+            Some(slash) => format!("{}/_rels/{}.rels", &part[..slash], &part[slash + 1..]),
+            None => format!("_rels/{}.rels", part),
+        };
+        self.read_rels(&rels_part)
+            .into_iter()
+            .map(|(_, target, rel_type)| (rel_type, target))
+            .collect()
+    }
+
+    /// Read the (id, target, type) of every relationship in the relationships part `rels_part`
+    /// (e.g., `xl/_rels/workbook.xml.rels`), in document order. A missing part has no
+    /// relationships.
+    fn read_rels(&mut self, rels_part: &str) -> Vec<(String, String, String)> {
+        let mut rels = Vec::new();
+        let limits = self.options.limits;
+        match self.xls.by_name(rels_part) {
+            Ok(part) if !limits.allow_bytes(part.size(), rels_part) => rels,
+            Ok(part) => {
                 // Looking for tree structure like:
                 //   Relationships
                 //     Relationship(id = "abc", target = "def")
@@ -190,18 +442,22 @@ where
                 //
                 //  Uncomment the following line to print out a copy of what
                 //  the xml looks like (will probably not be too big).
-                // let _ = std::io::copy(&mut rels, &mut std::io::stdout());
+                // let _ = std::io::copy(&mut part, &mut std::io::stdout());
 
-                let reader = BufReader::new(rels);
-                let mut reader = Reader::from_reader(reader);
+                let mut reader = xml_reader(part);
                 reader.trim_text(true);
 
                 let mut buf = Vec::new();
                 loop {
-                    match reader.read_event(&mut buf) {
+                    let event = reader.read_event(&mut buf);
+                    if !limits.allow_bytes(reader.buffer_position() as u64, rels_part) {
+                        break;
+                    }
+                    match event {
                         Ok(Event::Empty(ref e)) if e.name() == b"Relationship" => {
                             let mut id = String::new();
                             let mut target = String::new();
+                            let mut rel_type = String::new();
                             e.attributes().for_each(|a| {
                                 let a = a.unwrap();
                                 if a.key == b"Id" {
@@ -210,8 +466,11 @@ where
                                 if a.key == b"Target" {
                                     target = utils::attr_value(&a);
                                 }
+                                if a.key == b"Type" {
+                                    rel_type = utils::attr_value(&a);
+                                }
                             });
-                            map.insert(id, target);
+                            rels.push((id, target, rel_type));
                         }
                         Ok(Event::Eof) => break, // exits the loop when reaching end of file
                         Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
@@ -220,19 +479,21 @@ where
                     buf.clear();
                 }
 
-                map
+                rels
             }
-            Err(_) => map,
+            Err(_) => rels,
         }
     }
 
     /// Return `SheetMap` of all sheets in this workbook. See `SheetMap` class and associated
-    /// methods for more detailed documentation.
+    /// methods for more detailed documentation. Sheets are numbered by the order of their tabs in
+    /// Excel (i.e., the document order of the `<sheet>` elements in `xl/workbook.xml`); their
+    /// `sheetId`s are ignored for this purpose.
     pub fn sheets(&mut self) -> SheetMap {
         let rels = self.rels();
         let num_sheets = rels
             .iter()
-            .filter(|(_, v)| v.starts_with("worksheet"))
+            .filter(|(_, (target, _))| target.starts_with("worksheet"))
             .count();
         let mut sheets = SheetMap {
             sheets_by_name: HashMap::new(),
@@ -240,22 +501,27 @@ where
         };
         sheets.sheets_by_num.push(None); // never a "0" sheet (consistent with VBA)
 
+        let limits = self.options.limits;
         match self.xls.by_name("xl/workbook.xml") {
+            Ok(wb) if !limits.allow_bytes(wb.size(), "xl/workbook.xml") => sheets,
             Ok(wb) => {
                 // let _ = std::io::copy(&mut wb, &mut std::io::stdout());
-                let reader = BufReader::new(wb);
-                let mut reader = Reader::from_reader(reader);
+                let mut reader = xml_reader(wb);
                 reader.trim_text(true);
 
                 let mut buf = Vec::new();
                 let mut current_sheet_num: u8 = 0;
                 loop {
-                    match reader.read_event(&mut buf) {
+                    let event = reader.read_event(&mut buf);
+                    if !limits.allow_bytes(reader.buffer_position() as u64, "xl/workbook.xml") {
+                        break;
+                    }
+                    match event {
                         Ok(Event::Empty(ref e)) if e.name() == b"sheet" => {
-                            current_sheet_num += 1;
                             let mut name = String::new();
                             let mut id = String::new();
                             let mut num = 0;
+                            let mut visibility = SheetVisibility::Visible;
                             e.attributes().for_each(|a| {
                                 let a = a.unwrap();
                                 if a.key == b"r:id" {
@@ -269,19 +535,32 @@ where
                                         num = r;
                                     }
                                 }
+                                if a.key == b"state" {
+                                    visibility = match &utils::attr_value(&a)[..] {
+                                        "hidden" => SheetVisibility::Hidden,
+                                        "veryHidden" => SheetVisibility::VeryHidden,
+                                        _ => SheetVisibility::Visible,
+                                    };
+                                }
                             });
+                            // without a relationship there is no way to find the sheet's data
+                            let (target, rel_type) = match rels.get(&id) {
+                                Some(rel) => rel.clone(),
+                                None => {
+                                    warn!("no relationship {} for sheet {}", id, name);
+                                    buf.clear();
+                                    continue;
+                                }
+                            };
+                            current_sheet_num += 1;
                             sheets
                                 .sheets_by_name
                                 .insert(name.clone(), current_sheet_num);
-                            let target = {
-                                let s = rels.get(&id).unwrap();
-                                if let Some(stripped) = s.strip_prefix('/') {
-                                    stripped.to_string()
-                                } else {
-                                    "xl/".to_owned() + s
-                                }
-                            };
-                            let ws = Worksheet::new(id, name, current_sheet_num, target, num);
+                            let mut ws = Worksheet::new(id, name, current_sheet_num, target, num);
+                            if rel_type.ends_with("/chartsheet") {
+                                ws.kind = SheetKind::Chart;
+                            }
+                            ws.visibility = visibility;
                             sheets.sheets_by_num.push(Some(ws));
                         }
                         Ok(Event::Eof) => break,
@@ -296,6 +575,63 @@ where
         }
     }
 
+    /// Return the worksheet at the zero-based `index`, i.e., the sheet whose tab is `index + 1`
+    /// from the left. Note the difference with `SheetMap::get`, which (like VBA) is one-based, and
+    /// with `Worksheet::position`, which is one-based as well: `worksheet_at(0)` returns the sheet
+    /// whose `position` is 1. Returns `None` if there are not that many sheets.
+    ///
+    /// This only reads `xl/workbook.xml` and its relationships (which are small), so it is cheap,
+    /// but if you need several sheets it is better to call `sheets` once.
+    ///
+    /// # Example usage:
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let ws = wb.worksheet_at(2).unwrap();
+    ///     assert_eq!(ws.name, "Time");
+    ///     assert_eq!(ws.position, 3);
+    pub fn worksheet_at(&mut self, index: usize) -> Option<Worksheet> {
+        let mut sheets = self.sheets();
+        sheets.sheets_by_num.get_mut(index.checked_add(1)?)?.take()
+    }
+
+    /// Return the sheets whose tabs are shown in Excel, in the order of the tabs. Both hidden and
+    /// very hidden sheets (see `SheetVisibility`) are left out, which is usually what you want
+    /// when importing a workbook, since hidden sheets tend to hold lookup tables or other helper
+    /// data.
+    ///
+    /// # Example usage:
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/hidden_sheets.xlsx").unwrap();
+    ///     let names: Vec<_> = wb.visible_sheets().into_iter().map(|ws| ws.name).collect();
+    ///     assert_eq!(names, vec!["Data", "Summary"]);
+    pub fn visible_sheets(&mut self) -> Vec<Worksheet> {
+        self.sheets()
+            .sheets_by_num
+            .into_iter()
+            .flatten()
+            .filter(|ws| ws.visibility == SheetVisibility::Visible)
+            .collect()
+    }
+
+    /// Return the sheet whose tab is the furthest left (i.e., `worksheet_at(0)`), which is handy
+    /// for workbooks that only hold one sheet of data. Like `worksheet_at`, this goes by the order
+    /// of the tabs, not by `sheetId`. Returns `None` if the workbook has no sheets.
+    ///
+    /// # Example usage:
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let ws = wb.first_sheet().unwrap();
+    ///     assert_eq!(ws.name, "Sheet1");
+    pub fn first_sheet(&mut self) -> Option<Worksheet> {
+        self.worksheet_at(0)
+    }
+
     /// Open an existing workbook (xlsx file). Returns a `Result` in case there is an error opening
     /// the workbook.
     ///
@@ -343,135 +679,775 @@ where
     where
         T: Read + Seek,
     {
+        Workbook::new_with(buff, WorkbookOptions::default())
+    }
+
+    /// Read the parts of the workbook that are needed up front (the shared strings, styles and
+    /// date system), using `options`.
+    fn new_with(buff: T, options: WorkbookOptions) -> Result<Self, String> {
         match zip::ZipArchive::new(buff) {
             Ok(mut xls) => {
-                let strings = strings(&mut xls);
-                let styles = find_styles(&mut xls);
-                let date_system = get_date_system(&mut xls);
-                println!("date_system: {:?}", date_system);
+                let strings = strings(&mut xls, options.lenient_xml, options.limits)
+                    .map_err(|e| e.to_string())?;
+                let (styles, alignments, format_ids) =
+                    find_styles(&mut xls, options.limits).map_err(|e| e.to_string())?;
+                let date_system =
+                    get_date_system(&mut xls, options.limits).map_err(|e| e.to_string())?;
+                info!("date_system: {:?}", date_system);
                 Ok(Workbook {
                     xls,
                     encoding: String::from("utf8"),
                     date_system,
                     strings,
                     styles,
+                    alignments,
+                    format_ids,
+                    options,
                 })
             }
             Err(e) => Err(e.to_string()),
         }
     }
 
-    /// Alternative name for `Workbook::new`.
+    /// Open a workbook from any reader (e.g., a `File` or a `Cursor` over bytes in memory) using
+    /// the default options.
+    ///
+    /// # Example usage:
+    ///
+    ///     use std::fs::File;
+    ///     use xl::Workbook;
+    ///
+    ///     let file = File::open("tests/data/Book1.xlsx").unwrap();
+    ///     let mut wb = Workbook::from_reader(file).unwrap();
+    ///     assert_eq!(wb.sheets().len(), 4);
+    pub fn from_reader(reader: T) -> Result<Self, String> {
+        Workbook::from_reader_with(reader, WorkbookOptions::default())
+    }
 
-    /// Simple method to print out all the inner files of the xlsx zip.
-    pub fn contents(&mut self) {
-        for i in 0..self.xls.len() {
-            let file = self.xls.by_index(i).unwrap();
-            let outpath = match file.enclosed_name() {
-                Some(path) => path.to_owned(),
-                None => continue,
-            };
+    /// The same as `from_reader`, but using the given `options`. See `WorkbookOptions` for what
+    /// can be configured.
+    pub fn from_reader_with(reader: T, options: WorkbookOptions) -> Result<Self, String> {
+        Workbook::new_with(reader, options)
+    }
+
+    /// The names of all the entries (sheets, styles, shared strings, relationships, document
+    /// properties, etc.) in the xlsx zip, in the order they are stored. This is mostly useful for
+    /// debugging, e.g., to check whether a part exists before trying to read it.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let contents = wb.contents();
+    ///     assert!(contents.contains(&"xl/workbook.xml".to_string()));
+    pub fn contents(&mut self) -> Vec<String> {
+        (0..self.xls.len())
+            .filter_map(|i| self.xls.by_index_raw(i).ok().map(|f| f.name().to_string()))
+            .collect()
+    }
+
+    /// Size and compression information for every entry in the xlsx zip, in the order they are
+    /// stored (see `contents` for just the names). Only the zip's metadata is read, so nothing is
+    /// decompressed.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let info = wb.part_info();
+    ///     let strings = info.iter().find(|p| p.name == "xl/sharedStrings.xml").unwrap();
+    ///     assert_eq!(strings.compression, "Deflated");
+    ///     assert!(strings.compressed_size < strings.uncompressed_size);
+    pub fn part_info(&mut self) -> Vec<PartInfo> {
+        (0..self.xls.len())
+            .filter_map(|i| {
+                self.xls.by_index_raw(i).ok().map(|f| PartInfo {
+                    name: f.name().to_string(),
+                    compressed_size: f.compressed_size(),
+                    uncompressed_size: f.size(),
+                    compression: f.compression().to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// Read the calculation settings of the workbook, e.g., to decide whether the cached values of
+    /// formulas can be trusted or whether the workbook expects to be recalculated when it is opened.
+    /// Workbooks without a `<calcPr>` element get the defaults (see `CalcSettings`).
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/calc_settings.xlsx").unwrap();
+    ///     let calc = wb.calc_settings();
+    ///     assert!(calc.full_calc_on_load);
+    ///     assert_eq!(calc.iterate_count, 50);
+    pub fn calc_settings(&mut self) -> CalcSettings {
+        let mut settings = CalcSettings::default();
+        let limits = self.options.limits;
+        let wb = match self.xls.by_name("xl/workbook.xml") {
+            Ok(wb) if limits.allow_bytes(wb.size(), "xl/workbook.xml") => wb,
+            _ => return settings,
+        };
+        let mut reader = xml_reader(wb);
+        reader.trim_text(true);
+        let mut buf = Vec::new();
+        loop {
+            let event = reader.read_event(&mut buf);
+            if !limits.allow_bytes(reader.buffer_position() as u64, "xl/workbook.xml") {
+                break;
+            }
+            match event {
+                Ok(Event::Empty(ref e)) | Ok(Event::Start(ref e)) if e.name() == b"calcPr" => {
+                    e.attributes().for_each(|a| {
+                        let a = a.unwrap();
+                        let v = utils::attr_value(&a);
+                        match a.key {
+                            b"calcId" => settings.calc_id = v.parse().ok(),
+                            b"fullCalcOnLoad" => {
+                                settings.full_calc_on_load = v == "1" || v == "true"
+                            }
+                            b"iterate" => settings.iterate = v == "1" || v == "true",
+                            b"iterateCount" => {
+                                if let Ok(count) = v.parse() {
+                                    settings.iterate_count = count;
+                                }
+                            }
+                            _ => (),
+                        }
+                    });
+                    break;
+                }
+                Ok(Event::Eof) => break,
+                Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
+                _ => (),
+            }
+            buf.clear();
+        }
+        settings
+    }
+
+    /// The names of the sheets as listed in the document properties (`docProps/app.xml`), in the
+    /// order they are listed there. Excel writes these alongside `xl/workbook.xml`, so comparing
+    /// them with the names from `sheets` is a cheap check for a corrupt or badly edited file.
+    /// Excel lists the workbook's defined names (e.g., `Data!Print_Area`) there too, so titles
+    /// that match a defined name in `xl/workbook.xml` are left out. The headings Excel groups the
+    /// titles under are not used, since they are written in the language of the Excel that saved
+    /// the file. Workbooks without a readable `docProps/app.xml` give an empty list.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let names = wb.app_sheet_names();
+    ///     assert_eq!(names, vec!["Sheet1", "Sheet2", "Time", "Sheet3"]);
+    ///     assert_eq!(names, wb.sheets().by_name());
+    pub fn app_sheet_names(&mut self) -> Vec<String> {
+        let limits = self.options.limits;
+        let titles = match self.xls.by_name("docProps/app.xml") {
+            Ok(app) if !limits.allow_bytes(app.size(), "docProps/app.xml") => return vec![],
+            Ok(app) => match titles_of_parts(app, limits) {
+                Some(titles) => titles,
+                None => {
+                    warn!("could not read the titles in docProps/app.xml");
+                    return vec![];
+                }
+            },
+            Err(_) => return vec![],
+        };
+        // how Excel lists each defined name: built-in names lose their prefix, and names that
+        // belong to a sheet get the sheet's name in front
+        let sheets = self.sheets();
+        let names: Vec<String> = self
+            .defined_names()
+            .into_iter()
+            .flat_map(|(name, scope, _)| {
+                let name = name.trim_start_matches("_xlnm.").to_string();
+                match scope.and_then(|index| sheets.get(index + 1)) {
+                    Some(ws) => vec![
+                        format!("{}!{}", ws.name, name),
+                        format!("'{}'!{}", ws.name.replace('\'', "''"), name),
+                    ],
+                    None => vec![name],
+                }
+            })
+            .collect();
+        titles
+            .into_iter()
+            .filter(|title| !names.contains(title))
+            .collect()
+    }
 
-            if file.name().ends_with('/') {
-                println!("File {}: \"{}\"", i, outpath.display());
+    /// Check whether the dates in the workbook make sense under its date system (`date_system`),
+    /// as a safety net for files whose date system was lost or mixed up (e.g., when sheets were
+    /// copied between workbooks), which shifts every date by 1462 days. Up to 100 date-formatted
+    /// cells are sampled from the first 1000 rows of each sheet, and each is checked for falling
+    /// between the years 1900 and 2100 under both systems. The system under which more of them do
+    /// is returned (the current one when it is a tie), and a warning is logged if that is not the
+    /// current system. Returns `None` if the workbook has no dates to go by (including when its
+    /// dates only start further down).
+    ///
+    /// This is a heuristic: most dates are plausible under either system, so it can only catch
+    /// the cases where one of them puts the dates out of range.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::{DateSystem, Workbook};
+    ///
+    ///     let mut wb = Workbook::open("tests/data/wrong_date_system.xlsx").unwrap();
+    ///     if let Some(system) = wb.validate_date_system() {
+    ///         wb.date_system = system;
+    ///     }
+    ///     assert_eq!(wb.date_system, DateSystem::V1904);
+    pub fn validate_date_system(&mut self) -> Option<DateSystem> {
+        let mut samples: Vec<f64> = vec![];
+        let sheets = self.sheets();
+        for ws in sheets.sheets_by_num.iter().flatten() {
+            let rows = match ws.rows(self) {
+                Ok(rows) => rows,
+                Err(_) => continue,
+            };
+            for row in rows.take(DATE_SAMPLE_ROWS) {
+                let dates = row.0.iter().filter_map(|cell| match cell.value {
+                    ExcelValue::Date(_) | ExcelValue::DateTime(_) => {
+                        cell.raw_value.parse::<f64>().ok()
+                    }
+                    _ => None,
+                });
+                samples.extend(dates);
+                if samples.len() >= DATE_SAMPLES {
+                    break;
+                }
+            }
+            if samples.len() >= DATE_SAMPLES {
+                break;
+            }
+        }
+        samples.truncate(DATE_SAMPLES);
+        if samples.is_empty() {
+            return None;
+        }
+        let first = utils::date_to_excel_number(NaiveDate::from_ymd(1900, 1, 1).and_hms(0, 0, 0));
+        let last = utils::date_to_excel_number(NaiveDate::from_ymd(2101, 1, 1).and_hms(0, 0, 0));
+        // a number stands for the same date under the 1904 system as the number plus 1462 does
+        // under the 1900 system
+        let plausible = |system: DateSystem| {
+            let offset = if system == DateSystem::V1904 {
+                1462.0
             } else {
-                println!(
-                    "File {}: \"{}\" ({} bytes)",
-                    i,
-                    outpath.display(),
-                    file.size()
-                );
+                0.0
+            };
+            samples
+                .iter()
+                .filter(|&&n| (first..last).contains(&(n + offset)))
+                .count()
+        };
+        let current = self.date_system;
+        let other = match current {
+            DateSystem::V1900 => DateSystem::V1904,
+            DateSystem::V1904 => DateSystem::V1900,
+        };
+        if plausible(other) > plausible(current) {
+            warn!(
+                "dates look wrong under the {:?} date system; {:?} is more likely",
+                current, other
+            );
+            Some(other)
+        } else {
+            Some(current)
+        }
+    }
+
+    /// Write every worksheet in this workbook to its own csv file in `dir` (which must already
+    /// exist). Each file is named after its sheet, with any characters that are not allowed in
+    /// file names replaced by underscores. Every sheet is streamed with `Worksheet::write_csv`, so
+    /// memory use stays bounded. Returns the paths of the files that were created, in the order
+    /// of the sheets in the workbook.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let dir = std::env::temp_dir().join("xl_export_example");
+    ///     std::fs::create_dir_all(&dir).unwrap();
+    ///     let paths = wb.export_all_csv(&dir).unwrap();
+    ///     assert_eq!(paths.len(), 4);
+    ///     assert!(paths[0].ends_with("Sheet1.csv"));
+    pub fn export_all_csv(&mut self, dir: &Path) -> Result<Vec<PathBuf>, XlError> {
+        self.export_all_csv_with(dir, sanitize_sheet_name)
+    }
+
+    /// Like `export_all_csv`, but `file_name` decides what each file is called: it is given the
+    /// name of each sheet (in order) and returns the name of its file, without the `.csv`
+    /// extension. Its result is used as-is, so it must be a valid file name (see
+    /// `sanitize_sheet_name`, which `export_all_csv` uses, for a good starting point). If two
+    /// sheets end up with the same name, the position of the later one is appended to its name.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::{sanitize_sheet_name, Workbook};
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let dir = std::env::temp_dir().join("xl_export_with_example");
+    ///     std::fs::create_dir_all(&dir).unwrap();
+    ///     let mut n = 0;
+    ///     let paths = wb
+    ///         .export_all_csv_with(&dir, |name| {
+    ///             n += 1;
+    ///             format!("{:02}_{}", n, sanitize_sheet_name(name).replace(' ', "_"))
+    ///         })
+    ///         .unwrap();
+    ///     assert!(paths[2].ends_with("03_Time.csv"));
+    pub fn export_all_csv_with<F>(
+        &mut self,
+        dir: &Path,
+        mut file_name: F,
+    ) -> Result<Vec<PathBuf>, XlError>
+    where
+        F: FnMut(&str) -> String,
+    {
+        let sheets = self.sheets();
+        let mut paths: Vec<PathBuf> = Vec::with_capacity(sheets.len() as usize);
+        for ws in sheets.sheets_by_num.iter().flatten() {
+            let name = file_name(&ws.name);
+            let mut path = dir.join(format!("{}.csv", name));
+            if paths.contains(&path) {
+                // two sheet names can sanitize to the same thing (e.g., "a/b" and "a_b")
+                path = dir.join(format!("{}_{}.csv", name, ws.position));
             }
+            let mut out = BufWriter::new(fs::File::create(&path)?);
+            ws.write_csv(self, &mut out)?;
+            out.flush()?;
+            paths.push(path);
         }
+        Ok(paths)
     }
 
-    /// Create a SheetReader for the given worksheet. A `SheetReader` is a struct in the
-    /// `xl::Worksheet` class that can be used to iterate over rows, etc. See documentation in the
-    /// `xl::Worksheet` module for more information.
-    pub fn sheet_reader<'a>(&'a mut self, zip_target: &str) -> SheetReader<'a> {
-        let target = match self.xls.by_name(zip_target) {
-            Ok(ws) => ws,
-            Err(_) => panic!("Could not find worksheet: {}", zip_target),
+    /// Return the shared string table of the workbook (read from `xl/sharedStrings.xml` when the
+    /// workbook is opened). The value of a cell with `t="s"` is an index into this table, so
+    /// `shared_strings()[n]` is the text of a cell whose `raw_value` is `n`. Rich text is
+    /// flattened into plain text. Workbooks without shared strings return an empty slice.
+    ///
+    /// # Example usage:
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     assert_eq!(wb.shared_strings()[0], "Test");
+    pub fn shared_strings(&self) -> &[String] {
+        &self.strings
+    }
+
+    /// Stream the shared string table straight from `xl/sharedStrings.xml`, one string at a time
+    /// and in the same order as `shared_strings`, without collecting the strings into a vector.
+    /// This is useful for processing every string once (e.g., to build a search index) in
+    /// constant memory. Each call reads the part from the start again.
+    ///
+    /// # Example usage:
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let longest = wb.shared_strings_iter().map(|s| s.len()).max().unwrap();
+    ///     assert!(longest >= "Test".len());
+    pub fn shared_strings_iter(&mut self) -> impl Iterator<Item = String> + '_ {
+        shared_strings_iter(&mut self.xls, self.options.lenient_xml, self.options.limits)
+    }
+
+    /// Return the name, scope, and formula (e.g., `Sheet1!$A$1:$B$2`) of every defined name in the
+    /// workbook. The scope is the zero-based index of the sheet the name belongs to, or `None` for
+    /// names that apply to the whole workbook.
+    pub(crate) fn defined_names(&mut self) -> Vec<(String, Option<usize>, String)> {
+        let mut names = vec![];
+        let limits = self.options.limits;
+        let wb = match self.xls.by_name("xl/workbook.xml") {
+            Ok(wb) if limits.allow_bytes(wb.size(), "xl/workbook.xml") => wb,
+            _ => return names,
         };
-        // let _ = std::io::copy(&mut target, &mut std::io::stdout());
-        let reader = BufReader::new(target);
-        let mut reader = Reader::from_reader(reader);
+        let mut reader = xml_reader(wb);
         reader.trim_text(true);
-        SheetReader::new(reader, &self.strings, &self.styles, &self.date_system)
+        let mut buf = Vec::new();
+        let mut current: Option<(String, Option<usize>, String)> = None;
+        loop {
+            let event = reader.read_event(&mut buf);
+            if !limits.allow_bytes(reader.buffer_position() as u64, "xl/workbook.xml") {
+                break;
+            }
+            match event {
+                Ok(Event::Start(ref e)) if e.name() == b"definedName" => {
+                    let mut name = String::new();
+                    let mut scope = None;
+                    e.attributes().for_each(|a| {
+                        let a = a.unwrap();
+                        if a.key == b"name" {
+                            name = utils::attr_value(&a);
+                        }
+                        if a.key == b"localSheetId" {
+                            scope = utils::attr_value(&a).parse().ok();
+                        }
+                    });
+                    current = Some((name, scope, String::new()));
+                }
+                Ok(Event::Text(ref e)) => {
+                    if let Some((_, _, formula)) = current.as_mut() {
+                        formula.push_str(&e.unescape_and_decode(&reader).unwrap());
+                    }
+                }
+                Ok(Event::End(ref e)) if e.name() == b"definedName" => {
+                    names.extend(current.take());
+                }
+                Ok(Event::Eof) => break,
+                Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
+                _ => (),
+            }
+            buf.clear();
+        }
+        names
+    }
+
+    /// Swap the archive for one built over `placeholder` (which must hold a valid, if empty, zip)
+    /// and rebuild the workbook from the reader the old archive was using, exactly as when it was
+    /// first opened. Used by the `reopen` methods. The old reader is used up either way, so if the
+    /// workbook cannot be rebuilt, everything read from it is dropped as well and the workbook is
+    /// left as if it had been opened from the empty placeholder.
+    fn rebuild(&mut self, placeholder: T) -> Result<(), XlError> {
+        let placeholder = ZipArchive::new(placeholder).map_err(io::Error::from)?;
+        let reader = mem::replace(&mut self.xls, placeholder).into_inner();
+        let options = self.options.clone();
+        match Workbook::new_with(reader, options) {
+            Ok(wb) => {
+                *self = wb;
+                Ok(())
+            }
+            Err(e) => {
+                self.strings.clear();
+                self.styles.clear();
+                self.alignments.clear();
+                self.format_ids.clear();
+                self.date_system = DateSystem::V1900;
+                Err(XlError::Io(io::Error::new(io::ErrorKind::InvalidData, e)))
+            }
+        }
+    }
+
+    /// The options this workbook was opened with.
+    pub(crate) fn options(&self) -> &WorkbookOptions {
+        &self.options
+    }
+
+    /// Create a SheetReader for the given worksheet. A `SheetReader` is a struct in the
+    /// `xl::Worksheet` class that can be used to iterate over rows, etc. See documentation in the
+    /// `xl::Worksheet` module for more information. `zip_target` is the target of the sheet as
+    /// found in the workbook relationships, which may be relative to the `xl` folder (e.g.,
+    /// `worksheets/sheet1.xml`) or absolute (e.g., `/xl/worksheets/sheet1.xml`). Returns
+    /// `XlError::MissingPart` if the target does not exist in the workbook.
+    pub fn sheet_reader<'a>(&'a mut self, zip_target: &str) -> Result<SheetReader<'a>, XlError> {
+        let reader = xml_sheet_reader(&mut self.xls, zip_target, &self.options)?;
+        Ok(SheetReader::new(
+            reader,
+            &self.strings,
+            &self.styles,
+            &self.alignments,
+            &self.format_ids,
+            &self.date_system,
+        ))
+    }
+
+    /// Hand the raw quick-xml reader of a sheet to `f` and return whatever it returns, for
+    /// parsing things that the rest of the library does not read. `zip_target` is the same as for
+    /// `sheet_reader` (see `Worksheet::target`), and `XlError::MissingPart` is returned if it
+    /// does not exist. The reader is set up the way the library uses it: a byte order mark is
+    /// skipped, text is trimmed, and end tags are checked unless the workbook was opened with
+    /// `WorkbookOptions::lenient_xml`. Everything else (the event buffer, keeping track of where
+    /// you are, and handling errors) is up to `f`.
+    ///
+    /// # Example usage:
+    ///
+    ///     use quick_xml::events::Event;
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let cells = wb
+    ///         .with_sheet_reader(ws.target(), |reader| {
+    ///             let mut buf = Vec::new();
+    ///             let mut cells = 0;
+    ///             loop {
+    ///                 match reader.read_event(&mut buf) {
+    ///                     Ok(Event::Start(ref e)) if e.name() == b"c" => cells += 1,
+    ///                     Ok(Event::Eof) | Err(_) => break,
+    ///                     _ => (),
+    ///                 }
+    ///                 buf.clear();
+    ///             }
+    ///             cells
+    ///         })
+    ///         .unwrap();
+    ///     assert!(cells > 0);
+    pub fn with_sheet_reader<F, R>(&mut self, zip_target: &str, f: F) -> Result<R, XlError>
+    where
+        F: FnOnce(&mut Reader<BufReader<ZipFile<'_>>>) -> R,
+    {
+        let mut reader = xml_sheet_reader(&mut self.xls, zip_target, &self.options)?;
+        Ok(f(&mut reader))
     }
 }
 
-fn strings<T>(zip_file: &mut ZipArchive<T>) -> Vec<String>
+/// Open the xml of the sheet at `zip_target` (see `Workbook::sheet_reader`), following the
+/// `options` (mismatched end tags are accepted with `WorkbookOptions::lenient_xml`, and a part that
+/// is bigger than `WorkbookOptions::max_decompressed_bytes` is refused).
+fn xml_sheet_reader<'a, T>(
+    xls: &'a mut ZipArchive<T>,
+    zip_target: &str,
+    options: &WorkbookOptions,
+) -> Result<Reader<BufReader<ZipFile<'a>>>, XlError>
 where
     T: Read + Seek,
 {
-    let mut strings = Vec::new();
-    match zip_file.by_name("xl/sharedStrings.xml") {
-        Ok(strings_file) => {
-            let reader = BufReader::new(strings_file);
-            let mut reader = Reader::from_reader(reader);
-            reader.trim_text(true);
-            let mut buf = Vec::new();
-            let mut this_string = String::new();
-            let mut preserve_space = false;
-            loop {
-                match reader.read_event(&mut buf) {
-                    Ok(Event::Start(ref e)) if e.name() == b"t" => {
-                        if let Some(att) = utils::get(e.attributes(), b"xml:space") {
-                            if att == "preserve" {
-                                preserve_space = true;
-                            } else {
-                                preserve_space = false;
-                            }
-                        } else {
-                            preserve_space = false;
-                        }
-                    }
-                    Ok(Event::Text(ref e)) => {
-                        this_string.push_str(&e.unescape_and_decode(&reader).unwrap()[..])
+    let part = part_name(zip_target);
+    let target = match xls.by_name(&part) {
+        Ok(ws) => ws,
+        Err(_) => return Err(XlError::MissingPart(part)),
+    };
+    options.limits.check_bytes(target.size(), &part)?;
+    // let _ = std::io::copy(&mut target, &mut std::io::stdout());
+    let mut reader = xml_reader(target);
+    reader.trim_text(true);
+    reader.check_end_names(!options.lenient_xml);
+    Ok(reader)
+}
+
+/// Wrap a part of the xlsx zip in an xml reader. Some writers start their parts with a UTF-8 byte
+/// order mark, which is skipped so that the first thing the parser sees is the xml itself.
+fn xml_reader<R: Read>(part: R) -> Reader<BufReader<R>> {
+    let mut reader = BufReader::new(part);
+    if let Ok(buf) = reader.fill_buf() {
+        if buf.starts_with(b"\xEF\xBB\xBF") {
+            reader.consume(3);
+        }
+    }
+    Reader::from_reader(reader)
+}
+
+/// Turn the target of a workbook relationship into the name of the entry in the zip file. Targets
+/// are relative to the `xl` folder unless they start with a `/`, in which case they are relative
+/// to the root of the zip file.
+pub(crate) fn part_name(target: &str) -> String {
+    match target.strip_prefix('/') {
+        Some(absolute) => absolute.to_string(),
+        None => format!("xl/{}", target),
+    }
+}
+
+/// Turn a sheet name into something that can safely be used as a file name on any platform:
+/// characters that are not allowed in file names are replaced by underscores, as are trailing dots
+/// and spaces. This is how `Workbook::export_all_csv` names its files.
+///
+/// # Example usage
+///
+///     use xl::sanitize_sheet_name;
+///
+///     assert_eq!(sanitize_sheet_name("Q1/Q2"), "Q1_Q2");
+pub fn sanitize_sheet_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let name = name.trim_end_matches(['.', ' ']);
+    if name.is_empty() {
+        "_".to_owned()
+    } else {
+        name.to_owned()
+    }
+}
+
+fn strings<T>(
+    zip_file: &mut ZipArchive<T>,
+    lenient: bool,
+    limits: Limits,
+) -> Result<Vec<String>, XlError>
+where
+    T: Read + Seek,
+{
+    let mut iter = shared_strings_iter(zip_file, lenient, limits);
+    let strings = iter.by_ref().collect();
+    match iter.error {
+        Some(e) => Err(e),
+        None => Ok(strings),
+    }
+}
+
+/// Stream the strings in `xl/sharedStrings.xml` (if there is one) one at a time. With `lenient`,
+/// mismatched end tags are accepted (see `WorkbookOptions::lenient_xml`). The strings end early if
+/// the part is bigger than the `limits` allow.
+fn shared_strings_iter<T>(
+    zip_file: &mut ZipArchive<T>,
+    lenient: bool,
+    limits: Limits,
+) -> SharedStrings<zip::read::ZipFile<'_>>
+where
+    T: Read + Seek,
+{
+    let mut error = None;
+    let reader = zip_file
+        .by_name("xl/sharedStrings.xml")
+        .ok()
+        .and_then(|strings_file| {
+            if let Err(e) = limits.check_bytes(strings_file.size(), "xl/sharedStrings.xml") {
+                error = Some(e);
+                return None;
+            }
+            let mut reader = xml_reader(strings_file);
+            // whitespace is significant inside <t> elements with xml:space="preserve"
+            reader.trim_text(false);
+            reader.check_end_names(!lenient);
+            Some(reader)
+        });
+    SharedStrings {
+        reader,
+        buf: Vec::new(),
+        limits,
+        error,
+    }
+}
+
+/// An iterator over the strings (i.e., the `<si>` elements) of a shared strings part.
+struct SharedStrings<R: Read> {
+    /// `None` once the part has been read (or if there is no part at all)
+    reader: Option<Reader<BufReader<R>>>,
+    buf: Vec<u8>,
+    limits: Limits,
+    /// why the strings ended early, if they did
+    error: Option<XlError>,
+}
+
+impl<R: Read> Iterator for SharedStrings<R> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let reader = self.reader.as_mut()?;
+        let mut this_string = String::new();
+        let mut in_text = false;
+        let mut in_phonetic = false;
+        let mut preserve_space = false;
+        loop {
+            self.buf.clear();
+            let event = reader.read_event(&mut self.buf);
+            let position = reader.buffer_position() as u64;
+            if let Err(e) = self.limits.check_bytes(position, "xl/sharedStrings.xml") {
+                self.error = Some(e);
+                self.reader = None;
+                return None;
+            }
+            match event {
+                Ok(Event::Start(ref e)) if e.name() == b"si" => this_string.clear(),
+                Ok(Event::Empty(ref e)) if e.name() == b"si" => return Some("".to_owned()),
+                // a string with rich text formatting is split into several runs (<r>), each
+                // with its own <t>, so the string is only complete at the end of the <si>
+                Ok(Event::End(ref e)) if e.name() == b"si" => return Some(this_string),
+                Ok(Event::Start(ref e)) if e.name() == b"t" => {
+                    in_text = true;
+                    if let Some(att) = utils::get(e.attributes(), b"xml:space") {
+                        preserve_space = att == "preserve";
+                    } else {
+                        preserve_space = false;
                     }
-                    Ok(Event::Empty(ref e)) if e.name() == b"t" => strings.push("".to_owned()),
-                    Ok(Event::End(ref e)) if e.name() == b"t" => {
-                        if preserve_space {
-                            strings.push(this_string.to_owned());
-                        } else {
-                            strings.push(this_string.trim().to_owned());
-                        }
-                        this_string = String::new();
+                }
+                Ok(Event::End(ref e)) if e.name() == b"t" => in_text = false,
+                // phonetic hints (e.g., furigana) are not part of the text itself
+                Ok(Event::Start(ref e)) if e.name() == b"rPh" => in_phonetic = true,
+                Ok(Event::End(ref e)) if e.name() == b"rPh" => in_phonetic = false,
+                Ok(Event::Text(ref e)) if in_text && !in_phonetic => {
+                    let text = e.unescape_and_decode(reader).unwrap();
+                    if preserve_space {
+                        this_string.push_str(&text);
+                    } else {
+                        this_string.push_str(text.trim());
                     }
-                    Ok(Event::Eof) => break,
-                    Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
-                    _ => (),
                 }
-                buf.clear();
+                // the content of a CDATA section is taken literally, without trimming (quick-xml
+                // hands it over escaped, hence the unescaping)
+                Ok(Event::CData(ref e)) if in_text && !in_phonetic => {
+                    this_string.push_str(&e.unescape_and_decode(reader).unwrap());
+                }
+                Ok(Event::Eof) => {
+                    self.reader = None;
+                    return None;
+                }
+                Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Read the titles listed in the `<TitlesOfParts>` of the document properties (`docProps/app.xml`).
+/// Returns `None` if the xml cannot be read, or is bigger than the `limits` allow.
+fn titles_of_parts<R: Read>(app: R, limits: Limits) -> Option<Vec<String>> {
+    let mut reader = xml_reader(app);
+    reader.trim_text(true);
+    let mut buf = Vec::new();
+    let mut titles = vec![];
+    let mut in_titles = false;
+    loop {
+        let event = reader.read_event(&mut buf);
+        if !limits.allow_bytes(reader.buffer_position() as u64, "docProps/app.xml") {
+            return None;
+        }
+        match event {
+            Ok(Event::Start(ref e)) if e.name() == b"TitlesOfParts" => in_titles = true,
+            Ok(Event::End(ref e)) if e.name() == b"TitlesOfParts" => in_titles = false,
+            Ok(Event::Text(ref e)) if in_titles => {
+                titles.push(e.unescape_and_decode(&reader).ok()?);
             }
-            strings
+            Ok(Event::Eof) => break,
+            Err(_) => return None,
+            _ => (),
         }
-        Err(_) => strings,
+        buf.clear();
     }
+    Some(titles)
 }
 
-/// find the number of rows and columns used in a particular worksheet. takes the workbook xlsx
-/// location as its first parameter, and the location of the worksheet in question (within the zip)
-/// as the second parameter. Returns a tuple of (rows, columns) in the worksheet.
-fn find_styles<T>(xlsx: &mut ZipArchive<T>) -> Vec<String>
+/// The number formats, alignments and number format ids of the cell styles (see `find_styles`).
+type CellStyles = (Vec<String>, Vec<Alignment>, Vec<u32>);
+
+/// Find the number format, alignment and number format id of every entry in the `cellXfs`
+/// collection of the workbook's styles. All three vectors are indexed by the `s` attribute of a
+/// cell, so an entry is recorded for every `xf` even when its number format is unknown. Fails if
+/// the part is bigger than the `limits` allow.
+fn find_styles<T>(xlsx: &mut ZipArchive<T>, limits: Limits) -> Result<CellStyles, XlError>
 where
     T: Read + Seek,
 {
     let mut styles = Vec::new();
+    let mut alignments = Vec::new();
+    let mut format_ids = Vec::new();
     let mut number_formats = standard_styles();
     let styles_xml = match xlsx.by_name("xl/styles.xml") {
         Ok(s) => s,
-        Err(_) => return styles,
+        Err(_) => return Ok((styles, alignments, format_ids)),
     };
+    limits.check_bytes(styles_xml.size(), "xl/styles.xml")?;
     // let _ = std::io::copy(&mut styles_xml, &mut std::io::stdout());
-    let reader = BufReader::new(styles_xml);
-    let mut reader = Reader::from_reader(reader);
+    let mut reader = xml_reader(styles_xml);
     reader.trim_text(true);
     let mut buf = Vec::new();
     let mut record_styles = false;
     loop {
-        match reader.read_event(&mut buf) {
+        let event = reader.read_event(&mut buf);
+        limits.check_bytes(reader.buffer_position() as u64, "xl/styles.xml")?;
+        match event {
             Ok(Event::Empty(ref e)) if e.name() == b"numFmt" => {
                 let id = utils::get(e.attributes(), b"numFmtId").unwrap();
                 let code = utils::get(e.attributes(), b"formatCode").unwrap();
@@ -488,8 +1464,34 @@ where
                 if record_styles && e.name() == b"xf" =>
             {
                 let id = utils::get(e.attributes(), b"numFmtId").unwrap();
-                if number_formats.contains_key(&id) {
-                    styles.push(number_formats.get(&id).unwrap().to_string());
+                match number_formats.get(&id) {
+                    Some(format) => styles.push(format.to_string()),
+                    None => styles.push(String::new()),
+                }
+                format_ids.push(id.parse().unwrap_or(0));
+                alignments.push(Alignment::default());
+            }
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e))
+                if record_styles && e.name() == b"alignment" =>
+            {
+                if let Some(alignment) = alignments.last_mut() {
+                    e.attributes().for_each(|a| {
+                        let a = a.unwrap();
+                        match a.key {
+                            b"horizontal" => alignment.horizontal = Some(utils::attr_value(&a)),
+                            b"vertical" => alignment.vertical = Some(utils::attr_value(&a)),
+                            b"wrapText" => {
+                                let v = utils::attr_value(&a);
+                                alignment.wrap_text = v == "1" || v == "true";
+                            }
+                            b"textRotation" => {
+                                if let Ok(r) = utils::attr_value(&a).parse() {
+                                    alignment.text_rotation = r;
+                                }
+                            }
+                            _ => (),
+                        }
+                    });
                 }
             }
             Ok(Event::Eof) => break,
@@ -498,7 +1500,7 @@ where
         }
         buf.clear();
     }
-    styles
+    Ok((styles, alignments, format_ids))
 }
 
 /// Return hashmap of standard styles (ISO/IEC 29500:2011 in Part 1, section 18.8.30)
@@ -541,7 +1543,9 @@ fn standard_styles() -> HashMap<String, String> {
     styles
 }
 
-fn get_date_system<T>(xlsx: &mut ZipArchive<T>) -> DateSystem
+/// Read the date system from `xl/workbook.xml`. Fails if the part is bigger than the `limits`
+/// allow.
+fn get_date_system<T>(xlsx: &mut ZipArchive<T>, limits: Limits) -> Result<DateSystem, XlError>
 where
     T: Read + Seek,
 {
@@ -550,21 +1554,23 @@ where
 
     match xlsx.by_name("xl/workbook.xml") {
         Ok(wb) => {
-            let reader = BufReader::new(wb);
-            let mut reader = Reader::from_reader(reader);
+            limits.check_bytes(wb.size(), "xl/workbook.xml")?;
+            let mut reader = xml_reader(wb);
             reader.trim_text(true);
             let mut buf = Vec::new();
             loop {
-                match reader.read_event(&mut buf) {
+                let event = reader.read_event(&mut buf);
+                limits.check_bytes(reader.buffer_position() as u64, "xl/workbook.xml")?;
+                match event {
                     Ok(Event::Empty(ref e)) if e.name() == b"workbookPr" => {
                         if let Some(system) = utils::get(e.attributes(), b"date1904") {
                             if system == "1" {
-                                break DateSystem::V1904;
+                                break Ok(DateSystem::V1904);
                             }
                         }
-                        break DateSystem::V1900;
+                        break Ok(DateSystem::V1900);
                     }
-                    Ok(Event::Eof) => break DateSystem::V1900,
+                    Ok(Event::Eof) => break Ok(DateSystem::V1900),
                     Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
                     _ => (),
                 }
@@ -577,13 +1583,74 @@ where
 
 impl Workbook<Cursor<Vec<u8>>> {
     pub fn open(path: &str) -> Result<Self, String> {
-        let mut file = fs::File::open(path).unwrap();
+        Workbook::open_with(path, WorkbookOptions::default())
+    }
+
+    /// Open an existing workbook (xlsx file) using the given `options`. See `WorkbookOptions` for
+    /// what can be configured.
+    pub fn open_with(path: &str, options: WorkbookOptions) -> Result<Self, String> {
+        let mut file = fs::File::open(path).map_err(|e| e.to_string())?;
         let mut buff = vec![];
-        file.read_to_end(&mut buff).unwrap();
-        println!("Opened file: {}", path);
-        println!("File size: {} KB", buff.len() / 1024);
-        let inner = Cursor::new(buff);
-        Workbook::new(inner)
+        file.read_to_end(&mut buff).map_err(|e| e.to_string())?;
+        info!("Opened file: {}", path);
+        info!("File size: {} KB", buff.len() / 1024);
+        Workbook::from_reader_with(Cursor::new(buff), options)
+    }
+
+    /// Throw away everything that was read from the workbook and read it again from the bytes it
+    /// holds, as if it had just been opened: the zip's directory is parsed again and the shared
+    /// strings, styles and date system are reloaded (with the same `WorkbookOptions`). This lets
+    /// a tool that works through a workbook in several passes start each pass from a clean
+    /// state without keeping a second copy of the file around. Should the workbook not read again
+    /// (e.g., because the `WorkbookOptions` limits are exceeded), an error is returned and the
+    /// workbook is left empty: it has no sheets, shared strings or styles, and uses the 1900 date
+    /// system.
+    ///
+    /// # Example usage:
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let first_pass = wb.sheets().len();
+    ///     wb.reopen().unwrap();
+    ///     assert_eq!(wb.sheets().len(), first_pass);
+    pub fn reopen(&mut self) -> Result<(), XlError> {
+        self.rebuild(Cursor::new(EMPTY_ZIP.to_vec()))
+    }
+}
+
+/// The smallest valid zip file: an end of central directory record for an archive without any
+/// entries.
+const EMPTY_ZIP: [u8; 22] = *b"PK\x05\x06\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0";
+
+impl Workbook<Cursor<Arc<[u8]>>> {
+    /// Open a workbook from bytes that are already in memory and shared with other readers. Each
+    /// workbook created this way only holds a reference to `bytes`, so many independent workbooks
+    /// (e.g., one per thread) can be opened over the same file without copying it.
+    ///
+    /// # Example usage:
+    ///
+    ///     use std::sync::Arc;
+    ///     use xl::Workbook;
+    ///
+    ///     let bytes: Arc<[u8]> = std::fs::read("tests/data/Book1.xlsx").unwrap().into();
+    ///     let mut first = Workbook::from_shared(Arc::clone(&bytes)).unwrap();
+    ///     let mut second = Workbook::from_shared(bytes).unwrap();
+    ///     assert_eq!(first.sheets().len(), second.sheets().len());
+    pub fn from_shared(bytes: Arc<[u8]>) -> Result<Self, String> {
+        Workbook::from_shared_with(bytes, WorkbookOptions::default())
+    }
+
+    /// The same as `from_shared`, but using the given `options`.
+    pub fn from_shared_with(bytes: Arc<[u8]>, options: WorkbookOptions) -> Result<Self, String> {
+        Workbook::from_reader_with(Cursor::new(bytes), options)
+    }
+
+    /// The same as `reopen`, for workbooks created with `from_shared` or `from_shared_with`: read
+    /// the workbook again from the shared bytes, as if it had just been opened. The bytes are not
+    /// copied. If that fails, the workbook is left empty in the same way.
+    pub fn reopen(&mut self) -> Result<(), XlError> {
+        self.rebuild(Cursor::new(Arc::from(&EMPTY_ZIP[..])))
     }
 }
 
@@ -591,6 +1658,8 @@ impl Workbook<Cursor<Vec<u8>>> {
 mod tests {
     mod access {
         use super::super::*;
+        use crate::ExcelValue;
+        use chrono::NaiveDate;
 
         #[test]
         fn open_wb() {
@@ -604,6 +1673,464 @@ mod tests {
             assert_eq!(num_sheets, 4);
         }
 
+        #[test]
+        fn workbook_is_send() {
+            fn assert_send<T: Send>() {}
+            assert_send::<Workbook<fs::File>>();
+            assert_send::<Workbook<Cursor<Vec<u8>>>>();
+            assert_send::<Worksheet>();
+
+            let wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+            let handle = std::thread::spawn(move || {
+                let mut wb = wb;
+                let sheets = wb.sheets();
+                let ws = sheets.get("Sheet1").unwrap();
+                ws.rows(&mut wb).unwrap().count()
+            });
+            assert!(handle.join().unwrap() > 0);
+        }
+
+        #[test]
+        fn from_shared() {
+            let bytes: Arc<[u8]> = fs::read("tests/data/Book1.xlsx").unwrap().into();
+            let handles: Vec<_> = (0..2)
+                .map(|_| {
+                    let bytes = Arc::clone(&bytes);
+                    std::thread::spawn(move || {
+                        let mut wb = Workbook::from_shared(bytes).unwrap();
+                        let sheets = wb.sheets();
+                        let ws = sheets.get("Sheet1").unwrap();
+                        ws.read_to_buffer(&mut wb).unwrap()
+                    })
+                })
+                .collect();
+            let outputs: Vec<Vec<u8>> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+            let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+            let sheets = wb.sheets();
+            let expected = sheets
+                .get("Sheet1")
+                .unwrap()
+                .read_to_buffer(&mut wb)
+                .unwrap();
+            assert_eq!(outputs, vec![expected.clone(), expected]);
+        }
+
+        #[test]
+        fn sheet_order_is_tab_order() {
+            let mut wb = Workbook::open("tests/data/sheet_order.xlsx").unwrap();
+            let sheets = wb.sheets();
+            let names: Vec<&str> = (1..=sheets.len())
+                .map(|n| &sheets.get(n as usize).unwrap().name[..])
+                .collect();
+            assert_eq!(names, vec!["Third id", "First id", "Second id"]);
+            assert_eq!(sheets.get("First id").unwrap().position, 2);
+            let ws = sheets.get(1).unwrap();
+            let first = ws.rows(&mut wb).unwrap().next().unwrap();
+            assert_eq!(first[0].raw_value, "first tab");
+        }
+
+        #[test]
+        fn relative_and_absolute_targets() {
+            let mut wb = Workbook::open("tests/data/sheet_targets.xlsx").unwrap();
+            let sheets = wb.sheets();
+            for name in &["Relative", "Absolute"] {
+                let ws = sheets.get(*name).unwrap();
+                let first = ws.rows(&mut wb).unwrap().next().unwrap();
+                assert_eq!(first[0].raw_value, name.to_lowercase());
+            }
+        }
+
+        #[test]
+        fn contents() {
+            let mut wb = Workbook::open("tests/data/sheet_targets.xlsx").unwrap();
+            assert_eq!(
+                wb.contents(),
+                vec![
+                    "[Content_Types].xml",
+                    "_rels/.rels",
+                    "xl/workbook.xml",
+                    "xl/_rels/workbook.xml.rels",
+                    "xl/styles.xml",
+                    "xl/worksheets/sheet1.xml",
+                    "xl/worksheets/sheet2.xml",
+                ]
+            );
+        }
+
+        #[test]
+        fn no_sheets() {
+            let mut wb = Workbook::open("tests/data/no_sheets.xlsx").unwrap();
+            let sheets = wb.sheets();
+            assert_eq!(sheets.len(), 0);
+            assert!(sheets.is_empty());
+            assert!(sheets.by_name().is_empty());
+            assert!(sheets.get(0).is_none());
+            assert!(sheets.get(1).is_none());
+            assert!(sheets.get("x").is_none());
+            assert!(wb.worksheet_at(0).is_none());
+            // the workbook lists a sheet, but its relationship is missing, so it cannot be read
+            let mut wb = Workbook::open("tests/data/missing_sheet_rels.xlsx").unwrap();
+            let sheets = wb.sheets();
+            assert_eq!(sheets.by_name(), vec!["Found"]);
+            assert_eq!(sheets.get(1).unwrap().name, "Found");
+            assert!(sheets.get("Lost").is_none());
+        }
+
+        #[test]
+        fn lenient_xml() {
+            let options = WorkbookOptions::new().lenient_xml(true);
+            let mut wb = Workbook::open_with("tests/data/mismatched_tags.xlsx", options).unwrap();
+            assert_eq!(wb.shared_strings(), ["hello", "world"]);
+            let sheets = wb.sheets();
+            let ws = sheets.get(1).unwrap();
+            let values: Vec<String> = ws
+                .rows(&mut wb)
+                .unwrap()
+                .map(|row| row[0].value.to_string())
+                .collect();
+            assert_eq!(values, vec!["\"hello\"", "2", "\"world\""]);
+        }
+
+        #[test]
+        #[should_panic]
+        fn strict_xml() {
+            Workbook::open("tests/data/mismatched_tags.xlsx").unwrap();
+        }
+
+        #[test]
+        fn with_sheet_reader() {
+            let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+            let sheets = wb.sheets();
+            let ws = sheets.get("Sheet1").unwrap();
+            let dimension = wb
+                .with_sheet_reader(ws.target(), |reader| {
+                    let mut buf = Vec::new();
+                    loop {
+                        match reader.read_event(&mut buf) {
+                            Ok(Event::Empty(ref e)) if e.name() == b"dimension" => {
+                                break utils::get(e.attributes(), b"ref");
+                            }
+                            Ok(Event::Eof) | Err(_) => break None,
+                            _ => (),
+                        }
+                        buf.clear();
+                    }
+                })
+                .unwrap();
+            assert_eq!(dimension, Some("A1:R46".to_string()));
+            let missing = wb.with_sheet_reader("worksheets/nope.xml", |_| ());
+            assert!(
+                matches!(missing, Err(XlError::MissingPart(p)) if p == "xl/worksheets/nope.xml")
+            );
+        }
+
+        #[test]
+        fn part_info() {
+            let mut wb = Workbook::open("tests/data/stored.xlsx").unwrap();
+            let info = wb.part_info();
+            let names: Vec<_> = info.iter().map(|p| p.name.clone()).collect();
+            assert_eq!(names, wb.contents());
+            for part in &info {
+                assert_eq!(part.compression, "Stored");
+                assert_eq!(part.compressed_size, part.uncompressed_size);
+            }
+            let sheet = info
+                .iter()
+                .find(|p| p.name == "xl/worksheets/sheet1.xml")
+                .unwrap();
+            let mut xml = String::new();
+            wb.xls
+                .by_name("xl/worksheets/sheet1.xml")
+                .unwrap()
+                .read_to_string(&mut xml)
+                .unwrap();
+            assert_eq!(sheet.uncompressed_size, xml.len() as u64);
+        }
+
+        #[test]
+        fn reopen() {
+            let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+            let sheets = wb.sheets();
+            let ws = sheets.get("Sheet1").unwrap();
+            let first: Vec<_> = ws
+                .rows(&mut wb)
+                .unwrap()
+                .take(5)
+                .map(|r| r.to_string())
+                .collect();
+            let strings = wb.shared_strings().to_vec();
+            wb.reopen().unwrap();
+            assert_eq!(wb.shared_strings(), &strings[..]);
+            let second: Vec<_> = ws
+                .rows(&mut wb)
+                .unwrap()
+                .take(5)
+                .map(|r| r.to_string())
+                .collect();
+            assert_eq!(first, second);
+
+            let options = WorkbookOptions::new().recompute_dimensions(true);
+            let bytes: Arc<[u8]> = fs::read("tests/data/stale_dimension.xlsx").unwrap().into();
+            let mut wb = Workbook::from_shared_with(Arc::clone(&bytes), options).unwrap();
+            wb.reopen().unwrap();
+            // the bytes are still shared rather than copied, and the options are kept
+            assert_eq!(Arc::strong_count(&bytes), 2);
+            let sheets = wb.sheets();
+            assert_eq!(sheets.get(1).unwrap().rows(&mut wb).unwrap().count(), 4);
+
+            // a workbook that does not read again is left empty rather than half reset
+            let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+            wb.options = WorkbookOptions::new().max_decompressed_bytes(10);
+            assert!(wb.reopen().is_err());
+            assert!(wb.shared_strings().is_empty());
+            assert!(wb.sheets().is_empty());
+            assert!(wb.styles.is_empty());
+        }
+
+        #[test]
+        fn calc_settings() {
+            let mut wb = Workbook::open("tests/data/calc_settings.xlsx").unwrap();
+            assert_eq!(
+                wb.calc_settings(),
+                CalcSettings {
+                    calc_id: Some(191029),
+                    full_calc_on_load: true,
+                    iterate: true,
+                    iterate_count: 50,
+                }
+            );
+            let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+            let calc = wb.calc_settings();
+            assert_eq!(calc.calc_id, Some(162913));
+            assert!(!calc.full_calc_on_load);
+            assert!(!calc.iterate);
+            assert_eq!(calc.iterate_count, 100);
+            let mut wb = Workbook::open("tests/data/no_sheets.xlsx").unwrap();
+            assert_eq!(wb.calc_settings(), CalcSettings::default());
+        }
+
+        #[test]
+        fn app_sheet_names() {
+            let mut wb = Workbook::open("tests/data/app_props.xlsx").unwrap();
+            // the named range listed in app.xml is left out, and the sheet added to workbook.xml
+            // without updating app.xml shows up as a difference
+            assert_eq!(wb.app_sheet_names(), vec!["Data", "Q&A"]);
+            assert_eq!(wb.sheets().by_name(), vec!["Data", "Q&A", "Extra"]);
+
+            // the headings in app.xml are in the language of the Excel that wrote the file
+            let mut wb = Workbook::open("tests/data/app_props_de.xlsx").unwrap();
+            assert_eq!(wb.app_sheet_names(), vec!["Daten", "Q&A", "Übersicht"]);
+
+            // no app.xml, or one that cannot be read
+            let mut wb = Workbook::open("tests/data/hidden_sheets.xlsx").unwrap();
+            assert!(wb.app_sheet_names().is_empty());
+            let mut wb = Workbook::open("tests/data/broken_app_props.xlsx").unwrap();
+            assert!(wb.app_sheet_names().is_empty());
+        }
+
+        #[test]
+        fn validate_date_system() {
+            let mut wb = Workbook::open("tests/data/wrong_date_system.xlsx").unwrap();
+            assert_eq!(wb.date_system, DateSystem::V1900);
+            assert_eq!(wb.validate_date_system(), Some(DateSystem::V1904));
+            wb.date_system = DateSystem::V1904;
+            assert_eq!(wb.validate_date_system(), Some(DateSystem::V1904));
+            let sheets = wb.sheets();
+            let ws = sheets.get(1).unwrap();
+            let row2 = ws.rows(&mut wb).unwrap().nth(1).unwrap();
+            assert_eq!(
+                row2[0].value,
+                ExcelValue::Date(NaiveDate::from_ymd(1901, 4, 6))
+            );
+            // plausible dates keep the current system
+            let mut wb = Workbook::open("tests/data/dates.xlsx").unwrap();
+            assert_eq!(wb.validate_date_system(), Some(wb.date_system));
+            // nothing to go by
+            let mut wb = Workbook::open("tests/data/7_nulls.xlsx").unwrap();
+            assert_eq!(wb.validate_date_system(), None);
+            // only the first rows of a sheet are sampled
+            let mut wb = Workbook::open("tests/data/late_dates.xlsx").unwrap();
+            assert_eq!(wb.validate_date_system(), None);
+        }
+
+        #[test]
+        fn byte_order_marks() {
+            // the workbook, relationships, styles, shared strings and sheet all start with a BOM
+            let mut wb = Workbook::open("tests/data/bom.xlsx").unwrap();
+            assert!(matches!(wb.date_system, DateSystem::V1904));
+            assert_eq!(wb.shared_strings(), ["hello"]);
+            let sheets = wb.sheets();
+            let ws = sheets.get("Sheet1").unwrap();
+            let row = ws.rows(&mut wb).unwrap().next().unwrap();
+            assert_eq!(row[0].value, ExcelValue::String("hello".into()));
+            assert_eq!(
+                row[1].value,
+                ExcelValue::Date(NaiveDate::from_ymd(1904, 1, 2))
+            );
+        }
+
+        #[test]
+        fn worksheet_at() {
+            let mut wb = Workbook::open("tests/data/sheet_order.xlsx").unwrap();
+            let ws = wb.worksheet_at(1).unwrap();
+            assert_eq!(ws.name, "First id");
+            assert_eq!(ws.position, 2);
+            let rows: Vec<String> = ws.rows(&mut wb).unwrap().map(|r| r.to_string()).collect();
+            assert_eq!(rows, vec!["\"second tab\""]);
+            assert!(wb.worksheet_at(3).is_none());
+            assert!(wb.worksheet_at(usize::MAX).is_none());
+        }
+
+        #[test]
+        fn visible_sheets() {
+            let mut wb = Workbook::open("tests/data/hidden_sheets.xlsx").unwrap();
+            let sheets = wb.sheets();
+            let visibility: Vec<_> = (1..=sheets.len() as usize)
+                .map(|n| sheets.get(n).unwrap().visibility)
+                .collect();
+            assert_eq!(
+                visibility,
+                vec![
+                    SheetVisibility::Visible,
+                    SheetVisibility::Hidden,
+                    SheetVisibility::Visible,
+                    SheetVisibility::VeryHidden,
+                ]
+            );
+            let visible = wb.visible_sheets();
+            let names: Vec<_> = visible.iter().map(|ws| &ws.name[..]).collect();
+            assert_eq!(names, vec!["Data", "Summary"]);
+            assert_eq!(visible[1].position, 3);
+            let row1 = visible[1].rows(&mut wb).unwrap().next().unwrap();
+            assert_eq!(row1[0].raw_value, "summary");
+            let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+            assert_eq!(wb.visible_sheets().len(), 4);
+        }
+
+        #[test]
+        fn max_decompressed_bytes() {
+            // the shared strings are read when the workbook is opened
+            let options = WorkbookOptions::new().max_decompressed_bytes(100);
+            let err = Workbook::open_with("tests/data/Book1.xlsx", options).unwrap_err();
+            assert!(err.contains("xl/sharedStrings.xml"), "{}", err);
+            let options = WorkbookOptions::new().max_decompressed_bytes(1_000_000);
+            assert!(Workbook::open_with("tests/data/Book1.xlsx", options).is_ok());
+            // and so are the styles (3650 bytes here) and xl/workbook.xml (797 bytes in
+            // print_area.xlsx, whose styles are 590)
+            let options = WorkbookOptions::new().max_decompressed_bytes(1000);
+            let err = Workbook::open_with("tests/data/Book1.xlsx", options).unwrap_err();
+            assert!(err.contains("xl/styles.xml"), "{}", err);
+            let options = WorkbookOptions::new().max_decompressed_bytes(700);
+            let err = Workbook::open_with("tests/data/print_area.xlsx", options).unwrap_err();
+            assert!(err.contains("xl/workbook.xml"), "{}", err);
+            // the directory claims the styles are 1000 bytes, so they are only caught while reading
+            let options = WorkbookOptions::new().max_decompressed_bytes(10_000);
+            let err =
+                Workbook::open_with("tests/data/understated_styles.xlsx", options).unwrap_err();
+            assert!(err.contains("xl/styles.xml"), "{}", err);
+            assert!(Workbook::open("tests/data/understated_styles.xlsx").is_ok());
+            // parts read later stop at the limit, as if they ended there (the relationships of
+            // the workbook are 1254 bytes)
+            let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+            wb.options = WorkbookOptions::new().max_decompressed_bytes(1000);
+            assert!(wb.rels().is_empty());
+            assert!(wb.sheets().is_empty());
+            wb.options = WorkbookOptions::new().max_decompressed_bytes(1_000_000);
+            assert_eq!(wb.sheets().len(), 4);
+        }
+
+        #[test]
+        fn first_sheet() {
+            let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+            let ws = wb.first_sheet().unwrap();
+            assert_eq!(ws.position, 1);
+            let row1 = ws.rows(&mut wb).unwrap().next().unwrap();
+            assert_eq!(row1[0].value, ExcelValue::Number(1.0));
+            assert_eq!(row1[1].value, ExcelValue::Number(2.0));
+            // the left-most tab, even though its sheetId is 3
+            let mut wb = Workbook::open("tests/data/sheet_order.xlsx").unwrap();
+            assert_eq!(wb.first_sheet().unwrap().name, "Third id");
+            let mut wb = Workbook::open("tests/data/no_sheets.xlsx").unwrap();
+            assert!(wb.first_sheet().is_none());
+        }
+
+        #[test]
+        fn shared_strings() {
+            let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+            assert_eq!(
+                wb.shared_strings(),
+                &[
+                    "Test",
+                    "Merged",
+                    "Different styles in one cell",
+                    "Test with \r\nline breaks"
+                ]
+            );
+            // the table lines up with the raw values of string cells
+            let sheets = wb.sheets();
+            let ws = sheets.get("Sheet1").unwrap();
+            let strings = wb.shared_strings().to_vec();
+            for row in ws.rows(&mut wb).unwrap() {
+                for cell in row.0.iter().filter(|c| c.cell_type == "s") {
+                    let index: usize = cell.raw_value.parse().unwrap();
+                    assert_eq!(cell.value.to_string(), format!("\"{}\"", strings[index]));
+                }
+            }
+            let wb = Workbook::open("tests/data/sheet_order.xlsx").unwrap();
+            assert!(wb.shared_strings().is_empty());
+        }
+
+        #[test]
+        fn shared_string_escapes() {
+            let wb = Workbook::open("tests/data/string_escapes.xlsx").unwrap();
+            assert_eq!(
+                wb.shared_strings(),
+                [
+                    "Tom & Jerry",
+                    "a < b > c",
+                    "line1\nline2",
+                    "  padded  ",
+                    "\nleading newline",
+                    "<b>bold</b> & more",
+                    "Hello World",
+                    "quote \"x\" 'y'",
+                    "\u{1F600} emoji \u{e9}",
+                    "   ",
+                    "before & after",
+                    // whitespace around text is only kept with xml:space="preserve"
+                    "trimmed",
+                ]
+            );
+            let mut wb = Workbook::open("tests/data/string_escapes.xlsx").unwrap();
+            let streamed: Vec<String> = wb.shared_strings_iter().collect();
+            assert_eq!(streamed, wb.shared_strings());
+            let sheets = wb.sheets();
+            let ws = sheets.get(1).unwrap();
+            let row6 = ws.rows(&mut wb).unwrap().nth(5).unwrap();
+            assert_eq!(
+                row6[0].value,
+                ExcelValue::String("<b>bold</b> & more".into())
+            );
+        }
+
+        #[test]
+        fn shared_strings_iter() {
+            for file in &[
+                "Book1.xlsx",
+                "UPS.Galaxy.VS.PX.xlsx",
+                "empty_strings.xlsx",
+                "bom.xlsx",
+                "sheet_order.xlsx",
+            ] {
+                let mut wb = Workbook::open(&format!("tests/data/{}", file)).unwrap();
+                let expected = wb.shared_strings().to_vec();
+                let streamed: Vec<String> = wb.shared_strings_iter().collect();
+                assert_eq!(streamed, expected, "{}", file);
+                // every call starts over
+                assert_eq!(wb.shared_strings_iter().count(), expected.len());
+            }
+        }
+
         #[test]
         fn sheet_by_name_exists() {
             let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
@@ -639,12 +2166,69 @@ mod tests {
             assert_eq!(sheets.get("Time").unwrap().name, "Time");
         }
 
+        #[test]
+        fn sanitized_sheet_names() {
+            assert_eq!(sanitize_sheet_name("Sheet1"), "Sheet1");
+            assert_eq!(sanitize_sheet_name("Q1/Q2: \"final\""), "Q1_Q2_ _final_");
+            assert_eq!(sanitize_sheet_name("..."), "_");
+        }
+
+        #[test]
+        fn export_all_csv() {
+            let dir = std::env::temp_dir().join(format!("xl_export_{}", std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+            let paths = wb.export_all_csv(&dir).unwrap();
+            let names: Vec<_> = paths
+                .iter()
+                .map(|p| p.file_name().unwrap().to_str().unwrap())
+                .collect();
+            assert_eq!(
+                names,
+                vec!["Sheet1.csv", "Sheet2.csv", "Time.csv", "Sheet3.csv"]
+            );
+            let sheets = wb.sheets();
+            let expected = sheets
+                .get("Sheet1")
+                .unwrap()
+                .read_to_buffer(&mut wb)
+                .unwrap();
+            assert_eq!(fs::read(&paths[0]).unwrap(), expected);
+            fs::remove_dir_all(&dir).unwrap();
+        }
+
+        #[test]
+        fn export_all_csv_with() {
+            let dir = std::env::temp_dir().join(format!("xl_export_with_{}", std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+            let mut seen = vec![];
+            let paths = wb
+                .export_all_csv_with(&dir, |name| {
+                    seen.push(name.to_string());
+                    // every sheet gets the same name, so all but the first get their position
+                    "sheet".to_string()
+                })
+                .unwrap();
+            assert_eq!(seen, vec!["Sheet1", "Sheet2", "Time", "Sheet3"]);
+            let names: Vec<_> = paths
+                .iter()
+                .map(|p| p.file_name().unwrap().to_str().unwrap())
+                .collect();
+            assert_eq!(
+                names,
+                vec!["sheet.csv", "sheet_2.csv", "sheet_3.csv", "sheet_4.csv"]
+            );
+            assert!(paths.iter().all(|p| p.exists()));
+            fs::remove_dir_all(&dir).unwrap();
+        }
+
         #[test]
         fn inline_strings() {
             let mut wb = Workbook::open("tests/data/inlinestrings.xlsx").unwrap();
             let sheets = wb.sheets();
             let ws = sheets.get("Sheet Name").unwrap();
-            let row1 = ws.rows(&mut wb).next().unwrap();
+            let row1 = ws.rows(&mut wb).unwrap().next().unwrap();
             let v1 = &row1[0];
             assert_eq!(v1.to_string(), "\"Cell A1\"".to_string());
         }
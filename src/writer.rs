@@ -0,0 +1,496 @@
+//! This module implements a minimal xlsx writer. It is meant for simple data export (e.g., writing
+//! the results of processing a big workbook back out): values are written as they are, with no
+//! styling beyond what is needed to make dates show up as dates in Excel.
+
+use crate::error::XlError;
+use crate::utils;
+use crate::ExcelValue;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, BufWriter, Seek, Write};
+use std::path::Path;
+use zip::result::ZipError;
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+/// The index of the cell format (in `styles.xml`) used for each kind of value.
+const DATE_STYLE: u8 = 1;
+const DATETIME_STYLE: u8 = 2;
+const TIME_STYLE: u8 = 3;
+
+const XML_HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n";
+const MAIN_NS: &str = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+const REL_NS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+
+/// `WorkbookWriter` builds a new xlsx file from rows of `ExcelValue`s. Sheets are added with
+/// `add_sheet` and filled (top to bottom) with `write_row`; nothing is written to disk until you
+/// call `save` (or `write_to`). Strings are stored in the shared string table, so repeated text
+/// is only stored once. Dates, times and datetimes are written as Excel serial numbers (using the
+/// 1900 date system) with a date format, so they read back as dates.
+///
+/// # Example usage
+///
+///     use std::io::Cursor;
+///     use xl::{ExcelValue, Workbook, WorkbookWriter};
+///
+///     let mut writer = WorkbookWriter::new();
+///     let sheet = writer.add_sheet("Results");
+///     writer.write_row(sheet, &[ExcelValue::String("total".into()), ExcelValue::Number(42.0)]);
+///     let mut out = Cursor::new(vec![]);
+///     writer.write_to(&mut out).unwrap();
+///
+///     let mut wb = Workbook::new(Cursor::new(out.into_inner())).unwrap();
+///     let sheets = wb.sheets();
+///     let ws = sheets.get("Results").unwrap();
+///     let row = ws.rows(&mut wb).unwrap().next().unwrap();
+///     assert_eq!(row[1].value, ExcelValue::Number(42.0));
+#[derive(Debug, Default)]
+pub struct WorkbookWriter {
+    sheets: Vec<SheetData>,
+    strings: Vec<String>,
+    string_index: HashMap<String, usize>,
+    /// the number of cells that refer to the shared string table
+    string_refs: usize,
+}
+
+/// The name and (already serialized) rows of a sheet that is being written.
+#[derive(Debug)]
+struct SheetData {
+    name: String,
+    rows: String,
+    num_rows: u32,
+    num_cols: u16,
+}
+
+impl WorkbookWriter {
+    /// Create a writer for a workbook without any sheets.
+    pub fn new() -> Self {
+        WorkbookWriter::default()
+    }
+
+    /// Add an (empty) sheet called `name` after any sheets added before it. Returns the index of
+    /// the sheet, which is what you pass to `write_row`. Excel requires sheet names to be unique
+    /// and at most 31 characters long; it is up to you to respect that.
+    pub fn add_sheet(&mut self, name: &str) -> usize {
+        self.sheets.push(SheetData {
+            name: name.to_string(),
+            rows: String::new(),
+            num_rows: 0,
+            num_cols: 0,
+        });
+        self.sheets.len() - 1
+    }
+
+    /// Append a row to the sheet at index `sheet` (as returned by `add_sheet`). The first row
+    /// written goes in row 1, the next one in row 2, and so on; the first value of `row` goes in
+    /// column A. `ExcelValue::None` leaves a cell empty. Numbers that Excel cannot store (NaN and
+    /// infinity) are written as `#NUM!` errors. Panics if there is no sheet at index `sheet`, or
+    /// if the row has more columns than Excel allows.
+    pub fn write_row(&mut self, sheet: usize, row: &[ExcelValue]) {
+        let num_sheets = self.sheets.len();
+        let row_num = match self.sheets.get(sheet) {
+            Some(data) => data.num_rows + 1,
+            None => panic!("sheet {} does not exist ({} sheets)", sheet, num_sheets),
+        };
+        let mut xml = format!("<row r=\"{}\">", row_num);
+        for (i, value) in row.iter().enumerate() {
+            let reference = utils::make_reference(i as u16 + 1, row_num);
+            match value {
+                ExcelValue::None => continue,
+                ExcelValue::Bool(b) => {
+                    let _ = write!(
+                        xml,
+                        "<c r=\"{}\" t=\"b\"><v>{}</v></c>",
+                        reference, *b as u8
+                    );
+                }
+                ExcelValue::Number(n) if n.is_finite() => {
+                    let _ = write!(xml, "<c r=\"{}\"><v>{}</v></c>", reference, n);
+                }
+                ExcelValue::Number(_) => {
+                    let _ = write!(xml, "<c r=\"{}\" t=\"e\"><v>#NUM!</v></c>", reference);
+                }
+                ExcelValue::String(s) => {
+                    let index = self.string_index(s);
+                    let _ = write!(xml, "<c r=\"{}\" t=\"s\"><v>{}</v></c>", reference, index);
+                }
+                ExcelValue::Error(e) => {
+                    let hash = if e.starts_with('#') { "" } else { "#" };
+                    let _ = write!(
+                        xml,
+                        "<c r=\"{}\" t=\"e\"><v>{}{}</v></c>",
+                        reference,
+                        hash,
+                        escape(e)
+                    );
+                }
+                ExcelValue::Date(d) => {
+                    let number = utils::date_to_excel_number(d.and_hms(0, 0, 0));
+                    let _ = write!(
+                        xml,
+                        "<c r=\"{}\" s=\"{}\"><v>{}</v></c>",
+                        reference, DATE_STYLE, number
+                    );
+                }
+                ExcelValue::DateTime(d) => {
+                    let number = utils::date_to_excel_number(*d);
+                    let _ = write!(
+                        xml,
+                        "<c r=\"{}\" s=\"{}\"><v>{}</v></c>",
+                        reference, DATETIME_STYLE, number
+                    );
+                }
+                ExcelValue::Time(t) => {
+                    let seconds = t.signed_duration_since(chrono::NaiveTime::from_hms(0, 0, 0));
+                    let number = seconds.num_milliseconds() as f64 / 86_400_000.0;
+                    let _ = write!(
+                        xml,
+                        "<c r=\"{}\" s=\"{}\"><v>{}</v></c>",
+                        reference, TIME_STYLE, number
+                    );
+                }
+            }
+        }
+        xml.push_str("</row>");
+        let data = &mut self.sheets[sheet];
+        data.rows.push_str(&xml);
+        data.num_rows = row_num;
+        data.num_cols = data.num_cols.max(row.len() as u16);
+    }
+
+    /// Write the workbook to a new file at `path` (replacing it if it exists).
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), XlError> {
+        let file = fs::File::create(path)?;
+        self.write_to(BufWriter::new(file))
+    }
+
+    /// Write the workbook (as a zipped xlsx) to `out`.
+    pub fn write_to<W: Write + Seek>(&self, out: W) -> Result<(), XlError> {
+        let mut zip = ZipWriter::new(out);
+        let options = FileOptions::default();
+        let mut add = |name: &str, contents: &str| -> Result<(), XlError> {
+            zip.start_file(name, options).map_err(zip_error)?;
+            zip.write_all(contents.as_bytes())?;
+            Ok(())
+        };
+        add("[Content_Types].xml", &self.content_types())?;
+        add("_rels/.rels", ROOT_RELS)?;
+        add("xl/workbook.xml", &self.workbook_xml())?;
+        add("xl/_rels/workbook.xml.rels", &self.workbook_rels())?;
+        add("xl/styles.xml", STYLES)?;
+        add("xl/sharedStrings.xml", &self.shared_strings_xml())?;
+        for (i, sheet) in self.sheets.iter().enumerate() {
+            let name = format!("xl/worksheets/sheet{}.xml", i + 1);
+            add(&name, &sheet_xml(sheet))?;
+        }
+        zip.finish().map_err(zip_error)?;
+        Ok(())
+    }
+
+    /// Return the index of `s` in the shared string table, adding it if it is not there yet.
+    fn string_index(&mut self, s: &str) -> usize {
+        self.string_refs += 1;
+        if let Some(&index) = self.string_index.get(s) {
+            return index;
+        }
+        let index = self.strings.len();
+        self.strings.push(s.to_string());
+        self.string_index.insert(s.to_string(), index);
+        index
+    }
+
+    fn content_types(&self) -> String {
+        let mut xml = String::from(XML_HEADER);
+        xml.push_str(concat!(
+            "<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">",
+            "<Default Extension=\"rels\" ",
+            "ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>",
+            "<Default Extension=\"xml\" ContentType=\"application/xml\"/>",
+            "<Override PartName=\"/xl/workbook.xml\" ContentType=\"application/",
+            "vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml\"/>",
+            "<Override PartName=\"/xl/styles.xml\" ContentType=\"application/",
+            "vnd.openxmlformats-officedocument.spreadsheetml.styles+xml\"/>",
+            "<Override PartName=\"/xl/sharedStrings.xml\" ContentType=\"application/",
+            "vnd.openxmlformats-officedocument.spreadsheetml.sharedStrings+xml\"/>",
+        ));
+        for i in 1..=self.sheets.len() {
+            let _ = write!(
+                xml,
+                concat!(
+                    "<Override PartName=\"/xl/worksheets/sheet{}.xml\" ContentType=\"application/",
+                    "vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml\"/>"
+                ),
+                i
+            );
+        }
+        xml.push_str("</Types>");
+        xml
+    }
+
+    fn workbook_xml(&self) -> String {
+        let mut xml = format!(
+            "{}<workbook xmlns=\"{}\" xmlns:r=\"{}\"><sheets>",
+            XML_HEADER, MAIN_NS, REL_NS
+        );
+        for (i, sheet) in self.sheets.iter().enumerate() {
+            let _ = write!(
+                xml,
+                "<sheet name=\"{}\" sheetId=\"{}\" r:id=\"rId{}\"/>",
+                escape(&sheet.name),
+                i + 1,
+                i + 1
+            );
+        }
+        xml.push_str("</sheets></workbook>");
+        xml
+    }
+
+    fn workbook_rels(&self) -> String {
+        let mut xml = format!(
+            "{}<Relationships xmlns=\"{}\">",
+            XML_HEADER, "http://schemas.openxmlformats.org/package/2006/relationships"
+        );
+        let n = self.sheets.len();
+        for i in 1..=n {
+            let _ = write!(
+                xml,
+                "<Relationship Id=\"rId{}\" Type=\"{}/worksheet\" Target=\"worksheets/sheet{}.xml\"/>",
+                i, REL_NS, i
+            );
+        }
+        let _ = write!(
+            xml,
+            "<Relationship Id=\"rId{}\" Type=\"{}/styles\" Target=\"styles.xml\"/>",
+            n + 1,
+            REL_NS
+        );
+        let _ = write!(
+            xml,
+            "<Relationship Id=\"rId{}\" Type=\"{}/sharedStrings\" Target=\"sharedStrings.xml\"/>",
+            n + 2,
+            REL_NS
+        );
+        xml.push_str("</Relationships>");
+        xml
+    }
+
+    fn shared_strings_xml(&self) -> String {
+        let mut xml = format!(
+            "{}<sst xmlns=\"{}\" count=\"{}\" uniqueCount=\"{}\">",
+            XML_HEADER,
+            MAIN_NS,
+            self.string_refs,
+            self.strings.len()
+        );
+        for s in &self.strings {
+            // leading and trailing whitespace is only kept if we ask for it
+            if s.trim() != s {
+                let _ = write!(xml, "<si><t xml:space=\"preserve\">{}</t></si>", escape(s));
+            } else {
+                let _ = write!(xml, "<si><t>{}</t></si>", escape(s));
+            }
+        }
+        xml.push_str("</sst>");
+        xml
+    }
+}
+
+/// Serialize a whole worksheet, including its dimension.
+fn sheet_xml(sheet: &SheetData) -> String {
+    let dimension = if sheet.num_rows == 0 || sheet.num_cols == 0 {
+        "A1".to_string()
+    } else {
+        format!(
+            "A1:{}",
+            utils::make_reference(sheet.num_cols, sheet.num_rows)
+        )
+    };
+    format!(
+        "{}<worksheet xmlns=\"{}\" xmlns:r=\"{}\"><dimension ref=\"{}\"/><sheetData>{}</sheetData></worksheet>",
+        XML_HEADER, MAIN_NS, REL_NS, dimension, sheet.rows
+    )
+}
+
+/// Escape the characters that are special in xml text and attribute values. Control characters
+/// that xml does not allow at all are encoded the way Excel does it (e.g., `_x0001_`), and so is
+/// the `_` of any text that would otherwise be read as such an escape (`_x0041_` becomes
+/// `_x005F_x0041_`).
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for (i, c) in s.char_indices() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' => {
+                let _ = write!(escaped, "_x{:04X}_", c as u32);
+            }
+            '_' if looks_escaped(&s[i..]) => escaped.push_str("_x005F_"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Does `s` start with something that reads as an escaped character (`_x` followed by four hex
+/// digits and `_`)?
+fn looks_escaped(s: &str) -> bool {
+    let s = s.as_bytes();
+    s.len() >= 7
+        && s.starts_with(b"_x")
+        && s[2..6].iter().all(u8::is_ascii_hexdigit)
+        && s[6] == b'_'
+}
+
+// `io::Error::other` needs a newer compiler than the rest of the crate
+#[allow(clippy::io_other_error)]
+fn zip_error(e: ZipError) -> XlError {
+    match e {
+        ZipError::Io(e) => XlError::Io(e),
+        e => XlError::Io(io::Error::new(io::ErrorKind::Other, e)),
+    }
+}
+
+const ROOT_RELS: &str = concat!(
+    "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n",
+    "<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">",
+    "<Relationship Id=\"rId1\" ",
+    "Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument\" ",
+    "Target=\"xl/workbook.xml\"/></Relationships>"
+);
+
+/// The styles of a new workbook: the default format plus one format each for dates, datetimes
+/// and times (built-in number formats 14, 22 and 21).
+const STYLES: &str = concat!(
+    "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n",
+    "<styleSheet xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\">",
+    "<fonts count=\"1\"><font><sz val=\"11\"/><name val=\"Calibri\"/></font></fonts>",
+    "<fills count=\"2\"><fill><patternFill patternType=\"none\"/></fill>",
+    "<fill><patternFill patternType=\"gray125\"/></fill></fills>",
+    "<borders count=\"1\"><border><left/><right/><top/><bottom/><diagonal/></border></borders>",
+    "<cellStyleXfs count=\"1\"><xf numFmtId=\"0\" fontId=\"0\" fillId=\"0\" borderId=\"0\"/></cellStyleXfs>",
+    "<cellXfs count=\"4\">",
+    "<xf numFmtId=\"0\" fontId=\"0\" fillId=\"0\" borderId=\"0\" xfId=\"0\"/>",
+    "<xf numFmtId=\"14\" fontId=\"0\" fillId=\"0\" borderId=\"0\" xfId=\"0\" applyNumberFormat=\"1\"/>",
+    "<xf numFmtId=\"22\" fontId=\"0\" fillId=\"0\" borderId=\"0\" xfId=\"0\" applyNumberFormat=\"1\"/>",
+    "<xf numFmtId=\"21\" fontId=\"0\" fillId=\"0\" borderId=\"0\" xfId=\"0\" applyNumberFormat=\"1\"/>",
+    "</cellXfs>",
+    "<cellStyles count=\"1\"><cellStyle name=\"Normal\" xfId=\"0\" builtinId=\"0\"/></cellStyles>",
+    "</styleSheet>"
+);
+
+#[cfg(test)]
+mod tests {
+    use crate::{ExcelValue, Workbook, WorkbookWriter};
+    use chrono::{NaiveDate, NaiveTime};
+    use std::borrow::Cow;
+    use std::io::Cursor;
+
+    #[test]
+    fn round_trip() {
+        let date = NaiveDate::from_ymd(2021, 3, 14);
+        let datetime = date.and_hms(15, 9, 26);
+        let time = NaiveTime::from_hms(6, 30, 0);
+        let mut writer = WorkbookWriter::new();
+        let first = writer.add_sheet("First & <only>");
+        let second = writer.add_sheet("Second");
+        writer.write_row(
+            first,
+            &[
+                ExcelValue::String(Cow::Borrowed("name")),
+                ExcelValue::String(Cow::Borrowed("  padded ")),
+                ExcelValue::None,
+                ExcelValue::Bool(true),
+            ],
+        );
+        writer.write_row(
+            first,
+            &[
+                ExcelValue::Number(1.5),
+                ExcelValue::Date(date),
+                ExcelValue::DateTime(datetime),
+                ExcelValue::Time(time),
+                ExcelValue::Error("#N/A".to_string()),
+                ExcelValue::Number(f64::NAN),
+            ],
+        );
+        writer.write_row(second, &[ExcelValue::String(Cow::Borrowed("name"))]);
+        let mut out = Cursor::new(vec![]);
+        writer.write_to(&mut out).unwrap();
+
+        let mut wb = Workbook::new(Cursor::new(out.into_inner())).unwrap();
+        // "name" is only stored once
+        assert_eq!(wb.shared_strings(), ["name", "  padded "]);
+        let sheets = wb.sheets();
+        assert_eq!(sheets.len(), 2);
+        let ws = sheets.get("First & <only>").unwrap();
+        let rows = ws.to_vec(&mut wb).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].0.len(), 6);
+        assert_eq!(
+            rows[0][1].value,
+            ExcelValue::String(Cow::Borrowed("  padded "))
+        );
+        assert_eq!(rows[0][2].value, ExcelValue::None);
+        assert_eq!(rows[0][3].value, ExcelValue::Bool(true));
+        assert_eq!(rows[1][0].value, ExcelValue::Number(1.5));
+        assert_eq!(rows[1][1].value, ExcelValue::Date(date));
+        assert_eq!(rows[1][2].value, ExcelValue::DateTime(datetime));
+        assert_eq!(rows[1][3].value, ExcelValue::Time(time));
+        assert_eq!(rows[1][4].value, ExcelValue::Error("#N/A".to_string()));
+        assert_eq!(rows[1][5].value, ExcelValue::Error("#NUM!".to_string()));
+        let ws = sheets.get(2).unwrap();
+        let row = ws.rows(&mut wb).unwrap().next().unwrap();
+        assert_eq!(row[0].value, ExcelValue::String(Cow::Borrowed("name")));
+    }
+
+    #[test]
+    fn control_characters() {
+        let mut writer = WorkbookWriter::new();
+        let sheet = writer.add_sheet("Sheet1");
+        writer.write_row(
+            sheet,
+            &[
+                ExcelValue::String(Cow::Borrowed("bell\u{7}\ttab")),
+                ExcelValue::String(Cow::Borrowed("_x0041_ and max_x")),
+                ExcelValue::String(Cow::Borrowed("bell\u{7}\ttab")),
+            ],
+        );
+        let mut out = Cursor::new(vec![]);
+        writer.write_to(&mut out).unwrap();
+
+        // the escapes are not decoded when reading, so they show up as written
+        let mut wb = Workbook::new(Cursor::new(out.into_inner())).unwrap();
+        assert_eq!(
+            wb.shared_strings(),
+            ["bell_x0007_\ttab", "_x005F_x0041_ and max_x"]
+        );
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        assert_eq!(ws.to_vec(&mut wb).unwrap()[0].0.len(), 3);
+    }
+
+    #[test]
+    fn shared_string_counts() {
+        let mut writer = WorkbookWriter::new();
+        let sheet = writer.add_sheet("Sheet1");
+        let name = ExcelValue::String(Cow::Borrowed("name"));
+        writer.write_row(
+            sheet,
+            &[name.clone(), name.clone(), ExcelValue::Number(1.0)],
+        );
+        writer.write_row(sheet, &[name, ExcelValue::String(Cow::Borrowed("other"))]);
+        let xml = writer.shared_strings_xml();
+        assert!(xml.contains("count=\"4\" uniqueCount=\"2\""));
+    }
+
+    #[test]
+    #[should_panic]
+    fn missing_sheet() {
+        let mut writer = WorkbookWriter::new();
+        writer.write_row(0, &[ExcelValue::Bool(false)]);
+    }
+}
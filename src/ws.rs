@@ -1,21 +1,33 @@
 //! This module implements all the functionality specific to Excel worksheets. This mostly means
 
+use crate::error::XlError;
 use crate::utils;
 
-use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
-use quick_xml::events::Event;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use log::warn;
+use quick_xml::events::{BytesStart, Event};
 use quick_xml::Reader;
 use std::borrow::Cow;
 use std::cmp;
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::fmt;
-use std::io::BufReader;
-use std::io::Read;
+use std::fmt::Write as _;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, BufReader};
+use std::io::{Read, Write};
 use std::io::Seek;
+use std::iter::FusedIterator;
 use std::mem;
 use std::ops::Index;
+use std::str::FromStr;
 use zip::read::ZipFile;
 // use quick_xml::events::attributes::Attribute;
-use crate::wb::{DateSystem, Workbook};
+use crate::wb::{part_name, Alignment, DateSystem, Limits, Workbook};
+
+/// How many rows are written between calls to the progress callback of
+/// `Worksheet::write_csv_with_progress`.
+pub const PROGRESS_INTERVAL: u64 = 1000;
 
 /// The `SheetReader` is used in a `RowIter` to navigate a worksheet. It contains a pointer to the
 /// worksheet `ZipFile` in the xlsx file, the list of strings used in the workbook, the styles used
@@ -26,6 +38,8 @@ pub struct SheetReader<'a> {
     reader: Reader<BufReader<ZipFile<'a>>>,
     strings: &'a [String],
     styles: &'a [String],
+    alignments: &'a [Alignment],
+    format_ids: &'a [u32],
     date_system: &'a DateSystem,
 }
 
@@ -42,6 +56,10 @@ impl<'a> SheetReader<'a> {
     /// - The `styles` are used to determine the data type (primarily for dates). While each cell
     ///   has a 'cell type,' dates are a little trickier to get right. So we use the style
     ///   information when we can.
+    /// - The `alignments` are the alignment settings of each style (indexed the same way as the
+    ///   `styles`).
+    /// - The `format_ids` are the ids of the number format of each style (again indexed the same
+    ///   way as the `styles`), which tell built-in formats apart from custom ones.
     /// - Lastly, the `date_system` is used to determine what date we are looking at for cells that
     ///   contain date values. See the documentation for the `DateSystem` enum for more
     ///   information.
@@ -49,12 +67,16 @@ impl<'a> SheetReader<'a> {
         reader: Reader<BufReader<ZipFile<'a>>>,
         strings: &'a [String],
         styles: &'a [String],
+        alignments: &'a [Alignment],
+        format_ids: &'a [u32],
         date_system: &'a DateSystem,
     ) -> SheetReader<'a> {
         SheetReader {
             reader,
             strings,
             styles,
+            alignments,
+            format_ids,
             date_system,
         }
     }
@@ -74,20 +96,61 @@ fn used_area(used_area_range: &str) -> (u32, u16) {
     if end == -1 {
         (0, 0)
     } else {
-        let end_range = &used_area_range[end as usize..];
-        let mut end = 0;
-        // note, the extra '1' (in various spots below) is to deal with the ':' part of the
-        // range
-        for (i, c) in end_range[1..].chars().enumerate() {
-            if !c.is_ascii_alphabetic() {
-                end = i + 1;
-                break;
+        // a range that is not valid (e.g., one that goes past column XFD) is treated as if it was
+        // not there at all
+        match utils::parse_reference(&used_area_range[end as usize + 1..]) {
+            Some((col, row)) => (row, col),
+            None => (0, 0),
+        }
+    }
+}
+
+/// Return the (rows, columns) of the used area recorded in a `<dimension>` element. Returns `None`
+/// if the element is missing its range or the range is just "A1" (which Excel writes for empty
+/// sheets and which is often not accurate anyway).
+fn dimension(e: &BytesStart) -> Option<(u32, u16)> {
+    match utils::get(e.attributes(), b"ref") {
+        Some(used_area_range) if used_area_range != "A1" => Some(used_area(&used_area_range)),
+        _ => None,
+    }
+}
+
+/// Compute the (rows, columns) of the used area by scanning every row and cell in the sheet rather
+/// than trusting the `<dimension>` element (which may be stale). This reads the entire sheet, so it
+/// is only used when `WorkbookOptions::recompute_dimensions` is turned on.
+fn computed_used_area<R: BufRead>(reader: &mut Reader<R>, limits: Limits) -> (u32, u16) {
+    // only the references matter here, so an end tag that does not match is no reason to stop
+    reader.check_end_names(false);
+    let mut buf = Vec::new();
+    let (mut rows, mut cols) = (0, 0);
+    loop {
+        let event = reader.read_event(&mut buf);
+        // the pass that reads the cells stops at the same point and reports the error
+        if limits.too_many_bytes(reader.buffer_position() as u64) {
+            break;
+        }
+        match event {
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) if e.name() == b"row" => {
+                // a row number that cannot be read is left to the pass that reads the cells
+                if let Some(r) = utils::get(e.attributes(), b"r").and_then(|r| r.parse().ok()) {
+                    rows = cmp::max(rows, r);
+                }
             }
+            Ok(Event::Start(ref e)) if e.name() == b"c" => {
+                let r = utils::get(e.attributes(), b"r");
+                if let Some((col, row)) = r.and_then(|r| utils::parse_reference(&r)) {
+                    cols = cmp::max(cols, col);
+                    rows = cmp::max(rows, row);
+                }
+            }
+            // the parser cannot continue after an error, which the pass that reads the cells runs
+            // into as well (and either reports or panics on)
+            Ok(Event::Eof) | Err(_) => break,
+            _ => (),
         }
-        let col = utils::col2num(&end_range[1..end]).unwrap();
-        let row: u32 = end_range[end..].parse().unwrap();
-        (row, col)
+        buf.clear();
     }
+    (rows, cols)
 }
 
 /// The Worksheet is the primary object in this module since this is where most of the valuable
@@ -95,11 +158,41 @@ fn used_area(used_area_range: &str) -> (u32, u16) {
 #[derive(Debug)]
 pub struct Worksheet {
     pub name: String,
+    /// 1-based position of the sheet's tab in Excel, which is the order the sheets appear in
+    /// `xl/workbook.xml` (and not necessarily the order of their `sheetId`s)
     pub position: u8,
+    #[allow(dead_code)]
     relationship_id: String,
     /// location where we can find this worksheet in its xlsx file
     target: String,
+    #[allow(dead_code)]
     sheet_id: u8,
+    /// whether this is a regular worksheet or a chartsheet
+    pub kind: SheetKind,
+    /// whether the sheet's tab is shown in Excel
+    pub visibility: SheetVisibility,
+}
+
+/// The kinds of sheets a workbook can contain. Chartsheets hold a single chart and no cells, so
+/// iterating over their rows produces nothing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SheetKind {
+    /// a regular sheet with cells
+    Worksheet,
+    /// a sheet that only holds a chart
+    Chart,
+}
+
+/// Whether a sheet's tab is shown in Excel, from the `state` attribute of the sheet in
+/// `xl/workbook.xml`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SheetVisibility {
+    /// the tab is shown (the default)
+    Visible,
+    /// the tab is hidden, but can be unhidden from Excel's menus
+    Hidden,
+    /// the tab is hidden and can only be unhidden from VBA (typically used for helper sheets)
+    VeryHidden,
 }
 
 impl Worksheet {
@@ -125,14 +218,39 @@ impl Worksheet {
             relationship_id,
             target,
             sheet_id,
+            kind: SheetKind::Worksheet,
+            visibility: SheetVisibility::Visible,
         }
     }
 
+    /// The location of this sheet's xml in the xlsx zip, as recorded in the workbook's
+    /// relationships (e.g., `worksheets/sheet1.xml`). This is what `Workbook::sheet_reader` and
+    /// `Workbook::with_sheet_reader` expect.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     assert_eq!(ws.target(), "worksheets/sheet1.xml");
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
     /// Obtain a `RowIter` for this worksheet (that is in `workbook`). This is, arguably, the main
     /// part of the library. You use this method to iterate through all the values in this sheet.
     /// The simplest thing you can do is print the values out (which is what `xlcat` does), but you
     /// could do more if you wanted.
     ///
+    /// Every call starts a new pass over the sheet with a fresh reader, so it is fine to call this
+    /// more than once (e.g., once to look at the headers and again to stream the data). Note that
+    /// each pass decompresses the worksheet from the archive again; nothing is cached between
+    /// passes. If you need the data many times, read it once with `to_vec`.
+    ///
+    /// Chartsheets (see `SheetKind`) have no cells, so their iterator is always empty.
+    ///
     /// # Example usage
     ///
     ///     use xl::{Workbook, Worksheet, ExcelValue};
@@ -140,183 +258,392 @@ impl Worksheet {
     ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
     ///     let sheets = wb.sheets();
     ///     let ws = sheets.get("Sheet1").unwrap();
-    ///     let mut rows = ws.rows(&mut wb);
+    ///     let mut rows = ws.rows(&mut wb).unwrap();
     ///     let row1 = rows.next().unwrap();
     ///     assert_eq!(row1[0].raw_value, "1");
     ///     assert_eq!(row1[1].value, ExcelValue::Number(2f64));
-    pub fn rows<'a, T>(&self, workbook: &'a mut Workbook<T>) -> RowIter<'a>
+    pub fn rows<'a, T>(&self, workbook: &'a mut Workbook<T>) -> Result<RowIter<'a>, XlError>
     where
         T: Read + Seek,
     {
-        let reader = workbook.sheet_reader(&self.target);
-        RowIter {
+        let recompute_dimensions = workbook.options().recompute_dimensions;
+        let limits = workbook.options().limits;
+        let is_chart = self.kind == SheetKind::Chart;
+        let (num_rows, num_cols) = if recompute_dimensions && !is_chart {
+            computed_used_area(&mut workbook.sheet_reader(&self.target)?.reader, limits)
+        } else {
+            (0, 0)
+        };
+        let reader = workbook.sheet_reader(&self.target)?;
+        Ok(RowIter {
             worksheet_reader: reader,
             want_row: 1,
             next_row: None,
-            num_cols: 0,
-            num_rows: 0,
-            done_file: false,
+            num_cols,
+            num_rows,
+            // there is nothing to read in a chartsheet
+            done_file: is_chart,
+            pending_row: None,
+            trust_dimension: !recompute_dimensions,
+            shared_formulas: HashMap::new(),
+            strict_dimension: false,
+            raw_strings: false,
+            min_columns: 0,
+            row_attributes: false,
+            text_as_strings: false,
+            trailing_fill: true,
+            part: part_name(&self.target),
+            limits,
+            cells_read: 0,
+            error: None,
+            recover_errors: false,
+        })
+    }
+
+    /// Like `rows`, but the iterator starts at row number `start` (1-based) instead of the first
+    /// row, which is handy for resuming an import from a checkpoint. Earlier rows are
+    /// fast-forwarded through in the xml without being built, so they cost little more than the
+    /// decompression. A `start` of 0 is treated like 1.
+    ///
+    /// Note that shared formulas defined in the skipped rows are not seen, so the formulas of
+    /// cells that refer back to them are returned as empty strings.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let row5 = ws.rows_from(&mut wb, 5).unwrap().next().unwrap();
+    ///     assert_eq!(row5.1, 5);
+    pub fn rows_from<'a, T>(
+        &self,
+        workbook: &'a mut Workbook<T>,
+        start: u32,
+    ) -> Result<RowIter<'a>, XlError>
+    where
+        T: Read + Seek,
+    {
+        let mut rows = self.rows(workbook)?;
+        if start > 1 {
+            rows.skip_rows(start as usize - 1);
         }
+        Ok(rows)
     }
 
-    /// # Summary
-    /// The `read_to_buffer` function reads the contents of a worksheet within a workbook and returns it as a vector of bytes.
+    /// Read every row of this worksheet into memory. This is convenient for small sheets, but
+    /// keep in mind that it defeats the purpose of the streaming `rows` iterator on big ones. You
+    /// can wrap the result in a `Sheet2D` for spreadsheet-like random access.
     ///
-    /// # Returns
-    /// A vector of bytes that represent the contents of the worksheet.
+    /// # Example usage
     ///
-    /// # Example
-    /// ```
-    /// let mut workbook = Workbook::open("example.xlsx").unwrap();
-    /// let data = workbook.read_to_buffer(&mut workbook);
-    /// ```
-    pub fn read_to_buffer<'a, T>(&self, workbook: &'a mut Workbook<T>) -> Vec<u8>
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let rows = ws.to_vec(&mut wb).unwrap();
+    ///     assert_eq!(rows[0][0].raw_value, "1");
+    pub fn to_vec<'a, T>(&self, workbook: &'a mut Workbook<T>) -> Result<Vec<Row<'a>>, XlError>
     where
         T: Read + Seek,
     {
-        let mut out_bytes: Vec<u8> = vec![];
-        let mut sheet_reader = workbook.sheet_reader(&self.target);
-        let reader = &mut sheet_reader.reader;
-        let styles = sheet_reader.styles;
-        let date_system = sheet_reader.date_system;
+        let mut rows = self.rows(workbook)?;
+        let all_rows = rows.by_ref().collect();
+        rows.finish()?;
+        Ok(all_rows)
+    }
 
-        // the xml in the xlsx file will not contain elements for empty rows. So
-        // we need to "simulate" the empty rows since the user expects to see
-        // them when they iterate over the worksheet.
-        let mut buf = Vec::new();
-        let strings = sheet_reader.strings;
-        let mut in_value = false;
-        let mut cell_type = "".to_string();
-        let mut col = 0;
-        let mut pushed = 0;
-        let mut num_cols = 0;
-        let mut is_start_row = true;
-        let mut cell_style = "".to_string();
+    /// Read every cell of this worksheet as the text it displays, without having to deal with
+    /// `ExcelValue`s: strings are not quoted, numbers are formatted like `ExcelValue`'s `Display`
+    /// does, dates look like `2020-01-31`, and empty cells are empty strings. Every row is as wide
+    /// as the sheet, so the text of cell `B3` is at `grid[2][1]`. Like `to_vec`, this reads the
+    /// whole sheet into memory.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/7_nulls.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get(1).unwrap();
+    ///     let grid = ws.to_string_grid(&mut wb).unwrap();
+    ///     assert_eq!(grid[0], vec!["", "0", "1", "2", "3", "4"]);
+    ///     assert_eq!(grid[1][1], "foo");
+    pub fn to_string_grid<T>(&self, workbook: &mut Workbook<T>) -> Result<Vec<Vec<String>>, XlError>
+    where
+        T: Read + Seek,
+    {
+        let mut rows = self.rows(workbook)?;
+        let grid = rows
+            .by_ref()
+            .map(|row| row.0.iter().map(|cell| plain_text(&cell.value)).collect())
+            .collect();
+        rows.finish()?;
+        Ok(grid)
+    }
 
-        loop {
-            let event = reader.read_event(&mut buf);
+    /// Read the cells of this worksheet that hold a value into a map keyed by their reference
+    /// (e.g., `"B7"`). Empty cells are left out, so the map only grows with the data in the sheet,
+    /// not with its size, which makes it a good fit for comparing two versions of a sheet cell by
+    /// cell.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::{ExcelValue, Workbook};
+    ///
+    ///     let mut wb = Workbook::open("tests/data/7_nulls.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get(1).unwrap();
+    ///     let cells = ws.to_sparse_map(&mut wb).unwrap();
+    ///     assert!(!cells.contains_key("A1"));
+    ///     assert_eq!(cells["B1"], ExcelValue::String("0".into()));
+    pub fn to_sparse_map<'a, T>(
+        &self,
+        workbook: &'a mut Workbook<T>,
+    ) -> Result<HashMap<String, ExcelValue<'a>>, XlError>
+    where
+        T: Read + Seek,
+    {
+        let mut rows = self.rows(workbook)?;
+        let cells = rows
+            .by_ref()
+            .flat_map(|row| row.0)
+            .filter(|cell| cell.value != ExcelValue::None)
+            .map(|cell| (cell.reference, cell.value))
+            .collect();
+        rows.finish()?;
+        Ok(cells)
+    }
 
-            match event {
-                /* may be able to get a better estimate for the used area */
-                Ok(Event::Empty(ref e)) if e.name() == b"dimension" => {
-                    if let Some(used_area_range) = utils::get(e.attributes(), b"ref") {
-                        (_, num_cols) = used_area(&used_area_range);
-                    }
-                }
-                Ok(Event::Start(ref e)) if e.name() == b"row" => {
-                    is_start_row = true;
-                    col = 0;
-                }
-                /* -- end search for used area */
-                Ok(Event::Start(ref e)) if e.name() == b"v" || e.name() == b"t" => {
-                    in_value = true;
+    /// Read a single column (e.g., `"C"`) of this worksheet, top to bottom. The result has one
+    /// value per row, including `ExcelValue::None` for rows where the column is empty, so the
+    /// value for row `n` is at index `n - 1`. Returns `XlError::InvalidReference` if `column` is
+    /// not a valid column name.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::{ExcelValue, Workbook};
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let column = ws.column_values(&mut wb, "C").unwrap();
+    ///     assert_eq!(column.len(), 46);
+    ///     assert_eq!(column[1], ExcelValue::Number(21.0));
+    pub fn column_values<'a, T>(
+        &self,
+        workbook: &'a mut Workbook<T>,
+        column: &str,
+    ) -> Result<Vec<ExcelValue<'a>>, XlError>
+    where
+        T: Read + Seek,
+    {
+        let index = match utils::col2num(column) {
+            Some(col) => col as usize - 1,
+            None => return Err(XlError::InvalidReference(column.to_string())),
+        };
+        let mut rows = self.rows(workbook)?;
+        let values = rows
+            .by_ref()
+            .map(|row| match row.0.into_iter().nth(index) {
+                Some(cell) => cell.value,
+                None => ExcelValue::None,
+            })
+            .collect();
+        rows.finish()?;
+        Ok(values)
+    }
+
+    /// Read the rectangular block of cells in `range` (e.g., `"B2:E100"`, or a single cell like
+    /// `"B2"`) as numbers, one vector per row, which is the usual shape for handing a numeric grid
+    /// to a matrix library or a solver. Each cell is converted with `Cell::get_f64`; cells it
+    /// rejects (text that is not a number, booleans, dates, errors, and empty cells) become
+    /// `default`. Pass `f64::NAN` to be able to tell them apart, since Excel never stores NaN
+    /// itself. The result always has the full size of the range, even where it reaches past the
+    /// end of the sheet. Only the rows of the range are built, and reading stops after its last
+    /// row. Returns `XlError::InvalidReference` if `range` is not a valid range.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let block = ws.read_f64_block(&mut wb, "B2:C3", f64::NAN).unwrap();
+    ///     assert_eq!(block, vec![vec![20.0, 21.0], vec![38.0, 39.0]]);
+    pub fn read_f64_block<T>(
+        &self,
+        workbook: &mut Workbook<T>,
+        range: &str,
+        default: f64,
+    ) -> Result<Vec<Vec<f64>>, XlError>
+    where
+        T: Read + Seek,
+    {
+        let invalid = || XlError::InvalidReference(range.to_string());
+        let (first, last) = match range.split_once(':') {
+            Some((first, last)) => (first, last),
+            None => (range, range),
+        };
+        let (col1, row1) = utils::parse_reference(first).ok_or_else(invalid)?;
+        let (col2, row2) = utils::parse_reference(last).ok_or_else(invalid)?;
+        let (left, right) = (cmp::min(col1, col2) as usize, cmp::max(col1, col2) as usize);
+        let (top, bottom) = (cmp::min(row1, row2), cmp::max(row1, row2));
+        let height = (bottom - top + 1) as usize;
+        let mut rows = self.rows_from(workbook, top)?;
+        let mut block: Vec<Vec<f64>> = rows
+            .by_ref()
+            .take(height)
+            .map(|row| {
+                (left..=right)
+                    .map(|col| {
+                        row.0
+                            .get(col - 1)
+                            .and_then(Cell::get_f64)
+                            .unwrap_or(default)
+                    })
+                    .collect()
+            })
+            .collect();
+        rows.finish()?;
+        block.resize(height, vec![default; right - left + 1]);
+        Ok(block)
+    }
+
+    /// Read the first `n` rows of this worksheet into memory (e.g., to preview a big sheet). Rows
+    /// are read lazily, so the xml is only read as far as the `n`th row; the rest of the sheet is
+    /// never looked at. Like `to_vec`, empty rows count, so the result has `n` rows unless the
+    /// sheet is shorter than that.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let rows = ws.head(&mut wb, 2).unwrap();
+    ///     assert_eq!(rows.len(), 2);
+    ///     assert_eq!(rows[1][0].raw_value, "19");
+    pub fn head<'a, T>(
+        &self,
+        workbook: &'a mut Workbook<T>,
+        n: usize,
+    ) -> Result<Vec<Row<'a>>, XlError>
+    where
+        T: Read + Seek,
+    {
+        let mut rows = self.rows(workbook)?;
+        let first_rows = rows.by_ref().take(n).collect();
+        rows.finish()?;
+        Ok(first_rows)
+    }
+
+    /// Read the first row of this worksheet that holds any values (normally the row with the
+    /// column names) and stop there, without looking at the rest of the sheet. Empty rows before
+    /// it are skipped. Returns `None` if the sheet has no values at all.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let header = ws.header_row(&mut wb).unwrap().unwrap();
+    ///     assert_eq!(header.1, 1);
+    ///     assert_eq!(header[0].raw_value, "1");
+    pub fn header_row<'a, T>(
+        &self,
+        workbook: &'a mut Workbook<T>,
+    ) -> Result<Option<Row<'a>>, XlError>
+    where
+        T: Read + Seek,
+    {
+        let mut rows = self.rows(workbook)?;
+        let header = rows.find(|row| row.populated_count() > 0);
+        rows.finish()?;
+        Ok(header)
+    }
+
+    /// Does this worksheet have no populated cells? The sheet is scanned only as far as the first
+    /// cell with a value, so this is much cheaper than building rows just to find out that a sheet
+    /// is blank. Cells that are merely formatted (i.e., that have a style but no value) do not
+    /// count as populated, and neither do chartsheets.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     assert!(!ws.is_empty(&mut wb).unwrap());
+    pub fn is_empty<T>(&self, workbook: &mut Workbook<T>) -> Result<bool, XlError>
+    where
+        T: Read + Seek,
+    {
+        let mut reader = workbook.sheet_reader(&self.target)?.reader;
+        let mut in_cell = false;
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event(&mut buf) {
+                Ok(Event::Start(ref e)) if e.name() == b"c" => in_cell = true,
+                Ok(Event::End(ref e)) if e.name() == b"c" => in_cell = false,
+                Ok(Event::Start(ref e)) if in_cell && (e.name() == b"v" || e.name() == b"is") => {
+                    return Ok(false)
                 }
-                // note: because v elements are children of c elements,
-                // need this check to go before the 'in_cell' check
-                Ok(Event::Text(ref e)) if in_value => {
-                    let raw_value = e.unescape_and_decode(reader).unwrap();
-                    match &cell_type[..] {
-                        "s" => {
-                            if let Ok(pos) = raw_value.parse::<usize>() {
-                                out_bytes.push(b'"');
-                                out_bytes.append(&mut strings[pos]
-                                    .clone()
-                                    .into_bytes()
-                                    .iter()
-                                    .flat_map(|&byte| if byte == b'"' { vec![b'"', b'"'] } else { vec![byte] })
-                                    .collect());
-                                out_bytes.push(b'"');
-                            } else {
-                                out_bytes.push(b'"');
-                                out_bytes.append(&mut e
-                                    .escape_ascii()
-                                    .flat_map(|byte| if byte == b'"' { vec![b'"', b'"'] } else { vec![byte] })
-                                    .collect());
-                                out_bytes.push(b'"');
-                            }
-                        }
-                        "str" | "inlineStr" => {
-                            out_bytes.push(b'"');
-                            out_bytes.append(&mut e
-                                    .escape_ascii()
-                                    .flat_map(|byte| if byte == b'"' { vec![b'"', b'"'] } else { vec![byte] })
-                                    .collect());
+                Ok(Event::Eof) => break,
+                Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
+                _ => (),
+            }
+            buf.clear();
+        }
+        Ok(true)
+    }
 
-                            out_bytes.push(b'"');
-                        }
-                        _ if is_date(&cell_style) => {
-                            let num = raw_value.parse::<f64>().unwrap();
-                            let date_string = match utils::excel_number_to_date(num, date_system) {
-                                utils::DateConversion::Date(date) => date.to_string(),
-                                utils::DateConversion::DateTime(date) => {
-                                    date.format("%Y-%m-%d %H:%M:%S").to_string()
-                                }
-                                utils::DateConversion::Time(time) => {
-                                    time.format("%Y-%m-%d %H:%M:%S").to_string()
-                                }
-                                utils::DateConversion::Number(num) => {
-                                    format!("Invalid date {}", num)
-                                }
-                            };
-                            out_bytes.append(&mut date_string.into_bytes());
-                        }
-                        _ => {
-                            out_bytes.push(b'"');
-                            out_bytes.append(&mut e.escape_ascii().collect());
-                            out_bytes.push(b'"');
-                        }
-                    };
+    /// Find the number of the last row that has at least one populated cell, or 0 if the sheet
+    /// has none. Unlike `dimension`, this does not trust the `<dimension>` element (which is often
+    /// stale, or counts rows that are merely formatted), so the whole sheet is scanned. As with
+    /// `is_empty`, cells that only have a style do not count as populated.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/trailing_formatting.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     assert_eq!(ws.dimension(&mut wb).unwrap().last_row, 30);
+    ///     assert_eq!(ws.last_row(&mut wb).unwrap(), 5);
+    pub fn last_row<T>(&self, workbook: &mut Workbook<T>) -> Result<u32, XlError>
+    where
+        T: Read + Seek,
+    {
+        let mut reader = workbook.sheet_reader(&self.target)?.reader;
+        let (mut current_row, mut last_row) = (0, 0);
+        let mut in_cell = false;
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event(&mut buf) {
+                Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) if e.name() == b"row" => {
+                    current_row = utils::get(e.attributes(), b"r")
+                        .and_then(|r| r.parse().ok())
+                        .unwrap_or(current_row + 1);
                 }
-                /* Matching start of cell */
                 Ok(Event::Start(ref e)) if e.name() == b"c" => {
-                    cell_style = "".to_string();
-                    e.attributes().for_each(|a| {
-                        let a = a.unwrap();
-                        if a.key == b"t" {
-                            cell_type = utils::attr_value(&a);
-                        }
-                        if a.key == b"s" {
-                            if let Ok(num) = utils::attr_value(&a).parse::<usize>() {
-                                if let Some(style) = styles.get(num) {
-                                    cell_style = style.to_string();
-                                }
-                            }
-                        }
-                        if a.key == b"r" {
-                            let reference = utils::attr_value(&a);
-                            let (new_col, _row) = coordinates(reference);
-                            let diff = new_col - col - 1;
-
-                            for _ in 0..diff {
-                                out_bytes.push(b',');
-                                pushed += 1;
-                            }
-                            col = new_col;
-                        }
-                    });
-                    // Only add a comma if it isnt the first row
-                    if !is_start_row {
-                        out_bytes.push(b',');
-                        pushed += 1;
-                    } else {
-                        is_start_row = false;
+                    in_cell = true;
+                    let r = utils::get(e.attributes(), b"r");
+                    if let Some((_, row)) = r.and_then(|r| utils::parse_reference(&r)) {
+                        current_row = row;
                     }
                 }
-                Ok(Event::End(ref e)) if e.name() == b"c" => {
-                    cell_type = "nono".to_string();
-                }
-                Ok(Event::End(ref e)) if e.name() == b"v" || e.name() == b"t" => {
-                    in_value = false;
-                }
-                Ok(Event::End(ref e)) if e.name() == b"row" => {
-                    if pushed <= num_cols {
-                        for _ in pushed..(num_cols - 1) {
-                            out_bytes.push(b',');
-                        }
-                    }
-                    out_bytes.push(b'\n');
-                    is_start_row = true;
-                    pushed = 0;
+                Ok(Event::End(ref e)) if e.name() == b"c" => in_cell = false,
+                Ok(Event::Start(ref e)) if in_cell && (e.name() == b"v" || e.name() == b"is") => {
+                    last_row = cmp::max(last_row, current_row);
                 }
                 Ok(Event::Eof) => break,
                 Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
@@ -324,408 +651,5403 @@ impl Worksheet {
             }
             buf.clear();
         }
-        return out_bytes;
+        Ok(last_row)
     }
-}
 
-/// `ExcelValue` is the enum that holds the equivalent "rust value" of a `Cell`s "raw_value."
-#[derive(Debug, PartialEq)]
-pub enum ExcelValue<'a> {
-    Bool(bool),
-    Date(NaiveDate),
-    DateTime(NaiveDateTime),
-    Error(String),
-    None,
-    Number(f64),
-    String(Cow<'a, str>),
-    Time(NaiveTime),
-}
+    /// List the parts attached to this worksheet (drawings, hyperlinks, comments, tables, etc.)
+    /// as (relationship type, target) pairs, in the order they are listed in the sheet's
+    /// relationships part. The type is the full relationship URI (e.g.,
+    /// `http://schemas.openxmlformats.org/officeDocument/2006/relationships/drawing`) and the
+    /// target is exactly as written, so it is usually relative to the sheet (e.g.,
+    /// `../drawings/drawing1.xml`) or, for external hyperlinks, a URL. Sheets without anything
+    /// attached return an empty list.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/attachments.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Pictures").unwrap();
+    ///     let has_drawing = ws
+    ///         .related_parts(&mut wb)
+    ///         .iter()
+    ///         .any(|(rel_type, _)| rel_type.ends_with("/drawing"));
+    ///     assert!(has_drawing);
+    pub fn related_parts<T>(&self, workbook: &mut Workbook<T>) -> Vec<(String, String)>
+    where
+        T: Read + Seek,
+    {
+        workbook.sheet_rels(&self.target)
+    }
 
-impl fmt::Display for ExcelValue<'_> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            ExcelValue::Bool(b) => write!(f, "{}", b),
-            ExcelValue::Date(d) => write!(f, "{}", d),
-            ExcelValue::DateTime(d) => write!(f, "{}", d),
-            ExcelValue::Error(e) => write!(f, "#{}", e),
-            ExcelValue::None => write!(f, ""),
-            ExcelValue::Number(n) => write!(f, "{}", n),
-            ExcelValue::String(s) => write!(f, "\"{}\"", s),
-            ExcelValue::Time(t) => write!(f, "\"{}\"", t),
+    /// Read the display settings of this worksheet (whether gridlines and row/column headings are
+    /// shown and the zoom level). Settings that are not recorded in the file get Excel's defaults
+    /// (see `ViewSettings`). If the sheet has more than one view, the first one is used.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let view = ws.view_settings(&mut wb).unwrap();
+    ///     assert!(view.show_grid_lines);
+    ///     assert_eq!(view.zoom_scale, 100);
+    pub fn view_settings<T>(&self, workbook: &mut Workbook<T>) -> Result<ViewSettings, XlError>
+    where
+        T: Read + Seek,
+    {
+        let mut reader = workbook.sheet_reader(&self.target)?.reader;
+        let mut settings = ViewSettings::default();
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event(&mut buf) {
+                Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) if e.name() == b"sheetView" => {
+                    e.attributes().for_each(|a| {
+                        let a = a.unwrap();
+                        let value = utils::attr_value(&a);
+                        match a.key {
+                            b"showGridLines" => settings.show_grid_lines = xml_bool(&value),
+                            b"showRowColHeaders" => {
+                                settings.show_row_col_headers = xml_bool(&value)
+                            }
+                            b"zoomScale" => {
+                                if let Ok(zoom) = value.parse() {
+                                    settings.zoom_scale = zoom;
+                                }
+                            }
+                            _ => (),
+                        }
+                    });
+                    break;
+                }
+                // the views come before the data, so there is no need to look any further
+                Ok(Event::Start(ref e)) if e.name() == b"sheetData" => break,
+                Ok(Event::Eof) => break,
+                Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
+                _ => (),
+            }
+            buf.clear();
         }
+        Ok(settings)
     }
-}
 
-#[derive(Debug)]
-pub struct Cell<'a> {
-    /// The value you get by converting the raw_value (a string) into a Rust value
-    pub value: ExcelValue<'a>,
-    /// The formula (may be "empty") of the cell
-    pub formula: String,
-    /// What cell are we looking at? E.g., B3, A1, etc.
-    pub reference: String,
-    /// The cell style (e.g., the style you see in Excel by hitting Ctrl+1 and going to the
-    /// "Number" tab).
-    pub style: String,
-    /// The type of cell as recorded by Excel (s = string using sharedStrings.xml, str = raw
-    /// string, b = boolean, etc.). This may change from a `String` type to an `Enum` of some sorts
-    /// in the future.
-    pub cell_type: String,
-    /// The raw string value recorded in the xml
-    pub raw_value: String,
-}
+    /// Read the used area of this worksheet from its `<dimension>` element, e.g., `B2:F20`. Unlike
+    /// the row iterator, which always starts at A1, this tells you where the data actually
+    /// starts. If the sheet does not declare its dimension (or declares something invalid), the
+    /// used area is computed from the cells in the sheet instead, which means reading the whole
+    /// sheet. A sheet without any cells has a dimension of `A1:A1`, as Excel writes it.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let dim = ws.dimension(&mut wb).unwrap();
+    ///     assert_eq!((dim.first_row, dim.first_col), (1, 1));
+    ///     assert_eq!((dim.last_row, dim.last_col), (46, 18)); // A1:R46
+    pub fn dimension<T>(&self, workbook: &mut Workbook<T>) -> Result<Dimension, XlError>
+    where
+        T: Read + Seek,
+    {
+        let mut reader = workbook.sheet_reader(&self.target)?.reader;
+        let mut computed: Option<Dimension> = None;
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event(&mut buf) {
+                Ok(Event::Empty(ref e)) if e.name() == b"dimension" => {
+                    let declared = utils::get(e.attributes(), b"ref")
+                        .and_then(|range| Dimension::from_range(&range));
+                    if let Some(dimension) = declared {
+                        return Ok(dimension);
+                    }
+                }
+                // without a (valid) dimension, we have to look at every cell
+                Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) if e.name() == b"c" => {
+                    if let Some((col, row)) =
+                        utils::get(e.attributes(), b"r").and_then(|r| utils::parse_reference(&r))
+                    {
+                        match computed.as_mut() {
+                            Some(dimension) => dimension.include(col, row),
+                            None => {
+                                computed = Some(Dimension {
+                                    first_row: row,
+                                    last_row: row,
+                                    first_col: col,
+                                    last_col: col,
+                                })
+                            }
+                        }
+                    }
+                }
+                Ok(Event::Eof) => break,
+                Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
+                _ => (),
+            }
+            buf.clear();
+        }
+        Ok(computed.unwrap_or(Dimension {
+            first_row: 1,
+            last_row: 1,
+            first_col: 1,
+            last_col: 1,
+        }))
+    }
 
-impl Cell<'_> {
-    /// return the row/column coordinates of the current cell
-    pub fn coordinates(&self) -> (u16, u32) {
-        // let (col, row) = split_cell_reference(&self.reference);
-        let (col, row) = {
-            let r = &self.reference;
-            let mut end = 0;
-            for (i, c) in r.chars().enumerate() {
-                if !c.is_ascii_alphabetic() {
-                    end = i;
-                    break;
+    /// The number of columns in the used area, counted from column A, which is how wide the rows
+    /// of the row iterator are. This comes from the sheet's `<dimension>` element (see
+    /// `dimension`), or from the cells themselves when the workbook was opened with
+    /// `WorkbookOptions::recompute_dimensions`. No `Cell`s are built, so this is a cheap way to
+    /// size a grid before iterating. Rows can still come out wider if there are cells outside of
+    /// the declared dimension (see `RowIter::strict_dimension`). Chartsheets have no columns.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     assert_eq!(ws.column_count(&mut wb).unwrap(), 18); // A1:R46
+    ///     assert_eq!(ws.row_count(&mut wb).unwrap(), 46);
+    pub fn column_count<T>(&self, workbook: &mut Workbook<T>) -> Result<u16, XlError>
+    where
+        T: Read + Seek,
+    {
+        Ok(self.used_area(workbook)?.1)
+    }
+
+    /// The number of rows in the used area, counted from row 1, which is how many rows the row
+    /// iterator returns. See `column_count` for where the number comes from.
+    pub fn row_count<T>(&self, workbook: &mut Workbook<T>) -> Result<u32, XlError>
+    where
+        T: Read + Seek,
+    {
+        Ok(self.used_area(workbook)?.0)
+    }
+
+    /// The (rows, columns) of the used area for `row_count` and `column_count`.
+    fn used_area<T>(&self, workbook: &mut Workbook<T>) -> Result<(u32, u16), XlError>
+    where
+        T: Read + Seek,
+    {
+        if self.kind == SheetKind::Chart {
+            return Ok((0, 0));
+        }
+        if workbook.options().recompute_dimensions {
+            let limits = workbook.options().limits;
+            let mut reader = workbook.sheet_reader(&self.target)?.reader;
+            return Ok(computed_used_area(&mut reader, limits));
+        }
+        let dimension = self.dimension(workbook)?;
+        Ok((dimension.last_row, dimension.last_col))
+    }
+
+    /// Read the column settings of this worksheet (widths, visibility and grouping). Excel only
+    /// records columns that differ from the default, and groups neighbouring columns with the same
+    /// settings into one range, so the result has one `ColumnInfo` per range in the order they
+    /// appear in the file. Columns that are not listed have the default settings.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/outline.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let columns = ws.columns_info(&mut wb).unwrap();
+    ///     assert_eq!((columns[1].first_col, columns[1].last_col), (2, 3));
+    ///     assert_eq!(columns[1].outline_level, 1);
+    pub fn columns_info<T>(&self, workbook: &mut Workbook<T>) -> Result<Vec<ColumnInfo>, XlError>
+    where
+        T: Read + Seek,
+    {
+        let mut reader = workbook.sheet_reader(&self.target)?.reader;
+        let mut columns = Vec::new();
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event(&mut buf) {
+                Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) if e.name() == b"col" => {
+                    let mut column = ColumnInfo {
+                        first_col: 0,
+                        last_col: 0,
+                        width: None,
+                        hidden: false,
+                        outline_level: 0,
+                        collapsed: false,
+                    };
+                    e.attributes().for_each(|a| {
+                        let a = a.unwrap();
+                        let value = utils::attr_value(&a);
+                        match a.key {
+                            b"min" => column.first_col = value.parse().unwrap_or(0),
+                            b"max" => column.last_col = value.parse().unwrap_or(0),
+                            b"width" => column.width = value.parse().ok(),
+                            b"hidden" => column.hidden = xml_bool(&value),
+                            b"outlineLevel" => column.outline_level = value.parse().unwrap_or(0),
+                            b"collapsed" => column.collapsed = xml_bool(&value),
+                            _ => (),
+                        }
+                    });
+                    if column.last_col < column.first_col {
+                        column.last_col = column.first_col;
+                    }
+                    columns.push(column);
+                }
+                // the columns come before the data, so there is no need to look any further
+                Ok(Event::Start(ref e)) if e.name() == b"sheetData" => break,
+                Ok(Event::Eof) => break,
+                Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
+                _ => (),
+            }
+            buf.clear();
+        }
+        Ok(columns)
+    }
+
+    /// Read the print settings of this worksheet: orientation, paper size, scale, and margins.
+    /// Settings that are not recorded in the file get Excel's defaults (see `PageSetup`). These
+    /// are stored after the data of the sheet, so the whole worksheet has to be read (but the
+    /// cells themselves are skipped without being parsed).
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::{Orientation, Workbook};
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let setup = ws.page_setup(&mut wb).unwrap();
+    ///     assert_eq!(setup.orientation, Orientation::Portrait);
+    ///     assert_eq!(setup.left_margin, 0.7);
+    pub fn page_setup<T>(&self, workbook: &mut Workbook<T>) -> Result<PageSetup, XlError>
+    where
+        T: Read + Seek,
+    {
+        let mut reader = workbook.sheet_reader(&self.target)?.reader;
+        let mut setup = PageSetup::default();
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event(&mut buf) {
+                Ok(Event::Start(ref e)) if e.name() == b"sheetData" => {
+                    reader.read_to_end(b"sheetData", &mut buf).unwrap();
+                }
+                Ok(Event::Empty(ref e)) if e.name() == b"pageMargins" => {
+                    e.attributes().for_each(|a| {
+                        let a = a.unwrap();
+                        let margin = match a.key {
+                            b"left" => &mut setup.left_margin,
+                            b"right" => &mut setup.right_margin,
+                            b"top" => &mut setup.top_margin,
+                            b"bottom" => &mut setup.bottom_margin,
+                            _ => return,
+                        };
+                        if let Ok(inches) = utils::attr_value(&a).parse() {
+                            *margin = inches;
+                        }
+                    });
+                }
+                Ok(Event::Empty(ref e)) if e.name() == b"pageSetup" => {
+                    e.attributes().for_each(|a| {
+                        let a = a.unwrap();
+                        let value = utils::attr_value(&a);
+                        match a.key {
+                            b"orientation" if value == "landscape" => {
+                                setup.orientation = Orientation::Landscape
+                            }
+                            b"paperSize" => {
+                                if let Ok(size) = value.parse() {
+                                    setup.paper_size = size;
+                                }
+                            }
+                            b"scale" => {
+                                if let Ok(scale) = value.parse() {
+                                    setup.scale = scale;
+                                }
+                            }
+                            _ => (),
+                        }
+                    });
                 }
+                Ok(Event::Eof) => break,
+                Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
+                _ => (),
             }
-            (&r[..end], &r[end..])
+            buf.clear();
+        }
+        Ok(setup)
+    }
+
+    /// Return the print area of this worksheet as its top-left and bottom-right cells (e.g.,
+    /// `("A1", "D20")`), or `None` if no print area is set. If the print area is made up of
+    /// several ranges, only the first one is returned.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/print_area.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Report").unwrap();
+    ///     let area = ws.print_area(&mut wb);
+    ///     assert_eq!(area, Some(("A1".to_string(), "C3".to_string())));
+    pub fn print_area<T>(&self, workbook: &mut Workbook<T>) -> Option<(String, String)>
+    where
+        T: Read + Seek,
+    {
+        let index = self.position as usize - 1;
+        let (_, _, formula) = workbook
+            .defined_names()
+            .into_iter()
+            .find(|(name, scope, _)| name == "_xlnm.Print_Area" && *scope == Some(index))?;
+        // the formula looks like Sheet1!$A$1:$C$3 (possibly followed by ,Sheet1!$E$1:$F$3 etc.)
+        let mut in_quotes = false;
+        let first = formula
+            .split(|c| {
+                if c == '\'' {
+                    in_quotes = !in_quotes;
+                }
+                c == ',' && !in_quotes
+            })
+            .next()?;
+        let range = match first.rfind('!') {
+            Some(i) => &first[i + 1..],
+            None => first,
         };
-        let col = utils::col2num(col).unwrap();
-        let row = row.parse().unwrap();
-        (col, row)
+        let range = range.replace('$', "");
+        match range.split_once(':') {
+            Some((start, end)) => Some((start.to_string(), end.to_string())),
+            None if !range.is_empty() => Some((range.clone(), range)),
+            None => None,
+        }
     }
-}
 
-pub fn coordinates(r: String) -> (u16, u32) {
-    // let (col, row) = split_cell_reference(&self.reference);
-    let (col, row) = {
-        let mut end = 0;
-        for (i, c) in r.chars().enumerate() {
-            if !c.is_ascii_alphabetic() {
-                end = i;
-                break;
+    /// # Summary
+    /// The `read_to_buffer` function reads the contents of a worksheet within a workbook and returns it as a vector of bytes.
+    ///
+    /// # Returns
+    /// A vector of bytes that represent the contents of the worksheet, or an `XlError` if the
+    /// worksheet cannot be read.
+    ///
+    /// # Example
+    /// ```
+    /// use xl::Workbook;
+    ///
+    /// let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    /// let sheets = wb.sheets();
+    /// let ws = sheets.get("Sheet1").unwrap();
+    /// let data = ws.read_to_buffer(&mut wb).unwrap();
+    /// assert!(data.starts_with(b"\"1\",\"2\""));
+    /// ```
+    pub fn read_to_buffer<T>(&self, workbook: &mut Workbook<T>) -> Result<Vec<u8>, XlError>
+    where
+        T: Read + Seek,
+    {
+        let mut out_bytes = vec![];
+        self.write_csv(workbook, &mut out_bytes)?;
+        Ok(out_bytes)
+    }
+
+    /// Write the contents of this worksheet to `out` as JSON Lines (a.k.a. NDJSON): one JSON
+    /// object per row, using the values in the header row (see `JsonLinesOptions`) as keys. Rows
+    /// above the header row are skipped. Columns with a blank header are keyed by their column
+    /// letter instead. Values are converted with `serde_json::Value::from(&ExcelValue)`.
+    ///
+    /// This method is only available with the `serde` feature.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::{JsonLinesOptions, Workbook};
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let mut out = vec![];
+    ///     ws.write_jsonl(&mut wb, &mut out, &JsonLinesOptions::default()).unwrap();
+    #[cfg(feature = "serde")]
+    pub fn write_jsonl<T, W>(
+        &self,
+        workbook: &mut Workbook<T>,
+        out: &mut W,
+        options: &JsonLinesOptions,
+    ) -> Result<(), XlError>
+    where
+        T: Read + Seek,
+        W: Write,
+    {
+        let mut rows = self
+            .rows(workbook)?
+            .skip_header_rows(options.header_row.saturating_sub(1));
+        let headers: Vec<String> = match rows.next() {
+            Some(row) => row
+                .0
+                .iter()
+                .enumerate()
+                .map(|(i, c)| match &c.value {
+                    ExcelValue::String(s) if !s.trim().is_empty() => s.to_string(),
+                    ExcelValue::None | ExcelValue::String(_) => {
+                        utils::num2col(i as u16 + 1).unwrap()
+                    }
+                    v => v.to_string(),
+                })
+                .collect(),
+            None => return rows.finish(),
+        };
+        for row in rows.by_ref() {
+            let mut object = serde_json::Map::new();
+            for (i, cell) in row.0.iter().enumerate() {
+                let key = match headers.get(i) {
+                    Some(header) => header.clone(),
+                    None => utils::num2col(i as u16 + 1).unwrap(),
+                };
+                object.insert(key, serde_json::Value::from(&cell.value));
             }
+            serde_json::to_writer(&mut *out, &object).map_err(|e| XlError::Io(e.into()))?;
+            out.write_all(b"\n")?;
         }
-        (&r[..end], &r[end..])
-    };
-    let col = utils::col2num(col).unwrap();
-    let row = row.parse().unwrap();
-    (col, row)
-}
+        rows.finish()
+    }
 
-#[derive(Debug)]
-pub struct Row<'a>(pub Vec<Cell<'a>>, pub usize);
+    /// Write the contents of this worksheet to `out` as comma-delimited text (the same format
+    /// `read_to_buffer` produces). Unlike `read_to_buffer`, the output is written a row at a time,
+    /// so memory use stays bounded no matter how big the sheet is. You will probably want to wrap
+    /// `out` in a `BufWriter` if it is a file.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let mut out = std::io::sink();
+    ///     ws.write_csv(&mut wb, &mut out).unwrap();
+    pub fn write_csv<T, W>(&self, workbook: &mut Workbook<T>, out: &mut W) -> Result<(), XlError>
+    where
+        T: Read + Seek,
+        W: Write,
+    {
+        self.write_csv_with_progress(workbook, out, |_| ())
+    }
+
+    /// The same as `write_csv`, but `progress` is called with the number of rows written so far
+    /// every `PROGRESS_INTERVAL` rows (and once more when the sheet is finished). This is useful
+    /// for driving a progress bar when exporting very large sheets.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let mut rows_done = 0;
+    ///     let mut out = std::io::sink();
+    ///     ws.write_csv_with_progress(&mut wb, &mut out, |n| rows_done = n).unwrap();
+    ///     assert_eq!(rows_done, 46);
+    pub fn write_csv_with_progress<T, W, F>(
+        &self,
+        workbook: &mut Workbook<T>,
+        out: &mut W,
+        progress: F,
+    ) -> Result<(), XlError>
+    where
+        T: Read + Seek,
+        W: Write,
+        F: FnMut(u64),
+    {
+        self.write_csv_inner(workbook, out, &CsvOptions::default(), progress)
+    }
+
+    /// The same as `write_csv`, but lets you change how the csv is written. See `CsvOptions` for
+    /// what can be configured.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::{CsvOptions, Workbook};
+    ///
+    ///     let mut wb = Workbook::open("tests/data/7_nulls.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get(1).unwrap();
+    ///     let options = CsvOptions {
+    ///         null_text: Some("NULL".to_string()),
+    ///         ..CsvOptions::default()
+    ///     };
+    ///     let mut out = vec![];
+    ///     ws.write_csv_with_options(&mut wb, &mut out, &options).unwrap();
+    ///     assert!(out.starts_with(b"NULL,\"0\""));
+    pub fn write_csv_with_options<T, W>(
+        &self,
+        workbook: &mut Workbook<T>,
+        out: &mut W,
+        options: &CsvOptions,
+    ) -> Result<(), XlError>
+    where
+        T: Read + Seek,
+        W: Write,
+    {
+        self.write_csv_inner(workbook, out, options, |_| ())
+    }
+
+    /// Write a csv built from the rows of this worksheet: `f` is called with every row and
+    /// returns the full record to write for it (e.g., the original values plus a computed column).
+    /// Note that this bypasses the usual serialization of the cells in the sheet: what is written
+    /// is the mapped `ExcelValue`s, quoted the same way `write_csv` quotes cells (text and numbers
+    /// are quoted, dates and errors are not, and `ExcelValue::None` is an empty field). Numbers are
+    /// written with every digit they need to be read back exactly, like the raw values `write_csv`
+    /// writes (and not rounded the way `ExcelValue`'s `Display` rounds them), so a value passed
+    /// through unchanged comes out the same as in `write_csv`.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::{ExcelValue, Workbook};
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let mut out = vec![];
+    ///     ws.write_csv_map(&mut wb, &mut out, |row| {
+    ///         let total: f64 = row
+    ///             .0
+    ///             .iter()
+    ///             .filter_map(|c| match c.value {
+    ///                 ExcelValue::Number(n) => Some(n),
+    ///                 _ => None,
+    ///             })
+    ///             .sum();
+    ///         vec![row[0].value.clone(), ExcelValue::Number(total)]
+    ///     })
+    ///     .unwrap();
+    ///     assert!(out.starts_with(b"\"1\",\"171\"\n"));
+    pub fn write_csv_map<'a, T, W, F>(
+        &self,
+        workbook: &'a mut Workbook<T>,
+        out: &mut W,
+        mut f: F,
+    ) -> Result<(), XlError>
+    where
+        T: Read + Seek,
+        W: Write,
+        F: FnMut(&Row<'a>) -> Vec<ExcelValue<'a>>,
+    {
+        let mut line = vec![];
+        let mut rows = self.rows(workbook)?;
+        for row in rows.by_ref() {
+            line.clear();
+            for (i, value) in f(&row).iter().enumerate() {
+                if i > 0 {
+                    line.push(b',');
+                }
+                write_csv_value(&mut line, value);
+            }
+            line.push(b'\n');
+            out.write_all(&line)?;
+        }
+        rows.finish()
+    }
+
+    fn write_csv_inner<T, W, F>(
+        &self,
+        workbook: &mut Workbook<T>,
+        out: &mut W,
+        options: &CsvOptions,
+        mut progress: F,
+    ) -> Result<(), XlError>
+    where
+        T: Read + Seek,
+        W: Write,
+        F: FnMut(u64),
+    {
+        let null_text = options.null_text.as_deref().unwrap_or("").as_bytes();
+        let mut out_bytes: Vec<u8> = vec![];
+        let recompute_dimensions = workbook.options().recompute_dimensions;
+        let limits = workbook.options().limits;
+        let part = part_name(&self.target);
+        let mut num_cols = if recompute_dimensions {
+            computed_used_area(&mut workbook.sheet_reader(&self.target)?.reader, limits).1
+        } else {
+            0
+        };
+        let mut sheet_reader = workbook.sheet_reader(&self.target)?;
+        let reader = &mut sheet_reader.reader;
+        let styles = sheet_reader.styles;
+        let date_system = sheet_reader.date_system;
+
+        // the xml in the xlsx file will not contain elements for empty rows. So
+        // we need to "simulate" the empty rows since the user expects to see
+        // them when they iterate over the worksheet.
+        let mut buf = Vec::new();
+        let strings = sheet_reader.strings;
+        let mut in_value = false;
+        // the (unescaped) text of the `<v>` element being read
+        let mut value_text = String::new();
+        let mut cell_type = "".to_string();
+        let mut col = 0;
+        // number of fields written so far in the current row
+        let mut fields = 0;
+        let mut has_value = false;
+        // does the current cell have a value element (`<v>` or `<t>`), even an empty one? A string
+        // cell with an empty value element holds an empty string rather than nothing (see
+        // `empty_string_value`)
+        let mut has_value_element = false;
+        // set for cells whose reference lies past the last column (XFD), which are dropped
+        let mut skip_cell = false;
+        // the text of the inline string (`<is>`) being read, which is collected the same way as
+        // in `RowIter` and written out once the string is complete
+        let mut inline_text: Option<String> = None;
+        let mut in_inline_text = false;
+        let mut in_phonetic = false;
+        let mut preserve_space = false;
+        let mut cell_style = "".to_string();
+        // the style of the current row, for cells without a style of their own
+        let mut row_style = "".to_string();
+        let mut rows_written: u64 = 0;
+        let mut last_progress: u64 = 0;
+        let mut cells_written: u64 = 0;
+        // where the last field with a value in the current row ends, for `trim_trailing_empty`
+        let mut values_end = 0;
+
+        loop {
+            let event = reader.read_event(&mut buf);
+            limits.check_bytes(reader.buffer_position() as u64, &part)?;
+
+            match event {
+                /* may be able to get a better estimate for the used area */
+                Ok(Event::Empty(ref e)) if !recompute_dimensions && e.name() == b"dimension" => {
+                    if let Some(used_area_range) = utils::get(e.attributes(), b"ref") {
+                        (_, num_cols) = used_area(&used_area_range);
+                    }
+                }
+                Ok(Event::Start(ref e)) if e.name() == b"row" => {
+                    fields = 0;
+                    col = 0;
+                    values_end = out_bytes.len();
+                    row_style = match row_properties(e, false).1.style_index {
+                        Some(num) => styles.get(num).cloned().unwrap_or_default(),
+                        None => "".to_string(),
+                    };
+                }
+                /* -- end search for used area */
+                Ok(Event::Start(ref e)) if e.name() == b"is" && !skip_cell => {
+                    inline_text = Some(String::new());
+                    reader.trim_text(false);
+                }
+                Ok(Event::End(ref e)) if e.name() == b"is" => {
+                    reader.trim_text(true);
+                    match inline_text.take() {
+                        Some(text) if !text.is_empty() => {
+                            has_value = true;
+                            out_bytes.push(b'"');
+                            out_bytes.extend_from_slice(text.replace('"', "\"\"").as_bytes());
+                            out_bytes.push(b'"');
+                        }
+                        _ => (),
+                    }
+                }
+                Ok(Event::Start(ref e)) if inline_text.is_some() && e.name() == b"rPh" => {
+                    in_phonetic = true
+                }
+                Ok(Event::End(ref e)) if inline_text.is_some() && e.name() == b"rPh" => {
+                    in_phonetic = false
+                }
+                Ok(Event::Start(ref e)) if inline_text.is_some() && e.name() == b"t" => {
+                    has_value_element = true;
+                    in_inline_text = !in_phonetic;
+                    preserve_space =
+                        utils::get(e.attributes(), b"xml:space").as_deref() == Some("preserve");
+                }
+                Ok(Event::Text(ref e)) if inline_text.is_some() => {
+                    if let (true, Some(text)) = (in_inline_text, inline_text.as_mut()) {
+                        let raw_value = e.unescape_and_decode(reader).unwrap();
+                        if preserve_space {
+                            text.push_str(&raw_value);
+                        } else {
+                            text.push_str(raw_value.trim());
+                        }
+                    }
+                }
+                Ok(Event::Start(ref e)) if e.name() == b"v" || e.name() == b"t" => {
+                    in_value = !skip_cell;
+                    has_value_element = true;
+                }
+                Ok(Event::Empty(ref e)) if e.name() == b"v" || e.name() == b"t" => {
+                    has_value_element = true;
+                }
+                // note: because v elements are children of c elements,
+                // need this check to go before the 'in_cell' check
+                Ok(Event::Text(ref e)) if in_value => {
+                    // the value may come in several pieces, so it is written out at `</v>`
+                    value_text.push_str(&e.unescape_and_decode(reader).unwrap());
+                    has_value = true;
+                }
+                /* Matching start of cell */
+                Ok(Event::Start(ref e)) if e.name() == b"c" => {
+                    cell_style = row_style.clone();
+                    has_value = false;
+                    has_value_element = false;
+                    // cells are not required to have a reference, in which case they simply
+                    // follow the previous cell
+                    let mut new_col = col + 1;
+                    skip_cell = false;
+                    e.attributes().for_each(|a| {
+                        let a = a.unwrap();
+                        if a.key == b"t" {
+                            cell_type = utils::attr_value(&a);
+                        }
+                        if a.key == b"s" {
+                            if let Ok(num) = utils::attr_value(&a).parse::<usize>() {
+                                cell_style = styles.get(num).cloned().unwrap_or_default();
+                            }
+                        }
+                        if a.key == b"r" {
+                            match utils::parse_reference(&utils::attr_value(&a)) {
+                                Some((c, _)) => new_col = c,
+                                None => skip_cell = true,
+                            }
+                        }
+                    });
+                    if skip_cell {
+                        buf.clear();
+                        continue;
+                    }
+                    // fill in any cells missing between the last cell and this one
+                    while col + 1 < new_col {
+                        col += 1;
+                        if fields > 0 {
+                            out_bytes.push(b',');
+                        }
+                        out_bytes.extend_from_slice(null_text);
+                        fields += 1;
+                    }
+                    if fields > 0 {
+                        out_bytes.push(b',');
+                    }
+                    fields += 1;
+                    col = new_col;
+                }
+                Ok(Event::End(ref e)) if e.name() == b"c" => {
+                    let empty_string = !has_value
+                        && has_value_element
+                        && (cell_type == "str" || cell_type == "inlineStr");
+                    if empty_string && !skip_cell {
+                        out_bytes.extend_from_slice(b"\"\"");
+                        values_end = out_bytes.len();
+                    } else if !has_value && !skip_cell {
+                        out_bytes.extend_from_slice(null_text);
+                    } else if has_value && !skip_cell && cell_type != "bl" {
+                        values_end = out_bytes.len();
+                    }
+                    skip_cell = false;
+                    cell_type = "nono".to_string();
+                }
+                Ok(Event::End(ref e)) if e.name() == b"v" || e.name() == b"t" => {
+                    if in_value && has_value {
+                        let raw_value = mem::take(&mut value_text);
+                        match &cell_type[..] {
+                            "s" => {
+                                // an index past the end of the table (e.g., in a workbook without a
+                                // sharedStrings.xml part) is written as-is
+                                if let Some(s) = raw_value
+                                    .parse::<usize>()
+                                    .ok()
+                                    .and_then(|pos| strings.get(pos))
+                                {
+                                    out_bytes.push(b'"');
+                                    out_bytes.append(
+                                        &mut s
+                                            .clone()
+                                            .into_bytes()
+                                            .iter()
+                                            .flat_map(|&byte| {
+                                                if byte == b'"' {
+                                                    vec![b'"', b'"']
+                                                } else {
+                                                    vec![byte]
+                                                }
+                                            })
+                                            .collect(),
+                                    );
+                                    out_bytes.push(b'"');
+                                } else {
+                                    out_bytes.push(b'"');
+                                    out_bytes.append(
+                                        &mut raw_value
+                                            .bytes()
+                                            .flat_map(|byte| {
+                                                if byte == b'"' {
+                                                    vec![b'"', b'"']
+                                                } else {
+                                                    vec![byte]
+                                                }
+                                            })
+                                            .collect(),
+                                    );
+                                    out_bytes.push(b'"');
+                                }
+                            }
+                            "str" | "inlineStr" => {
+                                out_bytes.push(b'"');
+                                out_bytes.append(
+                                    &mut raw_value
+                                        .bytes()
+                                        .flat_map(|byte| {
+                                            if byte == b'"' {
+                                                vec![b'"', b'"']
+                                            } else {
+                                                vec![byte]
+                                            }
+                                        })
+                                        .collect(),
+                                );
+
+                                out_bytes.push(b'"');
+                            }
+                            // a blank cell has no value, whatever its `<v>` says (like in `RowIter`)
+                            "bl" => out_bytes.extend_from_slice(null_text),
+                            "e" => {
+                                // errors are written unquoted so they can be told apart from text
+                                match &options.error_text {
+                                    Some(text) => out_bytes.extend_from_slice(text.as_bytes()),
+                                    None => out_bytes.extend_from_slice(raw_value.as_bytes()),
+                                }
+                            }
+                            _ if is_date(&cell_style) && raw_value.parse::<f64>().is_ok() => {
+                                let num = raw_value.parse::<f64>().unwrap();
+                                let date_string =
+                                    match utils::excel_number_to_date(num, date_system) {
+                                        utils::DateConversion::Date(date) => {
+                                            format_date(date, options.date_format.as_deref())
+                                        }
+                                        utils::DateConversion::DateTime(date) => {
+                                            date.format("%Y-%m-%d %H:%M:%S").to_string()
+                                        }
+                                        utils::DateConversion::Time(time) => {
+                                            time.format("%H:%M:%S").to_string()
+                                        }
+                                        utils::DateConversion::Number(num) => {
+                                            format!("Invalid date {}", num)
+                                        }
+                                    };
+                                // a custom date format can produce anything, so the date is quoted
+                                // like a string whenever it would otherwise break the record
+                                if date_string.contains(&[',', '"', '\n', '\r'][..]) {
+                                    out_bytes.push(b'"');
+                                    out_bytes.extend_from_slice(
+                                        date_string.replace('"', "\"\"").as_bytes(),
+                                    );
+                                    out_bytes.push(b'"');
+                                } else {
+                                    out_bytes.append(&mut date_string.into_bytes());
+                                }
+                            }
+                            _ if is_general(&cell_style) => {
+                                out_bytes.push(b'"');
+                                out_bytes.extend_from_slice(general_number(&raw_value).as_bytes());
+                                out_bytes.push(b'"');
+                            }
+                            _ => {
+                                out_bytes.push(b'"');
+                                out_bytes
+                                    .append(&mut raw_value.as_bytes().escape_ascii().collect());
+                                out_bytes.push(b'"');
+                            }
+                        };
+                    }
+                    in_value = false;
+                    in_inline_text = false;
+                }
+                Ok(Event::End(ref e)) if e.name() == b"row" => {
+                    if options.trim_trailing_empty {
+                        out_bytes.truncate(values_end);
+                        col = num_cols;
+                    }
+                    while col < num_cols {
+                        col += 1;
+                        if fields > 0 {
+                            out_bytes.push(b',');
+                        }
+                        out_bytes.extend_from_slice(null_text);
+                        fields += 1;
+                    }
+                    cells_written += fields as u64;
+                    limits.check_cells(cells_written)?;
+                    out_bytes.extend_from_slice(options.line_terminator.as_bytes());
+                    out.write_all(&out_bytes)?;
+                    out_bytes.clear();
+                    rows_written += 1;
+                    if rows_written - last_progress == PROGRESS_INTERVAL {
+                        progress(rows_written);
+                        last_progress = rows_written;
+                    }
+                }
+                Ok(Event::Eof) => break,
+                Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
+                _ => (),
+            }
+            buf.clear();
+        }
+        out.write_all(&out_bytes)?;
+        if rows_written != last_progress || rows_written == 0 {
+            progress(rows_written);
+        }
+        Ok(())
+    }
+}
+
+/// Options for `Worksheet::write_csv_with_options`. The defaults produce the same output as
+/// `Worksheet::write_csv`.
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    /// Text written for empty (i.e., missing) cells instead of an empty field. Use something like
+    /// `NULL` or `\N` when loading the csv into a database that distinguishes between null values
+    /// and empty strings.
+    pub null_text: Option<String>,
+    /// Text written for cells holding an error (e.g., `#REF!` or `#N/A`) instead of the error
+    /// itself. Errors are written without quotes, so they can be told apart from text that
+    /// happens to look like an error. Use `Some("".to_string())` to leave error cells empty.
+    pub error_text: Option<String>,
+    /// Written at the end of every row. Defaults to `\n`; use `\r\n` for Excel and other Windows
+    /// tools.
+    pub line_terminator: String,
+    /// How dates are written, as a `chrono` format string (e.g., `%d/%m/%Y` or `%B %e, %Y`).
+    /// Defaults to `%Y-%m-%d`, which is also used if the format is not valid. Dates are written
+    /// without quotes unless the formatted date contains a comma, a quote or a line break. Only
+    /// affects cells holding a date without a time.
+    pub date_format: Option<String>,
+    /// End every row after its last cell with a value, instead of padding it with empty fields
+    /// (or `null_text`) up to the width of the sheet. Rows then have different numbers of fields,
+    /// which keeps sparse sheets small but is not what every csv reader expects. Off by default.
+    pub trim_trailing_empty: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            null_text: None,
+            error_text: None,
+            line_terminator: "\n".to_string(),
+            date_format: None,
+            trim_trailing_empty: false,
+        }
+    }
+}
+
+/// How a worksheet is displayed in Excel (see `Worksheet::view_settings`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewSettings {
+    /// Are gridlines shown? Defaults to true.
+    pub show_grid_lines: bool,
+    /// Are the row and column headings (1, 2, 3... and A, B, C...) shown? Defaults to true.
+    pub show_row_col_headers: bool,
+    /// Zoom level in percent. Defaults to 100.
+    pub zoom_scale: u16,
+}
+
+impl Default for ViewSettings {
+    fn default() -> Self {
+        ViewSettings {
+            show_grid_lines: true,
+            show_row_col_headers: true,
+            zoom_scale: 100,
+        }
+    }
+}
+
+/// The orientation of a printed page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Orientation {
+    Portrait,
+    Landscape,
+}
+
+/// The print settings of a worksheet (see `Worksheet::page_setup`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageSetup {
+    /// Defaults to `Orientation::Portrait`.
+    pub orientation: Orientation,
+    /// The paper size id (e.g., 1 = Letter, 9 = A4; see section 18.3.1.63 of ISO/IEC 29500).
+    /// Defaults to 1.
+    pub paper_size: u16,
+    /// Print scaling in percent. Defaults to 100.
+    pub scale: u16,
+    /// Left margin in inches. Defaults to 0.7 (Excel's "Normal" margins).
+    pub left_margin: f64,
+    /// Right margin in inches. Defaults to 0.7.
+    pub right_margin: f64,
+    /// Top margin in inches. Defaults to 0.75.
+    pub top_margin: f64,
+    /// Bottom margin in inches. Defaults to 0.75.
+    pub bottom_margin: f64,
+}
+
+impl Default for PageSetup {
+    fn default() -> Self {
+        PageSetup {
+            orientation: Orientation::Portrait,
+            paper_size: 1,
+            scale: 100,
+            left_margin: 0.7,
+            right_margin: 0.7,
+            top_margin: 0.75,
+            bottom_margin: 0.75,
+        }
+    }
+}
+
+/// The used area of a worksheet (see `Worksheet::dimension`). Rows and columns are 1-based and
+/// inclusive, so `B2:F20` has a `first_row` of 2, a `first_col` of 2 (B), a `last_row` of 20 and
+/// a `last_col` of 6 (F).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dimension {
+    pub first_row: u32,
+    pub last_row: u32,
+    pub first_col: u16,
+    pub last_col: u16,
+}
+
+impl Dimension {
+    /// Parse a range like `B2:F20` (or a single cell like `A1`). Returns `None` if it is not a
+    /// valid range.
+    fn from_range(range: &str) -> Option<Self> {
+        let (start, end) = range.split_once(':').unwrap_or((range, range));
+        let (first_col, first_row) = utils::parse_reference(start)?;
+        let (last_col, last_row) = utils::parse_reference(end)?;
+        Some(Dimension {
+            first_row: cmp::min(first_row, last_row),
+            last_row: cmp::max(first_row, last_row),
+            first_col: cmp::min(first_col, last_col),
+            last_col: cmp::max(first_col, last_col),
+        })
+    }
+
+    /// Grow this dimension so that it includes the cell at `(col, row)`.
+    fn include(&mut self, col: u16, row: u32) {
+        self.first_row = cmp::min(self.first_row, row);
+        self.last_row = cmp::max(self.last_row, row);
+        self.first_col = cmp::min(self.first_col, col);
+        self.last_col = cmp::max(self.last_col, col);
+    }
+}
+
+/// The properties of a range of columns that share the same settings (see
+/// `Worksheet::columns_info`). Columns are 1-based, as in `col2num`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnInfo {
+    /// The first column in the range.
+    pub first_col: u16,
+    /// The last column in the range (the same as `first_col` for a single column).
+    pub last_col: u16,
+    /// The width of the columns in characters, if recorded.
+    pub width: Option<f64>,
+    /// Are the columns hidden (e.g., part of a collapsed group)?
+    pub hidden: bool,
+    /// The outline (grouping) level of the columns; 0 if they are not part of a group.
+    pub outline_level: u8,
+    /// Is the group of columns next to these collapsed? Like for rows, this is set on the summary
+    /// column of a group, not on the hidden columns.
+    pub collapsed: bool,
+}
+
+/// Options for `Worksheet::write_jsonl`.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone)]
+pub struct JsonLinesOptions {
+    /// The (1-based) row that holds the column names used as keys. Defaults to 1.
+    pub header_row: usize,
+}
+
+#[cfg(feature = "serde")]
+impl Default for JsonLinesOptions {
+    fn default() -> Self {
+        JsonLinesOptions { header_row: 1 }
+    }
+}
+
+/// `ExcelValue` is the enum that holds the equivalent "rust value" of a `Cell`s "raw_value."
+///
+/// Values implement `Eq` and `Hash`, so they can be used as keys in a `HashMap` (e.g., to count
+/// the distinct values in a column). Numbers are compared as floats, so `0.0` and `-0.0` are the
+/// same key. The one caveat is NaN: since NaN is not equal to itself, a `Number(NaN)` key can be
+/// inserted but never found again. Excel does not store NaN, so this only matters for values you
+/// make yourself.
+///
+/// When displayed, numbers are rounded to the 15 significant digits Excel keeps, without trailing
+/// zeros (so `3.0` is `3` and `0.1 + 0.2` is `0.3`). Numbers of 1e15 or more (in absolute value)
+/// and below 1e-9 are shown in Excel's scientific notation (e.g., `1E+20` or `-2.5E-12`) instead
+/// of with long runs of zeros; anything in between is shown in plain decimal notation.
+///
+///     use xl::ExcelValue;
+///
+///     assert_eq!(ExcelValue::Number(100000000000000.0).to_string(), "100000000000000");
+///     assert_eq!(ExcelValue::Number(1e20).to_string(), "1E+20");
+///     assert_eq!(ExcelValue::Number(0.000123).to_string(), "0.000123");
+///     assert_eq!(ExcelValue::Number(-2.5e-12).to_string(), "-2.5E-12");
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExcelValue<'a> {
+    Bool(bool),
+    Date(NaiveDate),
+    DateTime(NaiveDateTime),
+    Error(String),
+    None,
+    Number(f64),
+    String(Cow<'a, str>),
+    Time(NaiveTime),
+}
+
+impl fmt::Display for ExcelValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExcelValue::Bool(b) => write!(f, "{}", b),
+            ExcelValue::Date(d) => write!(f, "{}", d),
+            ExcelValue::DateTime(d) => write!(f, "{}", d),
+            ExcelValue::Error(e) => write!(f, "#{}", e),
+            ExcelValue::None => write!(f, ""),
+            ExcelValue::Number(n) => write!(f, "{}", general_format(*n)),
+            ExcelValue::String(s) => write!(f, "\"{}\"", s),
+            ExcelValue::Time(t) => write!(f, "\"{}\"", t),
+        }
+    }
+}
+
+impl Eq for ExcelValue<'_> {}
+
+impl Hash for ExcelValue<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match self {
+            ExcelValue::Bool(b) => b.hash(state),
+            ExcelValue::Date(d) => d.hash(state),
+            ExcelValue::DateTime(d) => d.hash(state),
+            ExcelValue::Error(e) => e.hash(state),
+            ExcelValue::None => (),
+            // 0.0 == -0.0, so they have to hash the same even though their bits differ
+            ExcelValue::Number(n) if *n == 0.0 => 0u64.hash(state),
+            ExcelValue::Number(n) => n.to_bits().hash(state),
+            ExcelValue::String(s) => s.hash(state),
+            ExcelValue::Time(t) => t.hash(state),
+        }
+    }
+}
+
+/// One of Excel's error values, e.g., the `#N/A` held by an `ExcelValue::Error`. Convert the text
+/// of an error with `ExcelError::from`; anything that is not one of the seven standard errors
+/// (e.g., the newer `#SPILL!`) becomes `Other`.
+///
+/// # Example usage
+///
+///     use xl::ExcelError;
+///
+///     let error = ExcelError::from("#N/A");
+///     assert_eq!(error, ExcelError::NA);
+///     assert_eq!(error.code(), 2042);
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ExcelError {
+    /// `#NULL!`: the intersection of two ranges is empty
+    Null,
+    /// `#DIV/0!`: division by zero
+    Div0,
+    /// `#VALUE!`: an argument has the wrong type
+    Value,
+    /// `#REF!`: a reference to a cell that does not exist
+    Ref,
+    /// `#NAME?`: an unknown function or name
+    Name,
+    /// `#NUM!`: a number that is invalid or out of range
+    Num,
+    /// `#N/A`: a value is not available
+    NA,
+    /// Any other error, with its text
+    Other(String),
+}
+
+impl ExcelError {
+    /// The numeric code Excel uses for this error internally (e.g., in VBA's `CVErr`), such as
+    /// 2042 for `#N/A`. `Other` errors have no standard code, so they return 0.
+    pub fn code(&self) -> u16 {
+        match self {
+            ExcelError::Null => 2000,
+            ExcelError::Div0 => 2007,
+            ExcelError::Value => 2015,
+            ExcelError::Ref => 2023,
+            ExcelError::Name => 2029,
+            ExcelError::Num => 2036,
+            ExcelError::NA => 2042,
+            ExcelError::Other(_) => 0,
+        }
+    }
+}
+
+impl From<&str> for ExcelError {
+    fn from(s: &str) -> Self {
+        match s {
+            "#NULL!" => ExcelError::Null,
+            "#DIV/0!" => ExcelError::Div0,
+            "#VALUE!" => ExcelError::Value,
+            "#REF!" => ExcelError::Ref,
+            "#NAME?" => ExcelError::Name,
+            "#NUM!" => ExcelError::Num,
+            "#N/A" => ExcelError::NA,
+            _ => ExcelError::Other(s.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ExcelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            ExcelError::Null => "#NULL!",
+            ExcelError::Div0 => "#DIV/0!",
+            ExcelError::Value => "#VALUE!",
+            ExcelError::Ref => "#REF!",
+            ExcelError::Name => "#NAME?",
+            ExcelError::Num => "#NUM!",
+            ExcelError::NA => "#N/A",
+            ExcelError::Other(s) => s,
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// The category of a number format, as listed in the Number tab of Excel's Format Cells dialog
+/// (see `Cell::format_category`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatCategory {
+    /// The General format, which is also used for cells without a style
+    General,
+    /// Plain numbers, e.g., `0.00` or `#,##0`
+    Number,
+    /// Numbers with a currency symbol, e.g., `"$"#,##0.00`
+    Currency,
+    /// Currency-style formats that line up symbols and decimal points, e.g.,
+    /// `_(* #,##0.00_);_(* \(#,##0.00\);_(* "-"??_);_(@_)`
+    Accounting,
+    /// Dates, including formats that show both a date and a time, e.g., `mm-dd-yy` or
+    /// `m/d/yy h:mm`
+    Date,
+    /// Times and durations, e.g., `h:mm AM/PM` or `[h]:mm:ss`
+    Time,
+    /// Percentages, e.g., `0.00%`
+    Percentage,
+    /// Fractions, e.g., `# ?/?`
+    Fraction,
+    /// Scientific notation, e.g., `0.00E+00`
+    Scientific,
+    /// Text, i.e., the `@` format
+    Text,
+    /// Anything else
+    Custom,
+}
+
+/// The kind of value a string should be parsed into by `ExcelValue::parse_as`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CellType {
+    Bool,
+    Date,
+    DateTime,
+    Error,
+    Number,
+    String,
+    Time,
+}
+
+impl ExcelValue<'static> {
+    /// Parse `s` into the given kind of value, rather than inferring it like `from_str` does.
+    /// Booleans accept (case-insensitive) `true`/`false`, dates use `YYYY-MM-DD`, date-times use
+    /// `YYYY-MM-DD HH:MM:SS` (or a `T` separator), and times use `HH:MM:SS`. Returns an error
+    /// message if `s` cannot be parsed as the requested type.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::{CellType, ExcelValue};
+    ///
+    ///     let v = ExcelValue::parse_as("42", CellType::String).unwrap();
+    ///     assert_eq!(v, ExcelValue::String("42".into()));
+    ///     assert!(ExcelValue::parse_as("forty-two", CellType::Number).is_err());
+    pub fn parse_as(s: &str, cell_type: CellType) -> Result<Self, String> {
+        let err = |kind: &str| format!("cannot parse {:?} as {}", s, kind);
+        match cell_type {
+            CellType::Bool => match s.to_ascii_lowercase().as_str() {
+                "true" => Ok(ExcelValue::Bool(true)),
+                "false" => Ok(ExcelValue::Bool(false)),
+                _ => Err(err("a boolean")),
+            },
+            CellType::Date => NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .map(ExcelValue::Date)
+                .map_err(|_| err("a date")),
+            CellType::DateTime => NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f")
+                .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f"))
+                .map(ExcelValue::DateTime)
+                .map_err(|_| err("a date-time")),
+            CellType::Error => Ok(ExcelValue::Error(s.to_string())),
+            CellType::Number => match s.trim().parse::<f64>() {
+                Ok(n) if n.is_finite() => Ok(ExcelValue::Number(n)),
+                _ => Err(err("a number")),
+            },
+            CellType::String => Ok(ExcelValue::String(Cow::Owned(s.to_string()))),
+            CellType::Time => NaiveTime::parse_from_str(s, "%H:%M:%S%.f")
+                .map(ExcelValue::Time)
+                .map_err(|_| err("a time")),
+        }
+    }
+}
+
+/// Infer an `ExcelValue` from a string, the way a spreadsheet does when a value is typed into a
+/// cell. The first of these that matches wins:
+///
+/// 1. an empty string is `ExcelValue::None`
+/// 2. anything that parses as a (finite) f64 is a `Number`
+/// 3. `true` or `false` (case-insensitive) is a `Bool`
+/// 4. `YYYY-MM-DD` is a `Date`
+/// 5. `YYYY-MM-DD HH:MM:SS` (or with a `T` separator) is a `DateTime`
+/// 6. `HH:MM:SS` is a `Time`
+/// 7. everything else is a `String`
+///
+/// Parsing never fails.
+///
+/// # Example usage
+///
+///     use xl::ExcelValue;
+///
+///     let v: ExcelValue = "3.5".parse().unwrap();
+///     assert_eq!(v, ExcelValue::Number(3.5));
+///     let v: ExcelValue = "TRUE".parse().unwrap();
+///     assert_eq!(v, ExcelValue::Bool(true));
+///     let v: ExcelValue = "hello".parse().unwrap();
+///     assert_eq!(v, ExcelValue::String("hello".into()));
+impl FromStr for ExcelValue<'static> {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Ok(ExcelValue::None);
+        }
+        let inferred = [
+            CellType::Number,
+            CellType::Bool,
+            CellType::Date,
+            CellType::DateTime,
+            CellType::Time,
+        ]
+        .iter()
+        .find_map(|&cell_type| ExcelValue::parse_as(s, cell_type).ok());
+        Ok(inferred.unwrap_or_else(|| ExcelValue::String(Cow::Owned(s.to_string()))))
+    }
+}
+
+/// Convert an `ExcelValue` into JSON. Dates and times become ISO 8601 strings, errors become their
+/// error string (e.g., "#N/A"), empty cells and non-finite numbers become `null`.
+#[cfg(feature = "serde")]
+impl From<&ExcelValue<'_>> for serde_json::Value {
+    fn from(value: &ExcelValue) -> Self {
+        match value {
+            ExcelValue::Bool(b) => serde_json::Value::Bool(*b),
+            ExcelValue::Date(d) => serde_json::Value::String(d.to_string()),
+            ExcelValue::DateTime(d) => {
+                serde_json::Value::String(d.format("%Y-%m-%dT%H:%M:%S").to_string())
+            }
+            ExcelValue::Error(e) => serde_json::Value::String(e.to_string()),
+            ExcelValue::None => serde_json::Value::Null,
+            ExcelValue::Number(n) => match serde_json::Number::from_f64(*n) {
+                Some(n) => serde_json::Value::Number(n),
+                None => serde_json::Value::Null,
+            },
+            ExcelValue::String(s) => serde_json::Value::String(s.to_string()),
+            ExcelValue::Time(t) => serde_json::Value::String(t.to_string()),
+        }
+    }
+}
+
+/// The kind of formula a cell holds (see section 18.18.6 of ISO/IEC 29500).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FormulaType {
+    /// A formula that only applies to its own cell
+    Normal,
+    /// A formula that is shared by a range of cells (and stored only once)
+    Shared,
+    /// An array formula, whose results fill (spill over) a range of cells
+    Array,
+    /// A what-if analysis data table
+    DataTable,
+}
+
+#[derive(Debug)]
+pub struct Cell<'a> {
+    /// The value you get by converting the raw_value (a string) into a Rust value
+    pub value: ExcelValue<'a>,
+    /// The formula (may be "empty") of the cell. For cells that use a shared formula, this is the
+    /// shared formula with its relative references moved to this cell (e.g., `A2*2` in B2 when
+    /// B1 holds `A1*2`). Note that this only works if the cell that defines the shared formula
+    /// was read first, which is not the case when it is skipped with `RowIter::skip_header_rows`
+    /// or `Iterator::nth`; such cells get an empty formula.
+    pub formula: String,
+    /// The kind of formula in the cell (`None` if the cell does not have a formula)
+    pub formula_type: Option<FormulaType>,
+    /// The range of cells (e.g., `A1:A10`) the cell's formula applies to. This is only set on the
+    /// top-left cell of an array formula, the cell that defines a shared formula, or a data
+    /// table; it is empty otherwise. For an array formula, every cell in the range holds one of
+    /// the (spilled) results.
+    pub formula_range: String,
+    /// What cell are we looking at? E.g., B3, A1, etc.
+    pub reference: String,
+    /// The cell style (e.g., the style you see in Excel by hitting Ctrl+1 and going to the
+    /// "Number" tab).
+    pub style: String,
+    /// The type of cell as recorded by Excel (s = string using sharedStrings.xml, str = raw
+    /// string, b = boolean, etc.). This may change from a `String` type to an `Enum` of some sorts
+    /// in the future.
+    pub cell_type: String,
+    /// The raw string value recorded in the xml
+    pub raw_value: String,
+    /// The alignment of the cell's style (if it has one)
+    alignment: Option<&'a Alignment>,
+    /// The index of the cell's style in the `cellXfs` of the workbook's styles (if it has one)
+    style_index: Option<usize>,
+    /// The id of the number format of the cell's style (if it has one)
+    format_id: Option<u32>,
+}
+
+impl Cell<'_> {
+    /// return the alignment settings (horizontal/vertical alignment, wrap text, and rotation) of
+    /// the current cell. Cells without an explicit alignment return `Alignment::default()`.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let row16 = ws.rows(&mut wb).unwrap().nth(15).unwrap();
+    ///     assert_eq!(row16[7].alignment().horizontal, Some("center".to_string()));
+    pub fn alignment(&self) -> Alignment {
+        match self.alignment {
+            Some(alignment) => alignment.clone(),
+            None => Alignment::default(),
+        }
+    }
+
+    /// return the index of the current cell's style (its `s` attribute), i.e., its position in
+    /// the `cellXfs` of the workbook's styles. Unlike `style`, which is only the number format,
+    /// cells with the same index share every part of their formatting, so this can be used to
+    /// group cells by style. Cells without a style return `None`.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/mixed_types.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get(1).unwrap();
+    ///     let row1 = ws.rows(&mut wb).unwrap().next().unwrap();
+    ///     assert_eq!(row1[0].style_index(), None);
+    ///     assert_eq!(row1[1].style_index(), Some(1));
+    pub fn style_index(&self) -> Option<usize> {
+        self.style_index
+    }
+
+    /// return the value of the current cell as a boolean. Besides actual boolean cells, this also
+    /// recognizes (case-insensitive) `true`/`false`, `yes`/`no`, and `1`/`0` strings, as well as
+    /// the numbers 1 and 0, since boolean-ish columns are often stored that way. Returns `None` for
+    /// anything else. The cell's `value` itself is never coerced.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/bools.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get(1).unwrap();
+    ///     let row = ws.rows(&mut wb).unwrap().next().unwrap();
+    ///     assert_eq!(row[2].as_bool(), Some(true)); // "TRUE" stored as a string
+    ///     assert_eq!(row[8].as_bool(), None); // "maybe"
+    pub fn as_bool(&self) -> Option<bool> {
+        match &self.value {
+            ExcelValue::Bool(b) => Some(*b),
+            ExcelValue::Number(n) if *n == 1.0 => Some(true),
+            ExcelValue::Number(n) if *n == 0.0 => Some(false),
+            ExcelValue::String(s) => match s.trim().to_ascii_lowercase().as_str() {
+                "true" | "yes" | "1" => Some(true),
+                "false" | "no" | "0" => Some(false),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// return the value of the current cell as a number, leniently: besides number cells, this
+    /// also accepts text that looks like a number (e.g., `" 2.5 "`), which is common when a
+    /// column mixes types. Returns `None` for anything else, including text such as `NaN` that
+    /// does not hold a finite number. Like `as_bool`, this coerces; the cell's `value` does not.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/mixed_types.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get(1).unwrap();
+    ///     let rows = ws.to_vec(&mut wb).unwrap();
+    ///     assert_eq!(rows[0][0].get_f64(), Some(1.5));
+    ///     assert_eq!(rows[1][0].get_f64(), Some(2.5)); // " 2.5 " stored as a string
+    ///     assert_eq!(rows[2][0].get_f64(), None); // "abc"
+    pub fn get_f64(&self) -> Option<f64> {
+        match &self.value {
+            ExcelValue::Number(n) => Some(*n),
+            ExcelValue::String(s) => s.trim().parse::<f64>().ok().filter(|n| n.is_finite()),
+            _ => None,
+        }
+    }
+
+    /// return the value of the current cell as a date, leniently: besides date (and date-time)
+    /// cells, this also accepts text holding an ISO-8601 date such as `2020-06-18`, optionally
+    /// followed by a time (`2020-06-18T13:45:00`), whose time is dropped. Plain numbers are not
+    /// treated as dates, since nothing says they are meant to be one. Returns `None` for anything
+    /// else.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///     use chrono::NaiveDate;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/mixed_types.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get(1).unwrap();
+    ///     let rows = ws.to_vec(&mut wb).unwrap();
+    ///     let date = NaiveDate::from_ymd(2020, 6, 18);
+    ///     assert_eq!(rows[0][1].get_date(), Some(date)); // a date cell
+    ///     assert_eq!(rows[1][1].get_date(), Some(date)); // "2020-06-18" stored as a string
+    pub fn get_date(&self) -> Option<NaiveDate> {
+        match &self.value {
+            ExcelValue::Date(d) => Some(*d),
+            ExcelValue::DateTime(d) => Some(d.date()),
+            ExcelValue::String(s) => {
+                let s = s.trim();
+                let (date, rest) = s.split_at(s.char_indices().nth(10).map_or(s.len(), |(i, _)| i));
+                if !(rest.is_empty() || rest.starts_with('T') || rest.starts_with(' ')) {
+                    return None;
+                }
+                NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+            }
+            _ => None,
+        }
+    }
+
+    /// return the date (or time) in the current cell formatted the way Excel displays it, using
+    /// the cell's own number format (`style`): a cell with the format `d-mmm-yy` gives `13-Mar-22`
+    /// rather than the `2022-03-13` of `ExcelValue`'s `Display`. Month and day names are in
+    /// English. Returns `None` if the cell does not hold a date, date-time or time, or if its
+    /// format cannot be reproduced (e.g., elapsed times like `[h]:mm`).
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/date_formats.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get(1).unwrap();
+    ///     let rows = ws.to_vec(&mut wb).unwrap();
+    ///     assert_eq!(rows[0][0].style, "d-mmm-yy");
+    ///     assert_eq!(rows[0][0].display_date().unwrap(), "13-Mar-22");
+    pub fn display_date(&self) -> Option<String> {
+        let datetime = match &self.value {
+            ExcelValue::Date(d) => d.and_hms(0, 0, 0),
+            ExcelValue::DateTime(d) => *d,
+            // a time is the fraction of the day after Excel's day 0
+            ExcelValue::Time(t) => NaiveDate::from_ymd(1899, 12, 31).and_time(*t),
+            _ => return None,
+        };
+        let parts = date_format_parts(&self.style)?;
+        Some(format_date_parts(&parts, &datetime))
+    }
+
+    /// return true if the current cell holds a number that is displayed as a percentage, i.e.,
+    /// whose number format (`style`) contains a `%` that is not part of a quoted or escaped
+    /// literal (e.g., `0%` or `0.00%`, but not `0.0"%"`).
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/percentages.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get(1).unwrap();
+    ///     let row1 = ws.rows(&mut wb).unwrap().next().unwrap();
+    ///     assert!(row1[0].is_percentage()); // 0%
+    ///     assert!(!row1[2].is_percentage()); // 0.0"%"
+    pub fn is_percentage(&self) -> bool {
+        matches!(self.value, ExcelValue::Number(_)) && is_percentage_format(&self.style)
+    }
+
+    /// return the value of the current cell as the percentage that Excel displays (e.g., 5.0 for
+    /// a value of 0.05), or `None` if the cell is not a percentage (see `is_percentage`).
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/percentages.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get(1).unwrap();
+    ///     let row1 = ws.rows(&mut wb).unwrap().next().unwrap();
+    ///     assert_eq!(row1[0].as_percentage(), Some(5.0));
+    pub fn as_percentage(&self) -> Option<f64> {
+        match self.value {
+            ExcelValue::Number(n) if self.is_percentage() => {
+                // moving the decimal point in the raw value avoids the rounding error that comes
+                // with multiplying (0.05 * 100.0 is 5.000000000000001)
+                let raw = self.raw_value.to_ascii_lowercase();
+                let shifted = match raw.split_once('e') {
+                    Some((mantissa, exp)) => exp
+                        .parse::<i32>()
+                        .ok()
+                        .map(|exp| format!("{}e{}", mantissa, exp + 2)),
+                    None => Some(format!("{}e2", raw)),
+                };
+                Some(shifted.and_then(|s| s.parse().ok()).unwrap_or(n * 100.0))
+            }
+            _ => None,
+        }
+    }
+
+    /// return true if the current cell holds a number without a fractional part (e.g., `3.0`),
+    /// which Excel displays as an integer under the General format.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let row1 = ws.rows(&mut wb).unwrap().next().unwrap();
+    ///     assert!(row1[0].is_integer());
+    pub fn is_integer(&self) -> bool {
+        match self.value {
+            ExcelValue::Number(n) => n.is_finite() && n.fract() == 0.0,
+            _ => false,
+        }
+    }
+
+    /// return the category of the number format of the current cell (the kind of value it is
+    /// meant to display). Built-in formats are classified by their id, which also covers the
+    /// built-in formats that depend on the locale (e.g., currency formats 5 to 8) and so do not
+    /// have a fixed format code. Custom formats are classified by looking at their format code,
+    /// and ones that do not look like any of the categories are `FormatCategory::Custom`. Cells
+    /// without a style use the General format.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::{FormatCategory, Workbook};
+    ///
+    ///     let mut wb = Workbook::open("tests/data/number_formats.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get(1).unwrap();
+    ///     let row1 = ws.rows(&mut wb).unwrap().next().unwrap();
+    ///     assert_eq!(row1[1].format_category(), FormatCategory::Number); // 0.00
+    ///     assert_eq!(row1[6].format_category(), FormatCategory::Date); // mm-dd-yy
+    pub fn format_category(&self) -> FormatCategory {
+        format_category(self.format_id, &self.style)
+    }
+
+    /// return the row/column coordinates of the current cell
+    pub fn coordinates(&self) -> (u16, u32) {
+        utils::parse_reference(&self.reference).unwrap()
+    }
+}
+
+/// A row of the worksheet. The first field holds the cells of the row and the second holds the
+/// (1-based) row number, which is also available as `number`. Additional information recorded on
+/// the `<row>` element itself is available through methods like `hidden` and `outline_level`.
+#[derive(Debug)]
+pub struct Row<'a>(pub Vec<Cell<'a>>, pub usize, RowProperties);
+
+/// Properties read from the attributes of a `<row>` element. Rows that were not present in the
+/// xml (i.e., "simulated" empty rows) use the default properties.
+#[derive(Debug, Default)]
+struct RowProperties {
+    hidden: bool,
+    outline_level: u8,
+    collapsed: bool,
+    /// the style of the row (`s` with `customFormat="1"`), which applies to its cells that do not
+    /// have a style of their own
+    style_index: Option<usize>,
+    /// all the attributes of the element, if `RowIter::with_row_attributes` asked for them
+    attributes: HashMap<String, String>,
+}
+
+impl<'a> Row<'a> {
+    /// Is this row hidden (e.g., filtered out or part of a collapsed group)?
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let row1 = ws.rows(&mut wb).unwrap().next().unwrap();
+    ///     assert!(!row1.hidden());
+    pub fn hidden(&self) -> bool {
+        self.2.hidden
+    }
+
+    /// The number of this row as Excel shows it (1-based). Use this rather than counting the rows
+    /// yourself (e.g., with `enumerate`) when reporting a problem with a row, since it stays right
+    /// when rows are skipped, such as with `RowIter::skip_header_rows` or `Worksheet::rows_from`.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let row = ws.rows_from(&mut wb, 5).unwrap().next().unwrap();
+    ///     assert_eq!(row.number(), 5);
+    pub fn number(&self) -> u32 {
+        self.1 as u32
+    }
+
+    /// The outline (grouping) level of this row. Rows that are not part of a group have an outline
+    /// level of 0.
+    pub fn outline_level(&self) -> u8 {
+        self.2.outline_level
+    }
+
+    /// Is the group of rows next to this one collapsed? Excel puts this flag on the summary row of
+    /// a group (by default, the row right below it), not on the hidden rows themselves.
+    pub fn collapsed(&self) -> bool {
+        self.2.collapsed
+    }
+
+    /// All the attributes of the `<row>` element this row was read from (e.g., `ht` for the row
+    /// height or `s` for the row style), keyed by attribute name. This is an escape hatch for
+    /// properties that have no method of their own. The attributes are only collected if the
+    /// rows came from a `RowIter` created `with_row_attributes`; otherwise (and for rows without
+    /// a `<row>` element) the map is empty.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/hidden_rows.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get(1).unwrap();
+    ///     let row1 = ws.rows(&mut wb).unwrap().with_row_attributes().next().unwrap();
+    ///     assert_eq!(row1.attributes()["r"], "1");
+    pub fn attributes(&self) -> &HashMap<String, String> {
+        &self.2.attributes
+    }
+
+    /// The number of cells in this row that hold a value (i.e., whose value is not
+    /// `ExcelValue::None`). Compare with the number of cells in the row to see how "full" it is.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/7_nulls.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get(1).unwrap();
+    ///     let header = ws.rows(&mut wb).unwrap().next().unwrap();
+    ///     assert_eq!(header.0.len(), 6);
+    ///     assert_eq!(header.populated_count(), 5);
+    pub fn populated_count(&self) -> usize {
+        self.0
+            .iter()
+            .filter(|cell| cell.value != ExcelValue::None)
+            .count()
+    }
+
+    /// Iterate over the cells of this row as (reference, value) pairs, e.g., for logging each
+    /// cell as `A5=42`.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::{ExcelValue, Workbook};
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let row1 = ws.rows(&mut wb).unwrap().next().unwrap();
+    ///     let (reference, value) = row1.iter_with_refs().nth(1).unwrap();
+    ///     assert_eq!(reference, "B1");
+    ///     assert_eq!(value, &ExcelValue::Number(2.0));
+    pub fn iter_with_refs(&self) -> impl Iterator<Item = (&str, &ExcelValue<'_>)> {
+        self.0
+            .iter()
+            .map(|cell| (cell.reference.as_str(), &cell.value))
+    }
+
+    /// Copy the values of this row into an array of exactly `N` values, for destructuring rows
+    /// of a sheet whose layout is known up front. Returns `None` if the row does not have exactly
+    /// `N` cells. Keep in mind that every row is as wide as the sheet (see `Worksheet::rows`),
+    /// not just as wide as its last populated cell. The values are cloned, so the row itself can
+    /// still be used afterwards.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::{ExcelValue, Workbook};
+    ///
+    ///     let mut wb = Workbook::open("tests/data/7_nulls.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get(1).unwrap();
+    ///     let row2 = ws.rows(&mut wb).unwrap().nth(1).unwrap();
+    ///     let [id, name, ..] = row2.to_array::<6>().unwrap();
+    ///     assert_eq!(id, ExcelValue::Number(0.0));
+    ///     assert_eq!(name.to_string(), "\"foo\"");
+    ///     assert!(row2.to_array::<5>().is_none());
+    pub fn to_array<const N: usize>(&self) -> Option<[ExcelValue<'a>; N]> {
+        let values: Vec<_> = self.0.iter().map(|cell| cell.value.clone()).collect();
+        <[ExcelValue<'a>; N]>::try_from(values).ok()
+    }
+}
+
+/// A fully materialized worksheet that can be indexed like a spreadsheet. Indexes are `(row,
+/// column)` tuples and are **1-based** to match Excel, so `sheet[(3, 2)]` is cell B3. Indexing out
+/// of range panics (like indexing a slice); use `get` if you would rather get an `Option`.
+///
+/// # Example usage
+///
+///     use xl::{Sheet2D, Workbook};
+///
+///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+///     let sheets = wb.sheets();
+///     let ws = sheets.get("Sheet1").unwrap();
+///     let sheet = Sheet2D::new(ws.to_vec(&mut wb).unwrap());
+///     assert_eq!(sheet[(3, 2)].reference, "B3");
+///     assert!(sheet.get((3, 0)).is_none());
+#[derive(Debug)]
+pub struct Sheet2D<'a> {
+    rows: Vec<Row<'a>>,
+}
+
+impl<'a> Sheet2D<'a> {
+    /// Wrap `rows` (normally obtained from `Worksheet::to_vec`) for random access. The rows are
+    /// expected to be consecutive, as they are when they come from a `RowIter`.
+    pub fn new(rows: Vec<Row<'a>>) -> Self {
+        Sheet2D { rows }
+    }
+
+    /// Return the cell at `(row, column)` (both 1-based) or `None` if it is out of range.
+    pub fn get(&self, (row, col): (u32, u16)) -> Option<&Cell<'a>> {
+        let first_row = self.rows.first()?.1;
+        let row = (row as usize).checked_sub(first_row)?;
+        let col = (col as usize).checked_sub(1)?;
+        self.rows.get(row)?.0.get(col)
+    }
+
+    /// All the rows in the sheet.
+    pub fn rows(&self) -> &[Row<'a>] {
+        &self.rows
+    }
+}
+
+impl<'a> Index<(u32, u16)> for Sheet2D<'a> {
+    type Output = Cell<'a>;
+
+    fn index(&self, (row, col): (u32, u16)) -> &Self::Output {
+        match self.get((row, col)) {
+            Some(cell) => cell,
+            None => panic!("cell (row {}, column {}) is out of range", row, col),
+        }
+    }
+}
+
+impl<'a> Index<u16> for Row<'a> {
+    type Output = Cell<'a>;
+
+    fn index(&self, column_index: u16) -> &Self::Output {
+        &self.0[column_index as usize]
+    }
+}
+
+impl fmt::Display for Row<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let vec = &self.0;
+        for (count, v) in vec.iter().enumerate() {
+            if count != 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", v)?;
+        }
+        write!(f, "")
+    }
+}
+
+impl fmt::Display for Cell<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+pub struct RowIter<'a> {
+    worksheet_reader: SheetReader<'a>,
+    want_row: usize,
+    next_row: Option<Row<'a>>,
+    num_rows: u32,
+    num_cols: u16,
+    done_file: bool,
+    /// the number and properties of a row whose start tag has been read (while skipping ahead)
+    /// but whose cells have not
+    pending_row: Option<(usize, RowProperties)>,
+    /// should we size the sheet using its `<dimension>` element? (false when the dimensions were
+    /// computed up front instead)
+    trust_dimension: bool,
+    /// the formula of each shared formula (by its `si` index) that we have seen so far, along
+    /// with the column and row of the cell it was written for
+    shared_formulas: HashMap<String, (String, u16, u32)>,
+    /// should rows and cells outside of the sheet's dimension be dropped?
+    strict_dimension: bool,
+    /// should every value be returned as a string of its raw xml value, without interpretation?
+    raw_strings: bool,
+    /// the smallest number of cells in a row, whatever the dimension of the sheet says
+    min_columns: u16,
+    /// should the attributes of each `<row>` element be kept (see `Row::attributes`)?
+    row_attributes: bool,
+    /// should numbers in cells with the Text (`@`) number format be returned as strings?
+    text_as_strings: bool,
+    /// should empty rows be simulated after the last `<row>` element, up to the end of the
+    /// sheet's dimension?
+    trailing_fill: bool,
+    /// the name of the worksheet's part in the zip (for error messages)
+    part: String,
+    limits: Limits,
+    /// the number of cells in the rows returned so far
+    cells_read: u64,
+    /// the limit that stopped the iterator early, if any
+    error: Option<XlError>,
+    /// should cells (and rows) that cannot be parsed be given error values instead of panicking?
+    recover_errors: bool,
+}
+
+fn new_cell() -> Cell<'static> {
+    Cell {
+        value: ExcelValue::None,
+        formula: "".to_string(),
+        formula_type: None,
+        formula_range: "".to_string(),
+        reference: "".to_string(),
+        style: "".to_string(),
+        cell_type: "".to_string(),
+        raw_value: "".to_string(),
+        alignment: None,
+        style_index: None,
+        format_id: None,
+    }
+}
+
+fn empty_row(num_cols: u16, this_row: usize) -> Option<Row<'static>> {
+    let mut row = vec![];
+    for n in 0..num_cols {
+        let mut c = new_cell();
+        c.reference.push_str(&utils::num2col(n + 1).unwrap());
+        c.reference.push_str(&this_row.to_string());
+        row.push(c);
+    }
+    Some(Row(row, this_row, RowProperties::default()))
+}
+
+/// Read the row number and properties from the attributes of a `<row>` element.
+fn row_properties(e: &BytesStart, keep_attributes: bool) -> (usize, RowProperties) {
+    read_row_properties(e, keep_attributes, false)
+}
+
+/// Like `row_properties`, but with `recover`, a row number that cannot be read is returned as 0
+/// (and attributes that cannot be read are skipped) instead of panicking.
+fn read_row_properties(
+    e: &BytesStart,
+    keep_attributes: bool,
+    recover: bool,
+) -> (usize, RowProperties) {
+    let mut this_row = 0;
+    let mut props = RowProperties::default();
+    // the row's style is only used when the row says so
+    let mut custom_format = false;
+    e.attributes().for_each(|a| {
+        let a = match a {
+            Ok(a) => a,
+            Err(_) if recover => return,
+            Err(e) => panic!("{:?}", e),
+        };
+        if keep_attributes {
+            props.attributes.insert(
+                String::from_utf8_lossy(a.key).into_owned(),
+                utils::attr_value(&a),
+            );
+        }
+        match a.key {
+            b"r" => match utils::attr_value(&a).parse() {
+                Ok(r) => this_row = r,
+                Err(_) if recover => (),
+                Err(e) => panic!("{:?}", e),
+            },
+            b"hidden" => {
+                props.hidden = xml_bool(&utils::attr_value(&a));
+            }
+            b"outlineLevel" => {
+                if let Ok(level) = utils::attr_value(&a).parse() {
+                    props.outline_level = level;
+                }
+            }
+            b"collapsed" => {
+                props.collapsed = xml_bool(&utils::attr_value(&a));
+            }
+            b"customFormat" => custom_format = xml_bool(&utils::attr_value(&a)),
+            b"s" => props.style_index = utils::attr_value(&a).parse().ok(),
+            _ => (),
+        }
+    });
+    if !custom_format {
+        props.style_index = None;
+    }
+    (this_row, props)
+}
+
+/// A string cell whose value element is present but empty (e.g., `<v></v>` or `<is><t/></is>`)
+/// holds an empty string. This is different from a cell without any value element, which holds
+/// `ExcelValue::None` like a missing cell.
+fn empty_string_value(c: &mut Cell) {
+    if c.value == ExcelValue::None && (c.cell_type == "str" || c.cell_type == "inlineStr") {
+        c.value = ExcelValue::String(Cow::Borrowed(""));
+    }
+}
+
+/// Is row number `row` outside of a sheet with `num_rows` rows (and does that matter)?
+fn beyond_dimension(strict_dimension: bool, num_rows: u32, row: usize) -> bool {
+    strict_dimension && num_rows > 0 && row > num_rows as usize
+}
+
+/// The error a `RowIter` stops with when `recover_errors` is on but the reader cannot get past a
+/// problem in the xml.
+fn unreadable_xml(part: &str, position: usize, e: &quick_xml::Error) -> XlError {
+    let message = format!("could not read {} past position {}: {}", part, position, e);
+    XlError::Io(io::Error::new(io::ErrorKind::InvalidData, message))
+}
+
+/// Pad `row` with empty cells until it is `num_cols` wide.
+fn pad_row(row: &mut Vec<Cell>, num_cols: u16, this_row: usize) {
+    while row.len() < num_cols as usize {
+        let mut cell = new_cell();
+        cell.reference
+            .push_str(&utils::num2col(row.len() as u16 + 1).unwrap());
+        cell.reference.push_str(&this_row.to_string());
+        row.push(cell);
+    }
+}
+
+impl<'a> RowIter<'a> {
+    /// Pair every row with its Excel row number (see `Row::number`). Unlike `enumerate`, which
+    /// counts the rows it has seen from 0, the numbers are the ones Excel shows, so they stay
+    /// right after skipping rows. This borrows the iterator rather than taking it, so `error` can
+    /// still tell you afterwards whether a limit cut the rows short.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let mut rows = ws.rows(&mut wb).unwrap().skip_header_rows(1);
+    ///     for (number, row) in rows.numbered() {
+    ///         if row[0].get_f64().is_none() {
+    ///             println!("bad value on row {}", number);
+    ///         }
+    ///     }
+    ///     assert!(rows.error().is_none());
+    pub fn numbered(&mut self) -> impl Iterator<Item = (u32, Row<'a>)> + '_ {
+        self.map(|row| (row.number(), row))
+    }
+
+    /// Skip the first `n` rows of the worksheet (e.g., header rows you are not interested in).
+    /// Unlike `.skip(n)`, the skipped rows are never built: we simply fast-forward through the
+    /// xml until we reach the first row we want. Note that `n` counts rows as you would see them
+    /// in Excel (including empty rows), not `<row>` elements in the xml.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let mut rows = ws.rows(&mut wb).unwrap().skip_header_rows(2);
+    ///     let row3 = rows.next().unwrap();
+    ///     assert_eq!(row3.1, 3);
+    ///     assert_eq!(row3[0].raw_value, "37");
+    pub fn skip_header_rows(mut self, n: usize) -> Self {
+        self.skip_rows(n);
+        self
+    }
+
+    /// Only return the rows and columns that fall within the used area declared by the sheet's
+    /// `<dimension>` element. Normally the iterator grows to fit any cells it finds outside of
+    /// the declared area (e.g., stray formatted cells far away from the data); in strict mode those
+    /// cells are dropped instead, so every row has exactly as many columns as the dimension says.
+    /// If the sheet does not declare its dimension, this has no effect.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let rows: Vec<_> = ws.rows(&mut wb).unwrap().strict_dimension().collect();
+    ///     assert_eq!(rows.len(), 46); // A1:R46
+    ///     assert_eq!(rows[0].0.len(), 18);
+    pub fn strict_dimension(mut self) -> Self {
+        self.strict_dimension = true;
+        self
+    }
+
+    /// Return every value as an `ExcelValue::String` holding the cell's raw value exactly as it is
+    /// written in the xml (so numbers are not reformatted and dates are not converted). Shared
+    /// strings are still looked up, so text cells hold their text rather than an index into the
+    /// shared string table. Empty cells are still `ExcelValue::None`.
+    ///
+    /// # Example usage
+    ///
+    ///     use std::borrow::Cow;
+    ///     use xl::{ExcelValue, Workbook};
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let row1 = ws.rows(&mut wb).unwrap().raw_strings().next().unwrap();
+    ///     assert_eq!(row1[0].value, ExcelValue::String(Cow::Borrowed("1")));
+    pub fn raw_strings(mut self) -> Self {
+        self.raw_strings = true;
+        self
+    }
+
+    /// Return the value of every cell with the Text number format (`@`) as a string of exactly
+    /// what is written in the xml, even when the cell is stored as a number. Codes such as zip
+    /// codes or SKUs are sometimes stored that way, and would otherwise come back as numbers
+    /// (e.g., `7` instead of `007`, when the writer kept the leading zeros). Other cells are not
+    /// affected.
+    ///
+    /// # Example usage
+    ///
+    ///     use std::borrow::Cow;
+    ///     use xl::{ExcelValue, Workbook};
+    ///
+    ///     let mut wb = Workbook::open("tests/data/text_format.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get(1).unwrap();
+    ///     let row1 = ws.rows(&mut wb).unwrap().text_as_strings().next().unwrap();
+    ///     assert_eq!(row1[0].value, ExcelValue::String(Cow::Borrowed("007")));
+    pub fn text_as_strings(mut self) -> Self {
+        self.text_as_strings = true;
+        self
+    }
+
+    /// Make every row (including the simulated empty rows) at least `n` cells wide. Normally rows
+    /// are as wide as the sheet's `<dimension>` says, but sheets without a dimension start out
+    /// with zero-width empty rows until the first cell is found. This keeps indexing into rows
+    /// stable in that case.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let row1 = ws.rows(&mut wb).unwrap().with_min_columns(20).next().unwrap();
+    ///     assert_eq!(row1.0.len(), 20); // the dimension is A1:R46
+    pub fn with_min_columns(mut self, n: u16) -> Self {
+        self.min_columns = n;
+        self.num_cols = cmp::max(self.num_cols, n);
+        self
+    }
+
+    /// Stop iterating after the last `<row>` element in the sheet instead of simulating empty rows
+    /// up to the end of the used area. The used area comes from the sheet's `<dimension>` element,
+    /// which can claim thousands of rows more than there are (e.g., after rows are deleted), and
+    /// every one of those would otherwise be returned as an empty row. Empty rows between two
+    /// `<row>` elements are still returned.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/stale_dimension.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get(1).unwrap();
+    ///     assert_eq!(ws.rows(&mut wb).unwrap().count(), 20); // A1:F20
+    ///     assert_eq!(ws.rows(&mut wb).unwrap().no_trailing_fill().count(), 4);
+    pub fn no_trailing_fill(mut self) -> Self {
+        self.trailing_fill = false;
+        self
+    }
+
+    /// Keep all the attributes of every `<row>` element, so they are available through
+    /// `Row::attributes`. This is off by default because most callers do not need them and
+    /// collecting them costs an allocation per row.
+    pub fn with_row_attributes(mut self) -> Self {
+        self.row_attributes = true;
+        self
+    }
+
+    /// Keep going when part of the sheet cannot be parsed, instead of panicking, so one damaged
+    /// row deep in a big sheet does not cost you the rest of the import. A cell that cannot be read
+    /// (e.g., because of a broken attribute, an unknown `&entity;` or a mismatched end tag) is
+    /// returned with an `ExcelValue::Error` whose text starts with `unreadable cell:` and says
+    /// what was wrong, and a row whose number cannot be read is taken to follow the previous row.
+    /// Every problem is logged as a warning (through the `log` crate). If the xml is so broken
+    /// that the reader cannot get past the problem, the iterator ends there (without the empty
+    /// rows that would otherwise fill out the sheet's dimension) and `error` says why.
+    ///
+    /// This only applies to rows read after it is turned on, so rows skipped by
+    /// `Worksheet::rows_from` are read the usual way.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::{ExcelValue, Workbook};
+    ///
+    ///     let mut wb = Workbook::open("tests/data/broken_row.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get(1).unwrap();
+    ///     let rows: Vec<_> = ws.rows(&mut wb).unwrap().recover_errors().collect();
+    ///     assert_eq!(rows.len(), 5);
+    ///     assert!(matches!(rows[2][1].value, ExcelValue::Error(_)));
+    ///     assert_eq!(rows[4][0].value, ExcelValue::Number(11.0));
+    pub fn recover_errors(mut self) -> Self {
+        self.recover_errors = true;
+        // mismatched end tags are caught by `read_row` instead, since the parser stops for good
+        // at the first error it reports
+        self.worksheet_reader.reader.check_end_names(false);
+        self
+    }
+
+    /// How far into the worksheet's xml the iterator has read, in bytes. This counts the
+    /// decompressed xml (not the compressed bytes in the zip), so it can be compared with the
+    /// `uncompressed_size` of the sheet's part (see `Workbook::part_info`) to estimate progress.
+    /// The iterator may read ahead by one row, so the position can lie past the end of the row
+    /// that was returned last.
+    ///
+    /// Compressed parts cannot be decompressed from the middle, so there is no way to start
+    /// reading at a byte position: to resume an interrupted import, keep the number of the last
+    /// row that was handled as well and pass it to `Worksheet::rows_from`.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::Workbook;
+    ///
+    ///     let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let mut rows = ws.rows(&mut wb).unwrap();
+    ///     assert_eq!(rows.byte_position(), 0);
+    ///     rows.next();
+    ///     assert!(rows.byte_position() > 0);
+    pub fn byte_position(&self) -> usize {
+        self.worksheet_reader.reader.buffer_position()
+    }
+
+    /// Why the iterator stopped early, if it did: `XlError::LimitExceeded` when the sheet went
+    /// past `WorkbookOptions::max_cells` or `WorkbookOptions::max_decompressed_bytes`, or an
+    /// `XlError::Io` error of kind `InvalidData` when `recover_errors` could not get past broken
+    /// xml. An iterator that stopped early returns `None` as if the sheet had ended there, so
+    /// check this when it matters whether you saw every row.
+    ///
+    /// # Example usage
+    ///
+    ///     use xl::{Workbook, WorkbookOptions};
+    ///
+    ///     let options = WorkbookOptions::new().max_cells(100);
+    ///     let mut wb = Workbook::open_with("tests/data/Book1.xlsx", options).unwrap();
+    ///     let sheets = wb.sheets();
+    ///     let ws = sheets.get("Sheet1").unwrap();
+    ///     let mut rows = ws.rows(&mut wb).unwrap();
+    ///     assert!(rows.by_ref().count() < 46);
+    ///     assert!(rows.error().is_some());
+    pub fn error(&self) -> Option<&XlError> {
+        self.error.as_ref()
+    }
+
+    /// Hand back the error that stopped the iterator early, if there was one.
+    fn finish(&mut self) -> Result<(), XlError> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Fast-forward past the next `n` rows without building them.
+    fn skip_rows(&mut self, n: usize) {
+        let target = self.want_row + n;
+        self.want_row = target;
+        if let Some(Row(_, row_num, _)) = &self.next_row {
+            if *row_num >= target {
+                return;
+            }
+            self.next_row = None;
+        }
+        let mut buf = Vec::new();
+        let reader = &mut self.worksheet_reader.reader;
+        let recover = self.recover_errors;
+        // skip the rest of a row; its cells are thrown away, so with `recover_errors` only xml
+        // the reader cannot get past stops the iterator
+        let skip_to_end_of_row = |reader: &mut Reader<_>, buf: &mut Vec<u8>| {
+            let skipped = reader.read_to_end(b"row", buf);
+            buf.clear();
+            match skipped {
+                Err(e) if !recover => panic!("{:?}", e),
+                skipped => skipped,
+            }
+        };
+        if let Some((row_num, _)) = &self.pending_row {
+            if *row_num >= target {
+                return;
+            }
+            self.pending_row = None;
+            if let Err(e) = skip_to_end_of_row(reader, &mut buf) {
+                self.give_up(&e);
+                return;
+            }
+        }
+        if self.done_file {
+            return;
+        }
+        loop {
+            let mut skip_row = false;
+            let event = reader.read_event(&mut buf);
+            if self.limits.too_many_bytes(reader.buffer_position() as u64) {
+                // the next call to `next` reports the error
+                self.done_file = true;
+                break;
+            }
+            match event {
+                Ok(Event::Empty(ref e)) if self.trust_dimension && e.name() == b"dimension" => {
+                    if let Some((rows, cols)) = dimension(e) {
+                        self.num_cols = cmp::max(cols, self.min_columns);
+                        self.num_rows = rows;
+                    }
+                }
+                Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e))
+                    if e.name() == b"row"
+                        && beyond_dimension(
+                            self.strict_dimension,
+                            self.num_rows,
+                            read_row_properties(e, false, recover).0,
+                        ) =>
+                {
+                    self.done_file = true;
+                    break;
+                }
+                Ok(Event::Start(ref e)) if e.name() == b"row" => {
+                    let (this_row, props) = read_row_properties(e, self.row_attributes, recover);
+                    if this_row >= target {
+                        self.pending_row = Some((this_row, props));
+                        break;
+                    }
+                    skip_row = true;
+                }
+                Ok(Event::Empty(ref e)) if e.name() == b"row" => {
+                    let (this_row, props) = read_row_properties(e, self.row_attributes, recover);
+                    if this_row >= target {
+                        let mut row = Vec::with_capacity(self.num_cols as usize);
+                        pad_row(&mut row, self.num_cols, this_row);
+                        self.next_row = Some(Row(row, this_row, props));
+                        break;
+                    }
+                }
+                Ok(Event::Eof) => {
+                    self.done_file = true;
+                    break;
+                }
+                // the parser cannot continue after an error
+                Err(ref e) if recover => {
+                    self.give_up(e);
+                    break;
+                }
+                Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
+                _ => (),
+            }
+            buf.clear();
+            if skip_row {
+                if let Err(e) = skip_to_end_of_row(reader, &mut buf) {
+                    self.give_up(&e);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Stop at xml the reader cannot get past (with `recover_errors`), keeping the reason for
+    /// `error`. Nothing is known about the rest of the sheet, so no empty rows are made up after
+    /// the last row that was read.
+    fn give_up(&mut self, e: &quick_xml::Error) {
+        let position = self.byte_position();
+        warn!("giving up on {} at position {}: {}", self.part, position, e);
+        self.error = Some(unreadable_xml(&self.part, position, e));
+        self.trailing_fill = false;
+        self.done_file = true;
+    }
+}
+
+impl<'a> Iterator for RowIter<'a> {
+    type Item = Row<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error.is_some() {
+            return None;
+        }
+        let row = self.read_row();
+        // a row that was read past the byte limit is thrown away, since it may be incomplete
+        let position = self.byte_position() as u64;
+        if let Err(e) = self.limits.check_bytes(position, &self.part) {
+            self.error = Some(e);
+            return None;
+        }
+        let row = row?;
+        self.cells_read += row.0.len() as u64;
+        if let Err(e) = self.limits.check_cells(self.cells_read) {
+            self.error = Some(e);
+            return None;
+        }
+        Some(row)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.skip_rows(n);
+        self.next()
+    }
+}
+
+impl<'a> RowIter<'a> {
+    /// Read the next row from the xml (or simulate an empty one), without checking the limits.
+    fn read_row(&mut self) -> Option<Row<'a>> {
+        // the xml in the xlsx file will not contain elements for empty rows. So
+        // we need to "simulate" the empty rows since the user expects to see
+        // them when they iterate over the worksheet.
+        if let Some(Row(_, row_num, _)) = &self.next_row {
+            // since we are currently buffering a row, we know we will either return it or a
+            // "simulated" (i.e., emtpy) row. So we grab the current row and update the fact that
+            // we will soon want a new row. We then figure out if we have the row we want or if we
+            // need to keep spitting out empty rows.
+            let current_row = self.want_row;
+            self.want_row += 1;
+            if *row_num == current_row {
+                // we finally hit the row we were looking for, so we reset the buffer and return
+                // the row that was sitting in it.
+                let mut r = None;
+                mem::swap(&mut r, &mut self.next_row);
+                return r;
+            } else {
+                // otherwise, we must still be sitting behind the row we want. So we return an
+                // empty row to simulate the row that exists in the spreadsheet.
+                return empty_row(self.num_cols, current_row);
+            }
+        } else if self.done_file {
+            // the file has been read completely, so all that is left is to simulate any empty
+            // rows at the end of the used area. The reader is never touched again.
+            if !self.trailing_fill || self.want_row > self.num_rows as usize {
+                return None;
+            }
+            self.want_row += 1;
+            return empty_row(self.num_cols, self.want_row - 1);
+        }
+        let mut buf = Vec::new();
+        let reader = &mut self.worksheet_reader.reader;
+        let strings = self.worksheet_reader.strings;
+        let styles = self.worksheet_reader.styles;
+        let alignments = self.worksheet_reader.alignments;
+        let format_ids = self.worksheet_reader.format_ids;
+        let date_system = self.worksheet_reader.date_system;
+        let recover = self.recover_errors;
+        let next_row = {
+            let mut row: Vec<Cell> = Vec::with_capacity(self.num_cols as usize);
+            // why the current cell could not be read (see `recover_errors`)
+            let mut bad_cell: Option<String> = None;
+            // the elements opened inside the current cell, to catch mismatched end tags when the
+            // reader is not checking them (see `recover_errors`)
+            let mut open_tags: Vec<Vec<u8>> = vec![];
+            let mut in_cell = false;
+            let mut in_value = false;
+            // state of an inline string (`<is>`), which is read like an entry of the shared
+            // string table: its text may be split into several runs, may come with phonetic
+            // hints that are not part of it, and keeps its whitespace with xml:space="preserve"
+            let mut in_inline = false;
+            let mut in_phonetic = false;
+            let mut preserve_space = false;
+            let mut c = new_cell();
+            // the index of the shared formula the current cell defines (if any)
+            let mut shared_index: Option<String> = None;
+            // whether a `<row>` has been opened (and not closed), and the elements opened inside
+            // it outside of any cell, so that a row that is cut off or closed by the wrong end tag
+            // is caught when the reader is not checking end tags (see `recover_errors`)
+            let mut in_row = self.pending_row.is_some();
+            let mut row_tags: Vec<Vec<u8>> = vec![];
+            let (mut this_row, mut props) = self.pending_row.take().unwrap_or_default();
+            loop {
+                let mut row_done = false;
+                let event = reader.read_event(&mut buf);
+                if self.limits.too_many_bytes(reader.buffer_position() as u64) {
+                    // `next` reports the error
+                    break None;
+                }
+                if recover {
+                    match &event {
+                        Ok(Event::Start(ref e)) if in_cell => open_tags.push(e.name().to_vec()),
+                        Ok(Event::End(ref e)) if in_cell && e.name() != b"c" => {
+                            // an end tag that does not match is taken to close the innermost
+                            // element anyway
+                            let open = open_tags.pop();
+                            if open.as_deref() != Some(e.name()) {
+                                let name = String::from_utf8_lossy(e.name());
+                                bad_cell.get_or_insert_with(|| format!("unexpected </{}>", name));
+                            }
+                        }
+                        Ok(Event::End(ref e)) if in_cell => {
+                            if let Some(tag) = open_tags.drain(..).next_back() {
+                                let name = String::from_utf8_lossy(&tag).into_owned();
+                                bad_cell.get_or_insert_with(|| format!("missing </{}>", name));
+                            }
+                        }
+                        Ok(Event::Start(ref e)) if e.name() == b"row" => in_row = true,
+                        Ok(Event::Start(ref e)) if in_row && e.name() != b"c" => {
+                            row_tags.push(e.name().to_vec())
+                        }
+                        Ok(Event::End(ref e)) if in_row && e.name() != b"row" => {
+                            let open = row_tags.pop();
+                            if open.as_deref() != Some(e.name()) {
+                                // there is no telling where the rest of the row went
+                                let expected = open.unwrap_or_else(|| b"row".to_vec());
+                                self.give_up(&quick_xml::Error::EndEventMismatch {
+                                    expected: String::from_utf8_lossy(&expected).into_owned(),
+                                    found: String::from_utf8_lossy(e.name()).into_owned(),
+                                });
+                                break None;
+                            }
+                        }
+                        Ok(Event::Eof) if in_row => {
+                            self.give_up(&quick_xml::Error::UnexpectedEof("row".to_string()));
+                            break None;
+                        }
+                        // the parser cannot continue after an error
+                        Err(e) => {
+                            self.give_up(e);
+                            break None;
+                        }
+                        _ => (),
+                    }
+                }
+                match event {
+                    /* may be able to get a better estimate for the used area */
+                    Ok(Event::Empty(ref e)) if self.trust_dimension && e.name() == b"dimension" => {
+                        if let Some((rows, cols)) = dimension(e) {
+                            self.num_cols = cmp::max(cols, self.min_columns);
+                            self.num_rows = rows;
+                        }
+                    }
+                    /* -- end search for used area */
+                    Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) if e.name() == b"row" => {
+                        // a row without any cells (e.g., a hidden row) shows up as an empty
+                        // element, so it ends as soon as it starts
+                        row_done = matches!(event, Ok(Event::Empty(_)));
+                        (this_row, props) = read_row_properties(e, self.row_attributes, recover);
+                        if recover && this_row == 0 {
+                            // rows are in order, so a row without a (readable) number is
+                            // assumed to come right after the previous one
+                            warn!(
+                                "row without a number in {}, using {}",
+                                self.part, self.want_row
+                            );
+                            this_row = self.want_row;
+                        }
+                    }
+                    Ok(Event::Start(ref e)) if e.name() == b"c" => {
+                        in_cell = true;
+                        // cells are not required to have a reference, in which case they simply
+                        // follow the previous cell
+                        if let Some(col) = utils::num2col(row.len() as u16 + 1) {
+                            c.reference = format!("{}{}", col, this_row);
+                        }
+                        // cells without a style of their own take on the style of their row
+                        let mut style_index = props.style_index;
+                        e.attributes().for_each(|a| {
+                            let a = match a {
+                                Ok(a) => a,
+                                Err(e) if recover => {
+                                    bad_cell.get_or_insert_with(|| e.to_string());
+                                    return;
+                                }
+                                Err(e) => panic!("{:?}", e),
+                            };
+                            if a.key == b"r" {
+                                c.reference = utils::attr_value(&a);
+                            }
+                            if a.key == b"t" {
+                                c.cell_type = utils::attr_value(&a);
+                            }
+                            if a.key == b"s" {
+                                if let Ok(num) = utils::attr_value(&a).parse::<usize>() {
+                                    style_index = Some(num);
+                                }
+                            }
+                        });
+                        if let Some(num) = style_index {
+                            if let Some(style) = styles.get(num) {
+                                c.style = style.to_string();
+                            }
+                            c.alignment = alignments.get(num);
+                            c.format_id = format_ids.get(num).copied();
+                            c.style_index = Some(num);
+                        }
+                    }
+                    Ok(Event::Start(ref e)) if e.name() == b"is" => {
+                        in_inline = true;
+                        // whitespace is significant inside <t> elements with xml:space="preserve"
+                        reader.trim_text(false);
+                    }
+                    Ok(Event::End(ref e)) if e.name() == b"is" => {
+                        in_inline = false;
+                        reader.trim_text(true);
+                    }
+                    Ok(Event::Start(ref e)) if in_inline && e.name() == b"rPh" => {
+                        in_phonetic = true
+                    }
+                    Ok(Event::End(ref e)) if in_inline && e.name() == b"rPh" => in_phonetic = false,
+                    Ok(Event::Start(ref e)) if in_inline && e.name() == b"t" => {
+                        in_value = !in_phonetic;
+                        preserve_space =
+                            utils::get(e.attributes(), b"xml:space").as_deref() == Some("preserve");
+                    }
+                    Ok(Event::Start(ref e)) if e.name() == b"v" || e.name() == b"t" => {
+                        in_value = true;
+                    }
+                    Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) if e.name() == b"f" => {
+                        // a shared formula is only written out for the first cell that uses it;
+                        // the other cells just refer to it by its index
+                        let mut index = None;
+                        c.formula_type = Some(FormulaType::Normal);
+                        e.attributes().for_each(|a| {
+                            let a = a.unwrap();
+                            if a.key == b"t" {
+                                c.formula_type = match &utils::attr_value(&a)[..] {
+                                    "shared" => Some(FormulaType::Shared),
+                                    "array" => Some(FormulaType::Array),
+                                    "dataTable" => Some(FormulaType::DataTable),
+                                    _ => Some(FormulaType::Normal),
+                                };
+                            }
+                            if a.key == b"ref" {
+                                c.formula_range = utils::attr_value(&a);
+                            }
+                            if a.key == b"si" {
+                                index = Some(utils::attr_value(&a));
+                            }
+                        });
+                        let shared = c.formula_type == Some(FormulaType::Shared);
+                        if let (true, Some(index)) = (shared, index) {
+                            if matches!(event, Ok(Event::Empty(_))) {
+                                if let Some((formula, col, row)) = self.shared_formulas.get(&index)
+                                {
+                                    if let Some((this_col, this_row)) =
+                                        utils::parse_reference(&c.reference)
+                                    {
+                                        c.formula = utils::shift_formula(
+                                            formula,
+                                            this_row as i64 - *row as i64,
+                                            this_col as i64 - *col as i64,
+                                        );
+                                    }
+                                }
+                            } else {
+                                shared_index = Some(index);
+                            }
+                        }
+                    }
+                    Ok(Event::End(ref e)) if e.name() == b"f" => {
+                        let index = shared_index.take();
+                        if let (Some(index), Some((col, row))) =
+                            (index, utils::parse_reference(&c.reference))
+                        {
+                            self.shared_formulas
+                                .insert(index, (c.formula.clone(), col, row));
+                        }
+                    }
+                    // note: because v elements are children of c elements,
+                    // need this check to go before the 'in_cell' check
+                    Ok(Event::Text(ref e)) if in_value => {
+                        // a value can arrive in several pieces (e.g., when it is interrupted by a
+                        // comment), so it is only interpreted once the element is closed
+                        let text = match e.unescape_and_decode(reader) {
+                            Ok(text) => text,
+                            Err(e) if recover => {
+                                bad_cell.get_or_insert_with(|| e.to_string());
+                                String::new()
+                            }
+                            Err(e) => panic!("{:?}", e),
+                        };
+                        if in_inline && !preserve_space {
+                            c.raw_value.push_str(text.trim());
+                        } else {
+                            c.raw_value.push_str(&text);
+                        }
+                    }
+                    // (whitespace between the elements of an inline string, and phonetic hints)
+                    Ok(Event::Text(_)) if in_inline => (),
+                    Ok(Event::Text(ref e)) if in_cell => match e.unescape_and_decode(reader) {
+                        Ok(txt) => c.formula.push_str(&txt),
+                        Err(e) if recover => {
+                            bad_cell.get_or_insert_with(|| e.to_string());
+                        }
+                        Err(e) => panic!("{:?}", e),
+                    },
+                    Ok(Event::End(ref e)) if e.name() == b"v" || e.name() == b"t" => {
+                        if in_value && !c.raw_value.is_empty() {
+                            c.value = match &c.cell_type[..] {
+                                "s" => {
+                                    let pos = c.raw_value.parse::<usize>().ok();
+                                    if let Some(s) = pos.and_then(|pos| strings.get(pos)) {
+                                        ExcelValue::String(Cow::Borrowed(s))
+                                    } else {
+                                        ExcelValue::String(Cow::Owned(c.raw_value.clone()))
+                                    }
+                                }
+                                _ if self.raw_strings => {
+                                    ExcelValue::String(Cow::Owned(c.raw_value.clone()))
+                                }
+                                "str" | "inlineStr" => {
+                                    ExcelValue::String(Cow::Owned(c.raw_value.clone()))
+                                }
+                                // with or without a formula
+                                "b" => bool_value(&c.raw_value),
+                                "bl" => ExcelValue::None,
+                                "e" => ExcelValue::Error(c.raw_value.to_string()),
+                                _ if self.text_as_strings && c.style == "@" => {
+                                    ExcelValue::String(Cow::Owned(c.raw_value.clone()))
+                                }
+                                "n" => number_value(&c.raw_value, &c.style, date_system),
+                                // cells without a type should hold numbers, but some writers
+                                // leave the type off text (e.g., the result of a formula)
+                                _ => number_value(&c.raw_value, &c.style, date_system),
+                            };
+                        }
+                        in_value = false;
+                        empty_string_value(&mut c);
+                    }
+                    Ok(Event::Empty(ref e)) if e.name() == b"v" || e.name() == b"t" => {
+                        empty_string_value(&mut c);
+                    }
+                    Ok(Event::End(ref e)) if e.name() == b"c" => {
+                        if let Some(reason) = bad_cell.take() {
+                            warn!(
+                                "could not read cell {} in {}: {}",
+                                c.reference, self.part, reason
+                            );
+                            c.value = ExcelValue::Error(format!("unreadable cell: {}", reason));
+                            in_value = false;
+                        }
+                        // the row always holds every column from A up to the right-most cell
+                        // seen so far, so a cell's column tells us exactly where it goes
+                        match utils::parse_reference(&c.reference) {
+                            // a cell that is not on the sheet (e.g., past column XFD) has nowhere
+                            // to go, so it is dropped
+                            None => (),
+                            Some((this_col, this_row)) => {
+                                let this_col = this_col as usize;
+                                if this_col <= row.len() {
+                                    // some generators write cells out of column order, in which
+                                    // case this cell replaces the empty cell put in its place
+                                    row[this_col - 1] = c;
+                                } else {
+                                    for n in row.len() + 1..this_col {
+                                        let mut cell = new_cell();
+                                        cell.reference = utils::make_reference(n as u16, this_row);
+                                        row.push(cell);
+                                    }
+                                    row.push(c);
+                                }
+                            }
+                        }
+                        c = new_cell();
+                        in_cell = false;
+                    }
+                    Ok(Event::End(ref e)) if e.name() == b"row" => row_done = true,
+                    Ok(Event::Eof) => break None,
+                    Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
+                    _ => (),
+                }
+                buf.clear();
+                if row_done {
+                    if beyond_dimension(self.strict_dimension, self.num_rows, this_row) {
+                        // rows come in order, so there is nothing left inside the dimension
+                        break None;
+                    }
+                    if self.strict_dimension && self.num_rows > 0 {
+                        row.truncate(self.num_cols as usize);
+                    } else {
+                        self.num_cols = cmp::max(self.num_cols, row.len() as u16);
+                    }
+                    pad_row(&mut row, self.num_cols, this_row);
+                    let next_row = Some(Row(row, this_row, props));
+                    if this_row == self.want_row {
+                        break next_row;
+                    } else {
+                        self.next_row = next_row;
+                        break empty_row(self.num_cols, self.want_row);
+                    }
+                }
+            }
+        };
+        if next_row.is_none() {
+            self.done_file = true;
+            if !self.trailing_fill || self.want_row > self.num_rows as usize {
+                return None;
+            }
+            self.want_row += 1;
+            return empty_row(self.num_cols, self.want_row - 1);
+        }
+        self.want_row += 1;
+        next_row
+    }
+}
+
+/// Once a `RowIter` returns `None` it keeps returning `None`; it does not read any further or
+/// produce more (empty) rows.
+impl FusedIterator for RowIter<'_> {}
+
+/// Append `value` to `out` as a csv field, quoted the way `Worksheet::write_csv` quotes the
+/// equivalent cell. There is no raw value to go by, so numbers are written in the shortest form
+/// that reads back as the same `f64`, which is what Excel writes as the raw value of a cell.
+fn write_csv_value(out: &mut Vec<u8>, value: &ExcelValue) {
+    let quoted = |out: &mut Vec<u8>, text: &str| {
+        out.push(b'"');
+        out.extend_from_slice(text.replace('"', "\"\"").as_bytes());
+        out.push(b'"');
+    };
+    match value {
+        ExcelValue::Bool(b) => quoted(out, if *b { "1" } else { "0" }),
+        ExcelValue::Date(d) => out.extend_from_slice(d.to_string().as_bytes()),
+        ExcelValue::DateTime(d) => {
+            out.extend_from_slice(d.format("%Y-%m-%d %H:%M:%S").to_string().as_bytes())
+        }
+        ExcelValue::Error(e) => out.extend_from_slice(e.as_bytes()),
+        ExcelValue::None => (),
+        ExcelValue::Number(n) => quoted(out, general_number(&n.to_string())),
+        ExcelValue::String(s) => quoted(out, s),
+        ExcelValue::Time(t) => out.extend_from_slice(t.format("%H:%M:%S").to_string().as_bytes()),
+    }
+}
+
+/// Format `date` with the `chrono` format string `format`, falling back to `YYYY-MM-DD` when
+/// there is no format or it is not valid.
+fn format_date(date: NaiveDate, format: Option<&str>) -> String {
+    let mut text = String::new();
+    match format {
+        Some(format) if write!(text, "{}", date.format(format)).is_ok() => text,
+        _ => date.to_string(),
+    }
+}
+
+/// The text of `value` the way `Display` shows it, but without the quotes around strings and
+/// times (see `Worksheet::to_string_grid`).
+fn plain_text(value: &ExcelValue) -> String {
+    match value {
+        ExcelValue::Error(e) => e.to_string(),
+        ExcelValue::String(s) => s.to_string(),
+        ExcelValue::Time(t) => t.to_string(),
+        value => value.to_string(),
+    }
+}
+
+/// Interpret an xml boolean attribute (which can be written as 1/0 or true/false).
+fn xml_bool(value: &str) -> bool {
+    value == "1" || value == "true"
+}
+
+/// Does `style` (a number format) display numbers the way the "General" format does?
+fn is_general(style: &str) -> bool {
+    style.is_empty() || style == "General" || style == "0"
+}
+
+/// Excel shows whole numbers without a decimal point under the General format, so a raw value of
+/// `3.0` becomes `3`. Anything else (including numbers in scientific notation) is left alone.
+fn general_number(raw: &str) -> &str {
+    match raw.split_once('.') {
+        Some((whole, frac))
+            if !whole.is_empty() && !frac.is_empty() && frac.bytes().all(|b| b == b'0') =>
+        {
+            whole
+        }
+        _ => raw,
+    }
+}
+
+/// Format a number like the General format does (see `ExcelValue`): rounded to 15 significant
+/// digits, and in scientific notation when it is at least 1e15 or below 1e-9 in absolute value.
+fn general_format(n: f64) -> String {
+    if !n.is_finite() || n == 0.0 {
+        // -0.0 is shown as 0, like in Excel
+        return if n == 0.0 {
+            "0".to_string()
+        } else {
+            n.to_string()
+        };
+    }
+    // rounding to 15 significant digits can carry into the next power of ten (e.g., 9.99...e14),
+    // so the exponent has to be taken from the rounded number
+    let rounded = format!("{:.14e}", n);
+    let (mantissa, exp) = rounded.split_once('e').unwrap();
+    let exp: i32 = exp.parse().unwrap();
+    if (-9..15).contains(&exp) {
+        // any decimal with 15 significant digits survives a round trip through an f64, so the
+        // shortest representation of the rounded number has at most 15 of them
+        rounded.parse::<f64>().unwrap().to_string()
+    } else {
+        let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+        let sign = if exp < 0 { '-' } else { '+' };
+        format!("{}E{}{:02}", mantissa, sign, exp.abs())
+    }
+}
+
+/// Interpret the raw value of a boolean (`t="b"`) cell. Excel writes `1` or `0`, but some writers
+/// spell out `TRUE` or `FALSE` instead (typically for the results of formulas).
+fn bool_value(raw: &str) -> ExcelValue<'static> {
+    let raw = raw.trim();
+    ExcelValue::Bool(!(raw == "0" || raw.eq_ignore_ascii_case("false")))
+}
+
+/// Interpret the raw value of a numeric cell, which is shown as a date (or time) when its number
+/// format looks like a date format. A value that is not a number at all is kept as a string.
+fn number_value(raw: &str, style: &String, date_system: &DateSystem) -> ExcelValue<'static> {
+    match raw.parse::<f64>() {
+        Ok(num) if is_date(style) => match utils::excel_number_to_date(num, date_system) {
+            utils::DateConversion::Date(date) => ExcelValue::Date(date),
+            utils::DateConversion::DateTime(date) => ExcelValue::DateTime(date),
+            utils::DateConversion::Time(time) => ExcelValue::Time(time),
+            utils::DateConversion::Number(num) => ExcelValue::Number(num as f64),
+        },
+        Ok(num) => ExcelValue::Number(num),
+        Err(_) => ExcelValue::String(Cow::Owned(raw.to_owned())),
+    }
+}
+
+/// Does the number format `style` show numbers as percentages? That is the case when it has a
+/// `%` outside of quoted text (`"%"`), escaped characters (`\%`), and brackets (`[Red]`).
+fn is_percentage_format(style: &str) -> bool {
+    let mut chars = style.chars();
+    let mut in_quotes = false;
+    let mut in_brackets = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if !in_brackets => in_quotes = !in_quotes,
+            '\\' if !in_quotes && !in_brackets => {
+                chars.next();
+            }
+            '[' if !in_quotes => in_brackets = true,
+            ']' if !in_quotes => in_brackets = false,
+            '%' if !in_quotes && !in_brackets => return true,
+            _ => (),
+        }
+    }
+    false
+}
+
+/// Classify a number format by its id (for built-in formats) or its format code.
+fn format_category(id: Option<u32>, code: &str) -> FormatCategory {
+    match id {
+        Some(0) => FormatCategory::General,
+        Some(1..=4) | Some(37..=40) => FormatCategory::Number,
+        Some(5..=8) => FormatCategory::Currency,
+        Some(9) | Some(10) => FormatCategory::Percentage,
+        Some(11) | Some(48) => FormatCategory::Scientific,
+        Some(12) | Some(13) => FormatCategory::Fraction,
+        Some(14..=17) | Some(22) | Some(27..=31) | Some(36) | Some(50..=54) | Some(57)
+        | Some(58) => FormatCategory::Date,
+        Some(18..=21) | Some(32..=35) | Some(45..=47) | Some(55) | Some(56) => FormatCategory::Time,
+        Some(41..=44) => FormatCategory::Accounting,
+        Some(49) => FormatCategory::Text,
+        _ => code_category(code),
+    }
+}
+
+/// Classify a custom number format by the placeholders in its format code. Only the first
+/// section (the one used for positive numbers) is looked at, and quoted or escaped literals only
+/// count towards currency symbols.
+fn code_category(code: &str) -> FormatCategory {
+    if code.is_empty() || code.eq_ignore_ascii_case("general") {
+        return FormatCategory::General;
+    }
+    // the placeholders of the first section, lowercased and without literals or [brackets]
+    let mut bare = String::new();
+    let mut currency = false;
+    let mut elapsed_time = false;
+    let mut chars = code.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            ';' => break,
+            '"' => {
+                for c in chars.by_ref().take_while(|&c| c != '"') {
+                    currency |= is_currency_symbol(c);
+                }
+            }
+            '\\' => currency |= chars.next().is_some_and(is_currency_symbol),
+            // padding and fill characters only change how the value is laid out
+            '_' => {
+                chars.next();
+            }
+            '*' => {
+                chars.next();
+                bare.push('*');
+            }
+            '[' => {
+                let bracket: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                let bracket = bracket.to_ascii_lowercase();
+                match bracket.strip_prefix('$') {
+                    // [$€-407] has a currency symbol, [$-409] only sets the locale
+                    Some(locale) => currency |= !locale.starts_with('-') && !locale.is_empty(),
+                    None => {
+                        elapsed_time |= !bracket.is_empty()
+                            && bracket.chars().all(|c| c == 'h' || c == 'm' || c == 's')
+                    }
+                }
+            }
+            c => {
+                currency |= is_currency_symbol(c);
+                bare.extend(c.to_lowercase());
+            }
+        }
+    }
+    let bare = bare.replace("am/pm", "").replace("a/p", "");
+    let has_digits = bare.contains(['0', '#', '?']);
+    let has_time = elapsed_time || bare.contains(['h', 's']);
+    let has_date = bare.contains(['y', 'd']) || (bare.contains('m') && !has_time);
+    if code.trim() == "@" {
+        FormatCategory::Text
+    } else if has_date {
+        FormatCategory::Date
+    } else if has_time {
+        FormatCategory::Time
+    } else if !has_digits {
+        FormatCategory::Custom
+    } else if is_percentage_format(code) {
+        FormatCategory::Percentage
+    } else if bare.contains("e+") || bare.contains("e-") {
+        FormatCategory::Scientific
+    } else if bare.contains('/') {
+        FormatCategory::Fraction
+    } else if bare.contains('*') {
+        FormatCategory::Accounting
+    } else if currency {
+        FormatCategory::Currency
+    } else if bare.chars().all(|c| "0#?,. -+()".contains(c)) {
+        FormatCategory::Number
+    } else {
+        FormatCategory::Custom
+    }
+}
+
+/// A piece of a date format code (see `date_format_parts`). The numbers are how many times the
+/// letter was repeated (e.g., `Month(3)` for `mmm`).
+#[derive(Debug, PartialEq)]
+enum DatePart {
+    Literal(String),
+    Year(usize),
+    Month(usize),
+    Day(usize),
+    Hour(usize),
+    Minute(usize),
+    Second(usize),
+    /// the number of digits of the fraction of a second (e.g., 2 for `ss.00`)
+    Fraction(usize),
+    /// the AM and PM markers as they are written in the code (e.g., `AM/PM` or `a/p`)
+    AmPm(String, String),
+}
+
+/// Split the first section of a date format code into its parts. Returns `None` if the code has
+/// no date or time in it, or has something that cannot be shown with a date alone (elapsed times
+/// like `[h]` and text placeholders).
+fn date_format_parts(code: &str) -> Option<Vec<DatePart>> {
+    let chars: Vec<char> = code.chars().collect();
+    let mut parts = vec![];
+    let mut literal = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+        let part = match c.to_ascii_lowercase() {
+            ';' => break,
+            '"' => {
+                while i < chars.len() && chars[i] != '"' {
+                    literal.push(chars[i]);
+                    i += 1;
+                }
+                i += 1;
+                continue;
+            }
+            '\\' => {
+                literal.extend(chars.get(i));
+                i += 1;
+                continue;
+            }
+            // padding takes the width of the next character, fill characters take none
+            '_' | '*' => {
+                if c == '_' {
+                    literal.push(' ');
+                }
+                i += 1;
+                continue;
+            }
+            '[' => {
+                let end = chars[i..].iter().position(|&c| c == ']')? + i;
+                let bracket: String = chars[i..end].iter().collect();
+                i = end + 1;
+                match bracket.strip_prefix('$') {
+                    // [$€-407] shows a currency symbol, [$-409] only sets the locale
+                    Some(locale) => literal.push_str(locale.split('-').next().unwrap_or("")),
+                    // elapsed hours, minutes or seconds
+                    None if bracket.chars().all(|c| "hHmMsS".contains(c)) => return None,
+                    // colors and conditions
+                    None => (),
+                }
+                continue;
+            }
+            '@' => return None,
+            'a' => {
+                let rest: String = chars[i - 1..].iter().collect();
+                let lower = rest.to_ascii_lowercase();
+                let len = if lower.starts_with("am/pm") {
+                    5
+                } else if lower.starts_with("a/p") {
+                    3
+                } else {
+                    literal.push(c);
+                    continue;
+                };
+                let (am, pm) = rest[..len].split_once('/').unwrap();
+                i += len - 1;
+                DatePart::AmPm(am.to_string(), pm.to_string())
+            }
+            '.' if matches!(parts.last(), Some(DatePart::Second(_)))
+                && literal.is_empty()
+                && chars.get(i) == Some(&'0') =>
+            {
+                let digits = chars[i..].iter().take_while(|&&c| c == '0').count();
+                i += digits;
+                DatePart::Fraction(digits)
+            }
+            letter @ ('y' | 'm' | 'd' | 'h' | 's') => {
+                let mut n = 1;
+                while chars
+                    .get(i)
+                    .is_some_and(|c| c.to_ascii_lowercase() == letter)
+                {
+                    n += 1;
+                    i += 1;
+                }
+                match letter {
+                    'y' => DatePart::Year(n),
+                    'm' => DatePart::Month(n),
+                    'd' => DatePart::Day(n),
+                    'h' => DatePart::Hour(n),
+                    _ => DatePart::Second(n),
+                }
+            }
+            _ => {
+                literal.push(c);
+                continue;
+            }
+        };
+        if !literal.is_empty() {
+            parts.push(DatePart::Literal(mem::take(&mut literal)));
+        }
+        parts.push(part);
+    }
+    if !literal.is_empty() {
+        parts.push(DatePart::Literal(literal));
+    }
+    if parts.iter().all(|p| matches!(p, DatePart::Literal(_))) {
+        return None;
+    }
+    // `m` and `mm` are minutes rather than months right after hours or right before seconds
+    let placeholders: Vec<usize> = (0..parts.len())
+        .filter(|&n| !matches!(parts[n], DatePart::Literal(_)))
+        .collect();
+    for (k, &n) in placeholders.iter().enumerate() {
+        if let DatePart::Month(len @ (1 | 2)) = parts[n] {
+            let after_hours = k > 0 && matches!(parts[placeholders[k - 1]], DatePart::Hour(_));
+            let before_seconds = placeholders
+                .get(k + 1)
+                .is_some_and(|&next| matches!(parts[next], DatePart::Second(_)));
+            if after_hours || before_seconds {
+                parts[n] = DatePart::Minute(len);
+            }
+        }
+    }
+    Some(parts)
+}
+
+/// Write out `datetime` following the `parts` of a date format code.
+fn format_date_parts(parts: &[DatePart], datetime: &NaiveDateTime) -> String {
+    let twelve_hour = parts.iter().any(|p| matches!(p, DatePart::AmPm(..)));
+    let mut out = String::new();
+    for part in parts {
+        let spec = match part {
+            DatePart::Literal(text) => {
+                out.push_str(text);
+                continue;
+            }
+            DatePart::Year(1 | 2) => "%y",
+            DatePart::Year(_) => "%Y",
+            DatePart::Month(1) => "%-m",
+            DatePart::Month(2) => "%m",
+            DatePart::Month(3) => "%b",
+            DatePart::Month(4) => "%B",
+            // just the first letter of the month
+            DatePart::Month(_) => {
+                out.extend(datetime.format("%B").to_string().chars().next());
+                continue;
+            }
+            DatePart::Day(1) => "%-d",
+            DatePart::Day(2) => "%d",
+            DatePart::Day(3) => "%a",
+            DatePart::Day(_) => "%A",
+            DatePart::Hour(1) if twelve_hour => "%-I",
+            DatePart::Hour(_) if twelve_hour => "%I",
+            DatePart::Hour(1) => "%-H",
+            DatePart::Hour(_) => "%H",
+            DatePart::Minute(1) => "%-M",
+            DatePart::Minute(_) => "%M",
+            DatePart::Second(1) => "%-S",
+            DatePart::Second(_) => "%S",
+            DatePart::Fraction(digits) => {
+                let fraction = format!("{:09}", datetime.nanosecond() % 1_000_000_000);
+                out.push('.');
+                out.push_str(&fraction[..cmp::min(*digits, 9)]);
+                continue;
+            }
+            DatePart::AmPm(am, pm) => {
+                out.push_str(if datetime.hour() < 12 { am } else { pm });
+                continue;
+            }
+        };
+        let _ = write!(out, "{}", datetime.format(spec));
+    }
+    out
+}
+
+/// Is `c` one of the currency symbols that Excel puts in its currency formats?
+fn is_currency_symbol(c: char) -> bool {
+    matches!(
+        c,
+        '$' | '€' | '£' | '¥' | '₹' | '₩' | '₽' | '₺' | '₪' | '₫' | '฿'
+    )
+}
+
+fn is_date(style: &String) -> bool {
+    let is_d = style == "d";
+    let is_like_d_and_not_like_red = style.contains('d') && !style.contains("Red");
+    let is_like_m = style.contains('m');
+    if is_d || is_like_d_and_not_like_red || is_like_m {
+        true
+    } else {
+        style.contains('y')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Alignment, CellType, ColumnInfo, CsvOptions, Dimension, ExcelError, ExcelValue,
+        FormatCategory, FormulaType, Orientation, PageSetup, Sheet2D, SheetKind, ViewSettings,
+        Workbook, WorkbookOptions, XlError,
+    };
+    use chrono::{NaiveDate, NaiveTime};
+    use std::{
+        borrow::Cow,
+        collections::HashMap,
+        fs,
+        io::{self, Cursor, Read},
+    };
+
+    #[test]
+    fn test_ups() {
+        let mut file = fs::File::open("./tests/data/UPS.Galaxy.VS.PX.xlsx").unwrap();
+        let mut buff = vec![];
+        file.read_to_end(&mut buff).unwrap();
+        let mut wb = Workbook::new(Cursor::new(buff)).unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Table001 (Page 1-19)").unwrap();
+        let mut row_iter = ws.rows(&mut wb).unwrap();
+        let row2 = row_iter.nth(1).unwrap();
+        assert_eq!(row2[3].value, ExcelValue::Number(0.0));
+        let row3 = row_iter.next().unwrap();
+        assert_eq!(row3[4].value, ExcelValue::String(Cow::Borrowed("Bit")));
+    }
+
+    #[test]
+    fn test_read_to_buffer() {
+        /* This spreadsheet has a combination of null values and missing cells to put the method
+         * through its paces */
+        let mut file = fs::File::open("./tests/data/7_nulls.xlsx").unwrap();
+        let mut buff = vec![];
+        file.read_to_end(&mut buff).unwrap();
+        let mut wb = Workbook::new(Cursor::new(buff)).unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let byte_buffer = ws.read_to_buffer(&mut wb).unwrap();
+        let byte_buffer_as_string = String::from_utf8(byte_buffer).unwrap();
+        let expected = ",\"0\",\"1\",\"2\",\"3\",\"4\"\n\"0\",\"foo\",\"0.4664743800292485\",,\"0.9373419333844548\",\"0.3870971408372121\"\n\"1\",,\"0.6363620246706366\",\"baz\",\"foo\",\"0.4664743800292485\"\n\"2\",,\"0.08179075658393076\",\"bar\",\"\",\"0.6363620246706366\"\n\"3\",,\"0.9373419333844548\",\"0.3870971408372121\",,\"0.08179075658393076\"\n,\"baz\",\"foo\",\"0.4664743800292485\",,\"0.9373419333844548\"\n\"5\",\"bar\",\"\",\"0.6363620246706366\",\"baz\",\"foo\"\n\"6\",\"0.3870971408372121\",,\"0.08179075658393076\",\"bar\",\n";
+
+        assert_eq!(byte_buffer_as_string, expected);
+    }
+
+    #[test]
+    fn test_read_to_buffer_with_dates() {
+        /* This spreadsheet has a combination of null values and missing cells to put the method
+         * through its paces */
+        let mut file = fs::File::open("./tests/data/dates2.xlsx").unwrap();
+        let mut buff = vec![];
+        file.read_to_end(&mut buff).unwrap();
+        let mut wb = Workbook::new(Cursor::new(buff)).unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let byte_buffer = ws.read_to_buffer(&mut wb).unwrap();
+        let byte_buffer_as_string = String::from_utf8(byte_buffer).unwrap();
+        println!("{:?}", byte_buffer_as_string);
+        let expected = "\"Line\",\"Date1\",\"String1\",\"String2\",\"Date2\",\"Float1\",\"String3\"\n\"11\",2022-03-13,\"S1_Line1\",\"S2_Line1\",2021-07-22,\"55401.4834901147\",\"S3L1\"\n\"12\",2022-05-06,\"S1_Line (2)\",\"S2_Line2\",2021-09-14,\"59895.0195440879\",\"S3L2\"\n\"13\",2022-10-01,\"S1, Line3\",\"S2_Line3\",2022-02-09,\"73563.1850302802\",\"S3L3\"\n\"14\",2022-11-24,\"S1 \"\"Line 4\"\"\",\"S2_Line4\",2022-04-04,\"81245.2187551785\",\"S3L4\"\n\"15\",2022-12-01,\"S1_Line5\",\"S2_Line5\",2022-04-11,\"82692.7459436702\",\"S3L5\"\n\"17\",2023-01-24,\"S1_Line6\",\"S2_Line6\",2022-06-04,\"98603.829483607406\",\"S3L6\"\n\"17\",2023-01-24,\"Ele \"\"Line 4\"\"\",\"test ws::tests::test_read_to_buffer_with_dates ... ok\n\ntest result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 24 filtered out; finished in 0.01s\",2022-06-04,\"98603.829483607406\",\"S3L6\"\n,,,,,,\n,,,,,,\n";
+
+        assert_eq!(byte_buffer_as_string, expected);
+    }
+
+    #[test]
+    fn test_hidden_rows() {
+        let mut wb = Workbook::open("./tests/data/hidden_rows.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let rows: Vec<_> = ws.rows(&mut wb).unwrap().collect();
+        assert_eq!(rows.len(), 7);
+        let hidden: Vec<_> = rows.iter().map(|r| r.hidden()).collect();
+        assert_eq!(hidden, vec![false, false, true, true, false, true, false]);
+        let levels: Vec<_> = rows.iter().map(|r| r.outline_level()).collect();
+        assert_eq!(levels, vec![0, 1, 2, 2, 1, 0, 0]);
+        // rows without any cells still have the full width of the sheet
+        assert_eq!(rows[5].0.len(), 2);
+        assert_eq!(rows[5][1].reference, "B6");
+        let visible: Vec<_> = rows.iter().filter(|r| !r.hidden()).map(|r| r.1).collect();
+        assert_eq!(visible, vec![1, 2, 5, 7]);
+    }
+
+    #[test]
+    fn test_skip_header_rows() {
+        let mut wb = Workbook::open("./tests/data/sparse_rows.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let all: Vec<String> = ws.rows(&mut wb).unwrap().map(|r| r.to_string()).collect();
+        for n in 0..13 {
+            let skipped: Vec<String> = ws
+                .rows(&mut wb)
+                .unwrap()
+                .skip_header_rows(n)
+                .map(|r| r.to_string())
+                .collect();
+            assert_eq!(skipped, all[n.min(all.len())..], "skipping {} rows", n);
+        }
+        let mut rows = ws.rows(&mut wb).unwrap().skip_header_rows(2);
+        assert_eq!(rows.next().unwrap().1, 3);
+        assert_eq!(rows.nth(1).unwrap().to_string(), "3,30,");
+    }
+
+    #[test]
+    fn test_recompute_dimensions() {
+        let mut wb = Workbook::open("./tests/data/stale_dimension.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let rows: Vec<_> = ws.rows(&mut wb).unwrap().collect();
+        assert!(rows.len() > 4);
+        assert_eq!(rows[0].0.len(), 6);
+
+        let options = WorkbookOptions::new().recompute_dimensions(true);
+        let mut wb = Workbook::open_with("./tests/data/stale_dimension.xlsx", options).unwrap();
+        let rows: Vec<_> = ws.rows(&mut wb).unwrap().collect();
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[3].to_string(), "4,40");
+        let buffer = String::from_utf8(ws.read_to_buffer(&mut wb).unwrap()).unwrap();
+        assert_eq!(buffer.lines().next(), Some("\"1\",\"10\""));
+    }
+
+    #[test]
+    fn test_sheet2d() {
+        let mut wb = Workbook::open("./tests/data/sparse_rows.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let sheet = Sheet2D::new(ws.to_vec(&mut wb).unwrap());
+        assert_eq!(sheet[(2, 3)].value, ExcelValue::Number(100.0));
+        assert_eq!(sheet[(9, 2)].reference, "B9");
+        assert_eq!(sheet[(3, 1)].value, ExcelValue::None);
+        assert!(sheet.get((0, 1)).is_none());
+        assert!(sheet.get((1, 4)).is_none());
+        assert!(sheet.get((100, 1)).is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_sheet2d_out_of_range() {
+        let mut wb = Workbook::open("./tests/data/sparse_rows.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let sheet = Sheet2D::new(ws.to_vec(&mut wb).unwrap());
+        let _ = &sheet[(1, 0)];
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_write_jsonl() {
+        let mut wb = Workbook::open("./tests/data/sparse_rows.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let mut out = vec![];
+        ws.write_jsonl(&mut wb, &mut out, &crate::JsonLinesOptions::default())
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let mut lines = out.lines();
+        assert_eq!(lines.next(), Some(r#"{"id":1.0,"x":10.0,"y":100.0}"#));
+        assert_eq!(lines.next(), Some(r#"{"id":null,"x":null,"y":null}"#));
+        assert_eq!(lines.next(), Some(r#"{"id":2.0,"x":null,"y":200.0}"#));
+
+        let mut out = vec![];
+        let options = crate::JsonLinesOptions { header_row: 2 };
+        ws.write_jsonl(&mut wb, &mut out, &options).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(
+            out.lines().next(),
+            Some(r#"{"1":null,"10":null,"100":null}"#)
+        );
+    }
+
+    #[test]
+    fn test_write_csv_with_progress() {
+        let mut wb = Workbook::open("./tests/data/lots_of_nulls.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let mut calls = vec![];
+        let mut out = vec![];
+        ws.write_csv_with_progress(&mut wb, &mut out, |n| calls.push(n))
+            .unwrap();
+        let lines = out.iter().filter(|&&b| b == b'\n').count() as u64;
+        assert_eq!(calls, vec![lines]);
+    }
+
+    #[test]
+    fn test_csv_null_text() {
+        let mut wb = Workbook::open("./tests/data/7_nulls.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let options = CsvOptions {
+            null_text: Some("\\N".to_string()),
+            ..CsvOptions::default()
+        };
+        let mut out = vec![];
+        ws.write_csv_with_options(&mut wb, &mut out, &options)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 8);
+        assert_eq!(
+            lines[3],
+            "\"2\",\\N,\"0.08179075658393076\",\"bar\",\"\",\"0.6363620246706366\""
+        );
+        assert!(out.starts_with("\\N,\"0\",\"1\""));
+        assert!(
+            out.ends_with("\"6\",\"0.3870971408372121\",\\N,\"0.08179075658393076\",\"bar\",\\N\n")
+        );
+
+        // string cells whose value is present but empty are empty strings, not nulls
+        let mut wb = Workbook::open("./tests/data/empty_strings.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let mut out = vec![];
+        ws.write_csv_with_options(&mut wb, &mut out, &options)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\"\",\\N,\"\",\\N,\"\",\\N,\"\",\"text\"\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_line_terminator() {
+        let mut wb = Workbook::open("./tests/data/multiline.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let options = CsvOptions {
+            line_terminator: "\r\n".to_string(),
+            ..CsvOptions::default()
+        };
+        let mut out = vec![];
+        ws.write_csv_with_options(&mut wb, &mut out, &options)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\"name\",\"qty\"\r\n\"first line\nsecond \"\"line\"\"\",\"1\"\r\n\"single\",\"2\"\r\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_multiline_cell() {
+        let mut wb = Workbook::open("./tests/data/multiline.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let out = String::from_utf8(ws.read_to_buffer(&mut wb).unwrap()).unwrap();
+        // the embedded newline stays inside the quoted field rather than ending the record
+        assert_eq!(
+            out,
+            "\"name\",\"qty\"\n\"first line\nsecond \"\"line\"\"\",\"1\"\n\"single\",\"2\"\n"
+        );
+    }
+
+    #[test]
+    fn test_as_bool() {
+        let mut wb = Workbook::open("./tests/data/bools.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let row = ws.rows(&mut wb).unwrap().next().unwrap();
+        // boolean cells keep their strict value, string cells are left alone
+        assert_eq!(row[0].value, ExcelValue::Bool(true));
+        assert_eq!(row[2].value, ExcelValue::String(Cow::Borrowed("TRUE")));
+        let bools: Vec<Option<bool>> = row.0.iter().map(|c| c.as_bool()).collect();
+        assert_eq!(
+            bools,
+            vec![
+                Some(true),
+                Some(false),
+                Some(true),
+                Some(false),
+                Some(true),
+                Some(false),
+                Some(true),
+                Some(false),
+                None,
+                Some(true),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn test_excel_value_from_str() {
+        let parse = |s: &str| s.parse::<ExcelValue>().unwrap();
+        assert_eq!(parse(""), ExcelValue::None);
+        assert_eq!(parse("1e3"), ExcelValue::Number(1000.0));
+        assert_eq!(parse("-0.5"), ExcelValue::Number(-0.5));
+        assert_eq!(parse("False"), ExcelValue::Bool(false));
+        assert_eq!(
+            parse("2020-02-29"),
+            ExcelValue::Date(NaiveDate::from_ymd(2020, 2, 29))
+        );
+        assert_eq!(
+            parse("2020-02-29T13:45:00"),
+            ExcelValue::DateTime(NaiveDate::from_ymd(2020, 2, 29).and_hms(13, 45, 0))
+        );
+        assert_eq!(
+            parse("08:30:00"),
+            ExcelValue::Time(NaiveTime::from_hms(8, 30, 0))
+        );
+        // non-finite numbers and invalid dates are just text
+        assert_eq!(parse("inf"), ExcelValue::String(Cow::Borrowed("inf")));
+        assert_eq!(
+            parse("2021-02-29"),
+            ExcelValue::String(Cow::Borrowed("2021-02-29"))
+        );
+    }
+
+    #[test]
+    fn test_excel_value_parse_as() {
+        assert_eq!(
+            ExcelValue::parse_as("1", CellType::String),
+            Ok(ExcelValue::String(Cow::Borrowed("1")))
+        );
+        assert_eq!(
+            ExcelValue::parse_as("#N/A", CellType::Error),
+            Ok(ExcelValue::Error("#N/A".to_string()))
+        );
+        assert!(ExcelValue::parse_as("yes", CellType::Bool).is_err());
+        assert!(ExcelValue::parse_as("2020-01-01", CellType::Time).is_err());
+    }
+
+    #[test]
+    fn test_rows_twice() {
+        let mut wb = Workbook::open("./tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let first: Vec<String> = ws.rows(&mut wb).unwrap().map(|r| r.to_string()).collect();
+        let second: Vec<String> = ws.rows(&mut wb).unwrap().map(|r| r.to_string()).collect();
+        assert!(!first.is_empty());
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_rows_fused() {
+        let mut wb = Workbook::open("./tests/data/sparse_rows.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let mut rows = ws.rows(&mut wb).unwrap();
+        // the dimension (A1:C12) says there are 12 rows, even though the last one with data is 9
+        let row_nums: Vec<usize> = rows.by_ref().map(|r| r.1).collect();
+        assert_eq!(row_nums, (1..=12).collect::<Vec<_>>());
+        for _ in 0..5 {
+            assert!(rows.next().is_none());
+        }
+
+        let mut wb = Workbook::open("./tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let count = {
+            let mut rows = ws.rows(&mut wb).unwrap();
+            let count = rows.by_ref().count();
+            for _ in 0..5 {
+                assert!(rows.next().is_none());
+            }
+            count
+        };
+        assert!(count > 0);
+        let mut rows = ws.rows(&mut wb).unwrap();
+        assert!(rows.nth(count + 10).is_none());
+        assert!(rows.next().is_none());
+    }
+
+    #[test]
+    fn test_shuffled_cells() {
+        let mut wb = Workbook::open("./tests/data/shuffled_cells.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let rows = ws.to_vec(&mut wb).unwrap();
+        assert_eq!(rows.len(), 3);
+        for row in &rows {
+            let refs: Vec<&str> = row.0.iter().map(|c| &c.reference[..]).collect();
+            let n = row.1;
+            assert_eq!(
+                refs,
+                vec![
+                    format!("A{}", n),
+                    format!("B{}", n),
+                    format!("C{}", n),
+                    format!("D{}", n)
+                ]
+            );
+        }
+        assert_eq!(rows[0].to_string(), "1,2,3,");
+        assert_eq!(rows[1].to_string(), "10,20,,40");
+        assert_eq!(rows[2].to_string(), "100,,300,");
+    }
+
+    #[test]
+    fn test_missing_sheet_part() {
+        let mut wb = Workbook::open("./tests/data/dangling_sheet.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Missing").unwrap();
+        match ws.rows(&mut wb) {
+            Err(XlError::MissingPart(part)) => assert_eq!(part, "xl/worksheets/missing.xml"),
+            _ => panic!("expected a missing part error"),
+        }
+        assert!(matches!(
+            ws.read_to_buffer(&mut wb),
+            Err(XlError::MissingPart(_))
+        ));
+        // the other sheet in the workbook is still readable
+        let ws = sheets.get("Good").unwrap();
+        assert_eq!(ws.read_to_buffer(&mut wb).unwrap(), b"\"1\"\n");
+    }
+
+    #[test]
+    fn test_shared_formulas() {
+        let mut wb = Workbook::open("./tests/data/shared_formulas.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let formulas: Vec<Vec<String>> = ws
+            .rows(&mut wb)
+            .unwrap()
+            .map(|r| r.0.iter().map(|c| c.formula.clone()).collect())
+            .collect();
+        assert_eq!(formulas[0], vec!["", "A1*2+$A$1", "A1+1", ""]);
+        assert_eq!(
+            formulas[1],
+            vec!["", "A2*2+$A$1", "SUM($A$1:A2)", "SUM($A$1:B2)"]
+        );
+        assert_eq!(
+            formulas[2],
+            vec!["", "A3*2+$A$1", "SUM($A$1:A3)", "SUM($A$1:B3)"]
+        );
+        assert_eq!(formulas[3], vec!["", "A4*2+$A$1", "", ""]);
+    }
+
+    #[test]
+    fn test_formula_result_types() {
+        let mut wb = Workbook::open("./tests/data/formula_results.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let row = ws.rows(&mut wb).unwrap().next().unwrap();
+        assert_eq!(row[2].formula, "CONCAT(A1,B1)");
+        assert_eq!(row[2].value, ExcelValue::String(Cow::Borrowed("foobar")));
+        assert_eq!(row[3].formula, "A1=B1");
+        assert_eq!(row[3].value, ExcelValue::Bool(false));
+        assert_eq!(row[4].formula, "1/0");
+        assert_eq!(row[4].value, ExcelValue::Error("#DIV/0!".to_string()));
+        assert_eq!(row[5].formula, "LEN(A1)");
+        assert_eq!(row[5].value, ExcelValue::Number(3.0));
+        // a text result that looks like a number is still text
+        assert_eq!(row[6].formula, "TEXT(F1,\"0.0\")");
+        assert_eq!(row[6].value, ExcelValue::String(Cow::Borrowed("3.0")));
+        // and so is a text result whose cell is (incorrectly) missing its type
+        assert_eq!(row[7].value, ExcelValue::String(Cow::Borrowed("ab")));
+        let csv = ws.read_to_buffer(&mut wb).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "\"foo\",\"bar\",\"foobar\",\"0\",#DIV/0!,\"3\",\"3.0\",\"ab\"\n"
+        );
+    }
+
+    #[test]
+    fn test_formula_types() {
+        let mut wb = Workbook::open("./tests/data/array_formulas.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let rows = ws.to_vec(&mut wb).unwrap();
+        assert_eq!(rows[0][0].formula_type, None);
+        assert_eq!(rows[0][1].formula_type, Some(FormulaType::Array));
+        assert_eq!(rows[0][1].formula, "A1:A3*2");
+        assert_eq!(rows[0][1].formula_range, "B1:B3");
+        // the rest of the spilled range only holds values
+        assert_eq!(rows[1][1].formula_type, None);
+        assert_eq!(rows[1][1].value, ExcelValue::Number(4.0));
+        assert_eq!(rows[0][2].formula_type, Some(FormulaType::Normal));
+        assert_eq!(rows[0][2].formula_range, "");
+        assert_eq!(rows[0][3].formula_type, Some(FormulaType::Shared));
+        assert_eq!(rows[1][3].formula_type, Some(FormulaType::Shared));
+        assert_eq!(rows[1][3].formula, "A2+1");
+        assert_eq!(rows[1][3].formula_range, "");
+    }
+
+    #[test]
+    fn test_csv_error_text() {
+        let mut wb = Workbook::open("./tests/data/formula_results.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let options = CsvOptions {
+            error_text: Some("".to_string()),
+            ..CsvOptions::default()
+        };
+        let mut out = vec![];
+        ws.write_csv_with_options(&mut wb, &mut out, &options)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\"foo\",\"bar\",\"foobar\",\"0\",,\"3\",\"3.0\",\"ab\"\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_date_format() {
+        let mut wb = Workbook::open("./tests/data/dates.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let options = CsvOptions {
+            date_format: Some("%B %e, %Y".to_string()),
+            ..CsvOptions::default()
+        };
+        let mut out = vec![];
+        ws.write_csv_with_options(&mut wb, &mut out, &options)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(
+            lines[1],
+            "\"11\",\"March 13, 2022\",\"S1_Line1\",\"S2_Line1\",\"July 22, 2021\",\
+             \"55401.483490114675\",\"S3L1\""
+        );
+        // dates without a comma are left unquoted, as are ones in an invalid format
+        for format in &["%d/%m/%Y", "%Q"] {
+            let options = CsvOptions {
+                date_format: Some(format.to_string()),
+                ..CsvOptions::default()
+            };
+            let mut out = vec![];
+            ws.write_csv_with_options(&mut wb, &mut out, &options)
+                .unwrap();
+            let out = String::from_utf8(out).unwrap();
+            let expected = if *format == "%Q" {
+                "\"11\",2022-03-13,"
+            } else {
+                "\"11\",13/03/2022,"
+            };
+            assert!(out.lines().nth(1).unwrap().starts_with(expected), "{}", out);
+        }
+    }
+
+    #[test]
+    fn test_read_f64_block() {
+        let mut wb = Workbook::open("tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let rows = ws.to_vec(&mut wb).unwrap();
+        let expected: Vec<Vec<f64>> = rows[1..10]
+            .iter()
+            .map(|row| row.0[1..5].iter().map(|c| c.get_f64().unwrap()).collect())
+            .collect();
+        drop(rows);
+        assert_eq!(ws.read_f64_block(&mut wb, "B2:E10", 0.0).unwrap(), expected);
+        // the corners can be given in any order, and absolute references work too
+        assert_eq!(
+            ws.read_f64_block(&mut wb, "E10:$B$2", 0.0).unwrap(),
+            expected
+        );
+        assert_eq!(
+            ws.read_f64_block(&mut wb, "C2", 0.0).unwrap(),
+            vec![vec![21.0]]
+        );
+        // past the end of the sheet
+        let block = ws.read_f64_block(&mut wb, "A46:B48", -1.0).unwrap();
+        assert_eq!(block.len(), 3);
+        assert!(block[0][0] > 0.0);
+        assert_eq!(block[1], vec![-1.0, -1.0]);
+        assert_eq!(block[2], vec![-1.0, -1.0]);
+        for range in &["", "B2:", "B2:E0", "2B:E10", "B2:E10:F11"] {
+            assert!(
+                matches!(
+                    ws.read_f64_block(&mut wb, range, 0.0),
+                    Err(XlError::InvalidReference(_))
+                ),
+                "{}",
+                range
+            );
+        }
+
+        let mut wb = Workbook::open("tests/data/mixed_types.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let block = ws.read_f64_block(&mut wb, "A1:B4", f64::NAN).unwrap();
+        assert_eq!(block[0][0], 1.5);
+        assert_eq!(block[1][0], 2.5);
+        assert!(block[2][0].is_nan()); // "abc"
+        assert!(block[3][0].is_nan()); // true
+        assert!(block.iter().all(|row| row[1].is_nan())); // dates
+    }
+
+    #[test]
+    fn test_to_sparse_map() {
+        let mut wb = Workbook::open("tests/data/sparse_rows.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let cells = ws.to_sparse_map(&mut wb).unwrap();
+        let mut wb = Workbook::open("tests/data/sparse_rows.xlsx").unwrap();
+        let rows = ws.to_vec(&mut wb).unwrap();
+        let populated: Vec<_> = rows
+            .iter()
+            .flat_map(|row| row.0.iter())
+            .filter(|cell| cell.value != ExcelValue::None)
+            .collect();
+        assert!(!populated.is_empty());
+        assert_eq!(cells.len(), populated.len());
+        for cell in populated {
+            assert_eq!(cells[&cell.reference], cell.value);
+        }
+    }
+
+    #[test]
+    fn test_recover_errors() {
+        let mut wb = Workbook::open("tests/data/broken_row.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let rows: Vec<_> = ws.rows(&mut wb).unwrap().recover_errors().collect();
+        assert_eq!(rows.len(), 5);
+        assert_eq!(rows[2][0].value, ExcelValue::Number(7.0));
+        assert_eq!(
+            rows[2][1].value,
+            ExcelValue::Error("unreadable cell: unexpected </x>".to_string())
+        );
+        match &rows[2][2].value {
+            ExcelValue::Error(e) => assert!(e.starts_with("unreadable cell:")),
+            v => panic!("expected an error, got {:?}", v),
+        }
+        // the rest of the row and the sheet read as usual
+        assert_eq!(rows[2][3].value, ExcelValue::Number(9.0));
+        // the row number that cannot be read is taken from the previous row
+        assert_eq!(rows[3].1, 4);
+        assert_eq!(rows[3][1].value, ExcelValue::String("10x".into()));
+        assert_eq!(rows[4][0].value, ExcelValue::Number(11.0));
+
+        // skipped rows can be broken too
+        let rows: Vec<_> = ws
+            .rows(&mut wb)
+            .unwrap()
+            .recover_errors()
+            .skip_header_rows(3)
+            .collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1][0].value, ExcelValue::Number(11.0));
+
+        // xml the reader cannot get past ends the iterator with an error, and no empty rows are
+        // made up between the last row read and the end of the dimension (A1:A10)
+        let mut wb = Workbook::open("tests/data/truncated_sheet.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let mut rows = ws.rows(&mut wb).unwrap().recover_errors();
+        let values: Vec<_> = rows.by_ref().map(|r| r[0].raw_value.clone()).collect();
+        assert_eq!(values, vec!["1", "2", "3", "4"]);
+        match rows.error() {
+            Some(XlError::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::InvalidData),
+            e => panic!("expected an xml error, got {:?}", e),
+        }
+        assert!(rows.next().is_none());
+        drop(rows);
+        let mut rows = ws.rows(&mut wb).unwrap().recover_errors();
+        assert!(rows.nth(6).is_none());
+        assert!(rows.error().is_some());
+
+        // recomputing the dimensions reads the whole sheet before the rows, which must not trip
+        // over the broken xml either
+        let options = WorkbookOptions::new().recompute_dimensions(true);
+        let mut wb = Workbook::open_with("tests/data/broken_row.xlsx", options).unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let mut rows = ws.rows(&mut wb).unwrap().recover_errors();
+        assert_eq!(rows.by_ref().count(), 5);
+        assert!(rows.error().is_none());
+        drop(rows);
+        assert_eq!(ws.row_count(&mut wb).unwrap(), 5);
+    }
+
+    #[test]
+    fn test_trim_trailing_empty() {
+        let mut wb = Workbook::open("tests/data/7_nulls.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let padded = String::from_utf8(ws.read_to_buffer(&mut wb).unwrap()).unwrap();
+        let options = CsvOptions {
+            trim_trailing_empty: true,
+            null_text: Some("NULL".to_string()),
+            ..CsvOptions::default()
+        };
+        let mut out = vec![];
+        ws.write_csv_with_options(&mut wb, &mut out, &options)
+            .unwrap();
+        let trimmed = String::from_utf8(out).unwrap();
+        // only the last row ends with an empty cell, and empty cells before a value are kept
+        let padded: Vec<&str> = padded.lines().collect();
+        let trimmed: Vec<&str> = trimmed.lines().collect();
+        assert_eq!(padded.len(), trimmed.len());
+        assert_eq!(
+            padded[7],
+            "\"6\",\"0.3870971408372121\",,\"0.08179075658393076\",\"bar\","
+        );
+        assert_eq!(
+            trimmed[7],
+            "\"6\",\"0.3870971408372121\",NULL,\"0.08179075658393076\",\"bar\""
+        );
+        assert_eq!(trimmed[0], "NULL,\"0\",\"1\",\"2\",\"3\",\"4\"");
+
+        // missing cells at the end of a row are not written either
+        let mut wb = Workbook::open("tests/data/sparse_rows.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let options = CsvOptions {
+            trim_trailing_empty: true,
+            ..CsvOptions::default()
+        };
+        let mut out = vec![];
+        ws.write_csv_with_options(&mut wb, &mut out, &options)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("\n\"3\",\"30\"\n"), "{}", out);
+    }
+
+    #[test]
+    fn test_blank_cell_type() {
+        let mut wb = Workbook::open("tests/data/blank_type.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let rows = ws.to_vec(&mut wb).unwrap();
+        let values: Vec<Vec<ExcelValue>> = rows
+            .iter()
+            .map(|row| row.0.iter().map(|c| c.value.clone()).collect())
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                vec![
+                    ExcelValue::Number(1.0),
+                    ExcelValue::None,
+                    ExcelValue::None,
+                    ExcelValue::String("x".into())
+                ],
+                vec![
+                    ExcelValue::None,
+                    ExcelValue::Number(2.0),
+                    ExcelValue::None,
+                    ExcelValue::Number(3.0)
+                ],
+            ]
+        );
+        drop(rows);
+        // the csv has empty fields in the same places
+        let out = ws.read_to_buffer(&mut wb).unwrap();
+        assert_eq!(out, b"\"1\",,,\"x\"\n,\"2\",,\"3\"\n");
+        let options = CsvOptions {
+            null_text: Some("NULL".to_string()),
+            ..CsvOptions::default()
+        };
+        let mut out = vec![];
+        ws.write_csv_with_options(&mut wb, &mut out, &options)
+            .unwrap();
+        assert_eq!(out, b"\"1\",NULL,NULL,\"x\"\nNULL,\"2\",NULL,\"3\"\n");
+    }
+
+    #[test]
+    fn test_numbered() {
+        let mut wb = Workbook::open("tests/data/sparse_rows.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let numbers: Vec<u32> = ws
+            .rows_from(&mut wb, 3)
+            .unwrap()
+            .numbered()
+            .map(|(n, row)| {
+                assert_eq!(n, row.number());
+                assert_eq!(n as usize, row.1);
+                n
+            })
+            .collect();
+        assert_eq!(numbers, (3..=12).collect::<Vec<_>>());
+
+        // the iterator is only borrowed, so a limit that cut the rows short can still be seen
+        let options = WorkbookOptions::new().max_cells(100);
+        let mut wb = Workbook::open_with("tests/data/Book1.xlsx", options).unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let mut rows = ws.rows(&mut wb).unwrap();
+        let numbers: Vec<u32> = rows.numbered().map(|(n, _)| n).collect();
+        assert!(!numbers.is_empty() && numbers.len() < 46);
+        assert_eq!(numbers, (1..=numbers.len() as u32).collect::<Vec<_>>());
+        assert!(matches!(rows.error(), Some(XlError::LimitExceeded(_))));
+    }
+
+    #[test]
+    fn test_bool_formulas() {
+        let mut wb = Workbook::open("tests/data/bool_formulas.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let rows = ws.to_vec(&mut wb).unwrap();
+        assert_eq!(rows[0][1].value, ExcelValue::Bool(true));
+        assert_eq!(rows[0][1].formula, "ISNUMBER(A1)");
+        assert_eq!(rows[0][1].formula_type, Some(FormulaType::Normal));
+        assert_eq!(rows[0][2].value, ExcelValue::Bool(false));
+        assert_eq!(rows[0][2].formula, "ISTEXT(A1)");
+        // without a formula
+        assert_eq!(rows[1][0].value, ExcelValue::Bool(true));
+        assert!(rows[1][0].formula.is_empty());
+        // spelled out
+        assert_eq!(rows[1][1].value, ExcelValue::Bool(true));
+        assert_eq!(rows[1][1].formula, "A1>40");
+        assert_eq!(rows[1][2].value, ExcelValue::Bool(false));
+        assert_eq!(rows[1][2].formula, "A1<40");
+    }
+
+    #[test]
+    fn test_display_date() {
+        let mut wb = Workbook::open("tests/data/date_formats.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let rows = ws.to_vec(&mut wb).unwrap();
+        let shown: Vec<Vec<Option<String>>> = rows
+            .iter()
+            .map(|row| row.0.iter().map(|c| c.display_date()).collect())
+            .collect();
+        let expected = vec![
+            vec![
+                Some("13-Mar-22"),
+                Some("Mar-22"),
+                Some("Sunday, March 13, 2022"),
+                Some("6:00 PM"),
+                Some("2022-03-13 12:00:43.200"),
+                None, // empty
+            ],
+            vec![
+                Some("13-Mar-22"),  // [$-409]d-mmm-yy;@
+                Some("01:00"),      // mm:ss (minutes, not months)
+                Some("13 de M 22"), // a literal and the first letter of the month
+                None,               // [h]:mm:ss
+                Some("3/13/22 1:30"),
+                Some("06h 00"), // hh\h mm
+            ],
+        ];
+        let expected: Vec<Vec<Option<String>>> = expected
+            .into_iter()
+            .map(|row| row.into_iter().map(|s| s.map(String::from)).collect())
+            .collect();
+        assert_eq!(shown, expected);
+    }
+
+    #[test]
+    fn test_stored_parts() {
+        // the same workbook, with every part stored in the zip without compression
+        let mut deflated = Workbook::open("tests/data/Book1.xlsx").unwrap();
+        let mut stored = Workbook::open("tests/data/Book1_stored.xlsx").unwrap();
+        assert!(stored
+            .part_info()
+            .iter()
+            .all(|part| part.compression == "Stored"));
+        assert_eq!(stored.shared_strings(), deflated.shared_strings());
+        let sheets = deflated.sheets();
+        assert_eq!(stored.sheets().by_name(), sheets.by_name());
+        for name in sheets.by_name() {
+            let ws = sheets.get(name).unwrap();
+            let expected: Vec<String> = ws
+                .rows(&mut deflated)
+                .unwrap()
+                .map(|row| row.to_string())
+                .collect();
+            let rows: Vec<String> = ws
+                .rows(&mut stored)
+                .unwrap()
+                .map(|row| row.to_string())
+                .collect();
+            assert_eq!(rows, expected, "{}", name);
+            assert_eq!(
+                ws.read_to_buffer(&mut stored).unwrap(),
+                ws.read_to_buffer(&mut deflated).unwrap(),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_column_count() {
+        let mut wb = Workbook::open("tests/data/7_nulls.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        assert_eq!(ws.column_count(&mut wb).unwrap(), 6); // A1:F8
+        assert_eq!(ws.row_count(&mut wb).unwrap(), 8);
+        let rows = ws.to_vec(&mut wb).unwrap();
+        assert_eq!(rows.len(), 8);
+        assert!(rows.iter().all(|row| row.0.len() == 6));
+        // computed from the cells when there is no dimension
+        let mut wb = Workbook::open("tests/data/no_dimension.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        assert_eq!(ws.column_count(&mut wb).unwrap(), 2);
+        assert_eq!(ws.row_count(&mut wb).unwrap(), 4);
+        // the declared dimension (A1:F20) is stale unless it is recomputed
+        let mut wb = Workbook::open("tests/data/stale_dimension.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        assert_eq!(ws.column_count(&mut wb).unwrap(), 6);
+        assert_eq!(ws.row_count(&mut wb).unwrap(), 20);
+        let options = WorkbookOptions::new().recompute_dimensions(true);
+        let mut wb = Workbook::open_with("tests/data/stale_dimension.xlsx", options).unwrap();
+        assert_eq!(ws.column_count(&mut wb).unwrap(), 2);
+        assert_eq!(ws.row_count(&mut wb).unwrap(), 4);
+        let mut wb = Workbook::open("tests/data/chartsheet.xlsx").unwrap();
+        let sheets = wb.sheets();
+        for name in sheets.by_name() {
+            let ws = sheets.get(name).unwrap();
+            if ws.kind == SheetKind::Chart {
+                assert_eq!(ws.column_count(&mut wb).unwrap(), 0);
+                assert_eq!(ws.row_count(&mut wb).unwrap(), 0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_max_cells() {
+        // 1000 rows of 10 cells
+        let options = WorkbookOptions::new().max_cells(995);
+        let mut wb = Workbook::open_with("tests/data/many_cells.xlsx", options).unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let mut rows = ws.rows(&mut wb).unwrap();
+        assert!(rows.error().is_none());
+        assert_eq!(rows.by_ref().count(), 99);
+        assert!(matches!(rows.error(), Some(XlError::LimitExceeded(_))));
+        assert!(rows.next().is_none());
+        drop(rows);
+        assert!(matches!(ws.to_vec(&mut wb), Err(XlError::LimitExceeded(_))));
+        let mut out = vec![];
+        assert!(matches!(
+            ws.write_csv(&mut wb, &mut out),
+            Err(XlError::LimitExceeded(_))
+        ));
+        // the empty cells that fill out the dimension count too
+        let options = WorkbookOptions::new().max_cells(10);
+        let mut wb = Workbook::open_with("tests/data/stale_dimension.xlsx", options).unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        assert!(matches!(ws.to_vec(&mut wb), Err(XlError::LimitExceeded(_))));
+        // exactly at the limit is fine
+        let options = WorkbookOptions::new().max_cells(10_000);
+        let mut wb = Workbook::open_with("tests/data/many_cells.xlsx", options).unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        assert_eq!(ws.to_vec(&mut wb).unwrap().len(), 1000);
+        let mut out = vec![];
+        ws.write_csv(&mut wb, &mut out).unwrap();
+    }
+
+    #[test]
+    fn test_max_decompressed_bytes() {
+        // the sheet is too big according to the zip's directory, so nothing is read at all
+        let options = WorkbookOptions::new().max_decompressed_bytes(10_000);
+        let mut wb = Workbook::open_with("tests/data/many_cells.xlsx", options).unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        match ws.rows(&mut wb) {
+            Err(XlError::LimitExceeded(msg)) => assert!(msg.contains("xl/worksheets/sheet1.xml")),
+            _ => panic!("expected the limit to be exceeded"),
+        }
+        // the directory claims the sheet is 1000 bytes, so it is only caught while reading
+        let options = WorkbookOptions::new().max_decompressed_bytes(10_000);
+        let mut wb = Workbook::open_with("tests/data/understated_size.xlsx", options).unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let mut rows = ws.rows(&mut wb).unwrap();
+        let n = rows.by_ref().count();
+        assert!(n > 0 && n < 1000, "{}", n);
+        assert!(matches!(rows.error(), Some(XlError::LimitExceeded(_))));
+        drop(rows);
+        let mut out = vec![];
+        assert!(matches!(
+            ws.write_csv(&mut wb, &mut out),
+            Err(XlError::LimitExceeded(_))
+        ));
+        // a generous limit changes nothing
+        let options = WorkbookOptions::new()
+            .max_decompressed_bytes(1_000_000)
+            .recompute_dimensions(true);
+        let mut wb = Workbook::open_with("tests/data/understated_size.xlsx", options).unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        assert_eq!(ws.to_vec(&mut wb).unwrap().len(), 1000);
+    }
+
+    #[test]
+    fn test_scientific_notation() {
+        let mut wb = Workbook::open("./tests/data/scientific.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let row = ws.rows(&mut wb).unwrap().next().unwrap();
+        assert_eq!(row[0].raw_value, "4.4E4");
+        assert_eq!(
+            row[0].value,
+            ExcelValue::Date(NaiveDate::from_ymd(2020, 6, 18))
+        );
+        assert_eq!(row[1].value, ExcelValue::Number(6.022e23));
+        assert_eq!(row[2].value, ExcelValue::Number(0.000123));
+        let csv = String::from_utf8(ws.read_to_buffer(&mut wb).unwrap()).unwrap();
+        assert_eq!(csv, "2020-06-18,\"6.022E23\",\"1.23E-4\"\n");
+    }
+
+    #[test]
+    fn test_populated_count() {
+        let mut wb = Workbook::open("./tests/data/7_nulls.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let counts: Vec<usize> = ws
+            .rows(&mut wb)
+            .unwrap()
+            .map(|r| r.populated_count())
+            .collect();
+        // empty strings (<is><t></t></is>) count, cells without a value do not
+        assert_eq!(counts, vec![5, 5, 5, 5, 4, 4, 6, 4]);
+    }
+
+    #[test]
+    fn test_raw_strings() {
+        let mut wb = Workbook::open("./tests/data/7_nulls.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let row = ws.rows(&mut wb).unwrap().raw_strings().nth(1).unwrap();
+        assert_eq!(row[0].value, ExcelValue::String(Cow::Borrowed("0")));
+        assert_eq!(row[1].value, ExcelValue::String(Cow::Borrowed("foo")));
+        assert_eq!(row[3].value, ExcelValue::None);
+
+        let mut wb = Workbook::open("./tests/data/scientific.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let row = ws.rows(&mut wb).unwrap().raw_strings().next().unwrap();
+        // a date-formatted number stays as written
+        assert_eq!(row[0].value, ExcelValue::String(Cow::Borrowed("4.4E4")));
+        assert_eq!(row[1].value, ExcelValue::String(Cow::Borrowed("6.022E23")));
+    }
+
+    #[test]
+    fn test_chartsheet() {
+        let mut wb = Workbook::open("./tests/data/chartsheet.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let kinds: Vec<SheetKind> = (1..=sheets.len())
+            .map(|n| sheets.get(n as usize).unwrap().kind)
+            .collect();
+        assert_eq!(kinds, vec![SheetKind::Worksheet, SheetKind::Chart]);
+        let chart = sheets.get("Chart1").unwrap();
+        assert_eq!(chart.rows(&mut wb).unwrap().count(), 0);
+        let data = sheets.get("Data").unwrap();
+        assert_eq!(data.rows(&mut wb).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_min_columns() {
+        let mut wb = Workbook::open("./tests/data/no_dimension.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let widths: Vec<usize> = ws.rows(&mut wb).unwrap().map(|r| r.0.len()).collect();
+        // the (empty) first row is read before any cell, so there is nothing to size it by
+        assert_eq!(widths, vec![0, 2, 2, 2]);
+        let rows: Vec<_> = ws.rows(&mut wb).unwrap().with_min_columns(4).collect();
+        let widths: Vec<usize> = rows.iter().map(|r| r.0.len()).collect();
+        assert_eq!(widths, vec![4, 4, 4, 4]);
+        assert_eq!(rows[1][3].reference, "D2");
+        assert_eq!(rows[2][1].raw_value, "2");
+    }
+
+    #[test]
+    fn test_count_distinct_values() {
+        let mut wb = Workbook::open("./tests/data/7_nulls.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let rows = ws.to_vec(&mut wb).unwrap();
+        let mut counts: HashMap<&ExcelValue, usize> = HashMap::new();
+        for row in &rows[1..] {
+            *counts.entry(&row[4].value).or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), 6);
+        assert_eq!(counts[&ExcelValue::None], 2);
+        assert_eq!(counts[&ExcelValue::String(Cow::Borrowed(""))], 1);
+        assert_eq!(counts[&ExcelValue::String(Cow::Borrowed("foo"))], 1);
+        assert_eq!(counts[&ExcelValue::Number(0.9373419333844548)], 1);
+
+        let mut zeros = HashMap::new();
+        zeros.insert(ExcelValue::Number(0.0), 1);
+        assert_eq!(zeros.get(&ExcelValue::Number(-0.0)), Some(&1));
+    }
+
+    #[test]
+    fn test_head() {
+        // the xml is broken in the fifth row, so this only works if we stop reading early
+        let mut wb = Workbook::open("./tests/data/truncated_sheet.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let rows = ws.head(&mut wb, 3).unwrap();
+        let values: Vec<&str> = rows.iter().map(|r| &r[0].raw_value[..]).collect();
+        assert_eq!(values, vec!["1", "2", "3"]);
+        assert!(ws.head(&mut wb, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_empty_strings() {
+        let mut wb = Workbook::open("./tests/data/empty_strings.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let row = ws.rows(&mut wb).unwrap().next().unwrap();
+        let empty = ExcelValue::String(Cow::Borrowed(""));
+        assert_eq!(row[0].value, empty); // <v></v>
+        assert_eq!(row[1].value, ExcelValue::None); // no cell at all
+        assert_eq!(row[2].value, empty); // <is><t/></is>
+        assert_eq!(row[3].value, ExcelValue::None); // a string cell without a value
+        assert_eq!(row[4].value, empty); // an empty shared string
+        assert_eq!(row[5].value, ExcelValue::None); // an empty number
+        assert_eq!(row[6].value, empty); // <v/>
+        assert_eq!(row[7].value, ExcelValue::String(Cow::Borrowed("text")));
+    }
+
+    #[test]
+    fn test_empty_strings_csv() {
+        // the csv writer and `rows` agree on which cells are empty strings and which are missing
+        let mut wb = Workbook::open("./tests/data/empty_strings.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let expected: Vec<String> = ws
+            .rows(&mut wb)
+            .unwrap()
+            .next()
+            .unwrap()
+            .0
+            .iter()
+            .map(|cell| match &cell.value {
+                ExcelValue::None => "NULL".to_string(),
+                ExcelValue::String(s) => format!("\"{}\"", s),
+                v => panic!("unexpected value {:?}", v),
+            })
+            .collect();
+        let options = CsvOptions {
+            null_text: Some("NULL".to_string()),
+            ..CsvOptions::default()
+        };
+        let mut out = vec![];
+        ws.write_csv_with_options(&mut wb, &mut out, &options)
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), expected.join(",") + "\n");
+        let buffer = String::from_utf8(ws.read_to_buffer(&mut wb).unwrap()).unwrap();
+        assert_eq!(buffer, expected.join(",").replace("NULL", "") + "\n");
+    }
+
+    #[test]
+    fn test_outline() {
+        let mut wb = Workbook::open("./tests/data/outline.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let rows: Vec<_> = ws.rows(&mut wb).unwrap().collect();
+        let levels: Vec<_> = rows.iter().map(|r| r.outline_level()).collect();
+        assert_eq!(levels, vec![0, 1, 2, 2, 1, 0]);
+        let collapsed: Vec<_> = rows.iter().map(|r| r.collapsed()).collect();
+        assert_eq!(collapsed, vec![false, false, false, false, true, true]);
+
+        let columns = ws.columns_info(&mut wb).unwrap();
+        assert_eq!(
+            columns,
+            vec![
+                ColumnInfo {
+                    first_col: 1,
+                    last_col: 1,
+                    width: Some(20.5),
+                    hidden: false,
+                    outline_level: 0,
+                    collapsed: false,
+                },
+                ColumnInfo {
+                    first_col: 2,
+                    last_col: 3,
+                    width: Some(9.140625),
+                    hidden: true,
+                    outline_level: 1,
+                    collapsed: false,
+                },
+                ColumnInfo {
+                    first_col: 4,
+                    last_col: 4,
+                    width: Some(9.140625),
+                    hidden: true,
+                    outline_level: 2,
+                    collapsed: false,
+                },
+                ColumnInfo {
+                    first_col: 5,
+                    last_col: 5,
+                    width: Some(9.140625),
+                    hidden: false,
+                    outline_level: 0,
+                    collapsed: true,
+                },
+            ]
+        );
+        // no <cols> at all
+        let mut wb = Workbook::open("./tests/data/7_nulls.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        assert!(ws.columns_info(&mut wb).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_dimension() {
+        let mut wb = Workbook::open("./tests/data/offset_dimension.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let declared = sheets.get("Declared").unwrap().dimension(&mut wb).unwrap();
+        assert_eq!(
+            declared,
+            Dimension {
+                first_row: 2,
+                last_row: 20,
+                first_col: 2,
+                last_col: 6,
+            }
+        );
+        let computed = sheets.get("Computed").unwrap().dimension(&mut wb).unwrap();
+        assert_eq!(
+            computed,
+            Dimension {
+                first_row: 3,
+                last_row: 7,
+                first_col: 3,
+                last_col: 5,
+            }
+        );
+        let empty = sheets.get("Empty").unwrap().dimension(&mut wb).unwrap();
+        assert_eq!((empty.last_row, empty.last_col), (1, 1));
+    }
+
+    #[test]
+    fn test_write_csv_map() {
+        let mut wb = Workbook::open("./tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let mut out = vec![];
+        ws.write_csv_map(&mut wb, &mut out, |row| {
+            let mut record: Vec<ExcelValue> =
+                row.0.iter().take(2).map(|c| c.value.clone()).collect();
+            record.push(ExcelValue::String(Cow::Owned(format!("row \"{}\"", row.1))));
+            record.push(ExcelValue::None);
+            record.push(ExcelValue::Date(NaiveDate::from_ymd(
+                2020,
+                1,
+                row.1 as u32 % 28 + 1,
+            )));
+            record
+        })
+        .unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 46);
+        assert_eq!(lines[0], "\"1\",\"2\",\"row \"\"1\"\"\",,2020-01-02");
+        assert_eq!(lines[1], "\"19\",\"20\",\"row \"\"2\"\"\",,2020-01-03");
+
+        // a number that is passed through is written the same as by `write_csv`, with every digit
+        let mut wb = Workbook::open("./tests/data/7_nulls.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let mut out = vec![];
+        ws.write_csv_map(&mut wb, &mut out, |row| {
+            vec![row[0].value.clone(), row[2].value.clone()]
+        })
+        .unwrap();
+        let mapped = String::from_utf8(out).unwrap();
+        let csv = String::from_utf8(ws.read_to_buffer(&mut wb).unwrap()).unwrap();
+        let csv_fields: Vec<&str> = csv.lines().nth(1).unwrap().split(',').collect();
+        assert_eq!(csv_fields[0], "\"0\"");
+        assert_eq!(csv_fields[2], "\"0.4664743800292485\"");
+        assert_eq!(mapped.lines().nth(1), Some("\"0\",\"0.4664743800292485\""));
+    }
+
+    #[test]
+    fn test_row_attributes() {
+        let mut wb = Workbook::open("./tests/data/row_heights.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let rows: Vec<_> = ws.rows(&mut wb).unwrap().with_row_attributes().collect();
+        assert_eq!(rows[0].attributes()["ht"], "30");
+        assert_eq!(rows[0].attributes()["customHeight"], "1");
+        assert_eq!(rows[0].attributes()["s"], "1");
+        assert_eq!(rows[0].attributes().len(), 5);
+        // a simulated empty row
+        assert!(rows[1].attributes().is_empty());
+        assert_eq!(rows[2].attributes()["r"], "3");
+        assert_eq!(rows[2].attributes().get("ht"), None);
+        // not collected unless asked for
+        let row = ws.rows(&mut wb).unwrap().next().unwrap();
+        assert!(row.attributes().is_empty());
+    }
+
+    #[test]
+    fn test_lowercase_coordinates() {
+        assert_eq!(crate::utils::parse_reference("b3"), Some((2, 3)));
+        assert_eq!(crate::utils::parse_reference("aa10"), Some((27, 10)));
+        let mut cell = super::new_cell();
+        cell.reference = "aa10".to_string();
+        assert_eq!(cell.coordinates(), (27, 10));
+    }
+
+    #[test]
+    fn test_column_values() {
+        let mut wb = Workbook::open("./tests/data/7_nulls.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let column = ws.column_values(&mut wb, "b").unwrap();
+        assert_eq!(
+            column,
+            vec![
+                ExcelValue::String(Cow::Borrowed("0")),
+                ExcelValue::String(Cow::Borrowed("foo")),
+                ExcelValue::None,
+                ExcelValue::None,
+                ExcelValue::None,
+                ExcelValue::String(Cow::Borrowed("baz")),
+                ExcelValue::String(Cow::Borrowed("bar")),
+                ExcelValue::Number(0.3870971408372121),
+            ]
+        );
+        // a column beyond the data is all empty
+        let column = ws.column_values(&mut wb, "Z").unwrap();
+        assert_eq!(column.len(), 8);
+        assert!(column.iter().all(|v| *v == ExcelValue::None));
+        assert!(matches!(
+            ws.column_values(&mut wb, "A1"),
+            Err(XlError::InvalidReference(_))
+        ));
+    }
+
+    #[test]
+    fn test_related_parts() {
+        let rel = |t: &str| {
+            format!(
+                "http://schemas.openxmlformats.org/officeDocument/2006/relationships/{}",
+                t
+            )
+        };
+        let mut wb = Workbook::open("./tests/data/attachments.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Pictures").unwrap();
+        assert_eq!(
+            ws.related_parts(&mut wb),
+            vec![
+                (rel("drawing"), "../drawings/drawing1.xml".to_string()),
+                (rel("hyperlink"), "https://example.com/?a=1&b=2".to_string()),
+                (rel("comments"), "../comments1.xml".to_string()),
+                (rel("table"), "../tables/table1.xml".to_string()),
+            ]
+        );
+        let ws = sheets.get("Plain").unwrap();
+        assert!(ws.related_parts(&mut wb).is_empty());
+    }
+
+    #[test]
+    fn test_iter_with_refs() {
+        let mut wb = Workbook::open("./tests/data/7_nulls.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let row = ws.rows(&mut wb).unwrap().nth(1).unwrap();
+        let pairs: Vec<String> = row
+            .iter_with_refs()
+            .map(|(reference, value)| format!("{}={}", reference, value))
+            .collect();
+        let expected: Vec<String> = row
+            .0
+            .iter()
+            .map(|c| format!("{}={}", c.reference, c.value))
+            .collect();
+        assert_eq!(pairs, expected);
+        assert_eq!(pairs.len(), 6);
+        assert!(pairs[0].starts_with("A2="));
+        assert!(pairs[5].starts_with("F2="));
+    }
+
+    #[test]
+    fn test_text_as_strings() {
+        let mut wb = Workbook::open("./tests/data/text_format.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let row = ws.rows(&mut wb).unwrap().next().unwrap();
+        assert_eq!(row[0].value, ExcelValue::Number(7.0));
+        assert_eq!(row[1].value, ExcelValue::Number(2100.0));
+        let row = ws.rows(&mut wb).unwrap().text_as_strings().next().unwrap();
+        assert_eq!(row[0].value, ExcelValue::String(Cow::Borrowed("007")));
+        assert_eq!(row[1].value, ExcelValue::String(Cow::Borrowed("02100")));
+        // numbers with other formats, text, and booleans are left alone
+        assert_eq!(row[2].value, ExcelValue::Number(7.0));
+        assert_eq!(row[3].value, ExcelValue::String(Cow::Borrowed("0042")));
+        assert_eq!(row[4].value, ExcelValue::Bool(true));
+    }
+
+    #[test]
+    fn test_inline_rich_text() {
+        let mut wb = Workbook::open("./tests/data/inline_rich_text.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let rows = ws.to_vec(&mut wb).unwrap();
+        let values: Vec<String> = rows[0].0.iter().map(|c| c.value.to_string()).collect();
+        assert_eq!(
+            values,
+            vec![
+                "\"plain\"",
+                "\"Hello World\"",
+                "\"東京\"",
+                "\"  padded  \"",
+                "\"plain\"",
+                "\"trimmed\"",
+            ]
+        );
+        assert_eq!(rows[0][1].raw_value, "Hello World");
+        assert!(rows[0]
+            .0
+            .iter()
+            .all(|c| c.formula.is_empty() || c.formula == "A1"));
+        assert_eq!(rows[1][0].value, ExcelValue::Number(2.0));
+        assert_eq!(rows[1][1].value, ExcelValue::String(Cow::Borrowed("a & b")));
+        let csv = String::from_utf8(ws.read_to_buffer(&mut wb).unwrap()).unwrap();
+        assert_eq!(
+            csv,
+            "\"plain\",\"Hello World\",\"東京\",\"  padded  \",\"plain\",\"trimmed\"\n\
+             \"2\",\"a & b\",,,,\n"
+        );
+    }
+
+    #[test]
+    fn test_percentages() {
+        let mut wb = Workbook::open("./tests/data/percentages.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let row = ws.rows(&mut wb).unwrap().next().unwrap();
+        let styles: Vec<&str> = row.0.iter().map(|c| &c.style[..]).collect();
+        assert_eq!(
+            styles,
+            vec![
+                "0%",
+                "0.00%",
+                "0.0\"%\"",
+                "0\\%",
+                "[Red]0.0%;[Blue]-0.0%",
+                "0%",
+                "",
+                "0%"
+            ]
+        );
+        let percentages: Vec<Option<f64>> = row.0.iter().map(|c| c.as_percentage()).collect();
+        assert_eq!(
+            percentages,
+            vec![
+                Some(5.0),
+                Some(12.34),
+                None,
+                None,
+                Some(-50.0),
+                None, // text
+                None, // no format
+                Some(1.1),
+            ]
+        );
+        let flags: Vec<bool> = row.0.iter().map(|c| c.is_percentage()).collect();
+        assert_eq!(
+            flags,
+            vec![true, true, false, false, true, false, false, true]
+        );
+    }
+
+    #[test]
+    fn test_style_index() {
+        let mut wb = Workbook::open("./tests/data/mixed_types.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let rows = ws.to_vec(&mut wb).unwrap();
+        let indexes: Vec<_> = rows.iter().map(|row| row[1].style_index()).collect();
+        assert_eq!(indexes, vec![Some(1), None, None, Some(2), None, None]);
+        assert_eq!(rows[0][1].style, "mm-dd-yy");
+        // simulated cells have no style
+        let mut wb = Workbook::open("./tests/data/empty_sheets.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Single").unwrap();
+        let row1 = ws.rows(&mut wb).unwrap().next().unwrap();
+        assert!(row1.0.iter().all(|c| c.style_index().is_none()));
+    }
+
+    #[test]
+    fn test_lenient_getters() {
+        let mut wb = Workbook::open("./tests/data/mixed_types.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let rows = ws.to_vec(&mut wb).unwrap();
+        let numbers: Vec<_> = rows.iter().map(|row| row[0].get_f64()).collect();
+        assert_eq!(
+            numbers,
+            vec![Some(1.5), Some(2.5), None, None, None, Some(-3.0)]
+        );
+        let date = NaiveDate::from_ymd(2020, 6, 18);
+        let dates: Vec<_> = rows.iter().map(|row| row[1].get_date()).collect();
+        assert_eq!(
+            dates,
+            vec![Some(date), Some(date), Some(date), Some(date), None, None]
+        );
+        // the values themselves are left alone
+        assert_eq!(rows[1][0].value, ExcelValue::String(Cow::Borrowed(" 2.5 ")));
+        assert_eq!(rows[5][1].value, ExcelValue::Number(44000.0));
+    }
+
+    #[test]
+    fn test_header_row() {
+        let mut wb = Workbook::open("./tests/data/empty_sheets.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Single").unwrap();
+        let header = ws.header_row(&mut wb).unwrap().unwrap();
+        assert_eq!(header.1, 5);
+        assert_eq!(header[2].value, ExcelValue::Number(7.0));
+        let ws = sheets.get("Formatted").unwrap();
+        assert!(ws.header_row(&mut wb).unwrap().is_none());
+        let ws = sheets.get("Empty").unwrap();
+        assert!(ws.header_row(&mut wb).unwrap().is_none());
+        // the rest of the sheet is never read, so the broken xml further down does not matter
+        let mut wb = Workbook::open("./tests/data/truncated_sheet.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let header = ws.header_row(&mut wb).unwrap().unwrap();
+        assert_eq!(header[0].raw_value, "1");
+    }
+
+    #[test]
+    fn test_is_empty() {
+        let mut wb = Workbook::open("./tests/data/empty_sheets.xlsx").unwrap();
+        let sheets = wb.sheets();
+        assert!(sheets.get("Empty").unwrap().is_empty(&mut wb).unwrap());
+        assert!(sheets.get("Formatted").unwrap().is_empty(&mut wb).unwrap());
+        assert!(!sheets.get("Single").unwrap().is_empty(&mut wb).unwrap());
+        assert!(!sheets.get("Inline").unwrap().is_empty(&mut wb).unwrap());
+        let mut wb = Workbook::open("./tests/data/chartsheet.xlsx").unwrap();
+        let sheets = wb.sheets();
+        assert!(sheets.get("Chart1").unwrap().is_empty(&mut wb).unwrap());
+    }
+
+    #[test]
+    fn test_last_row() {
+        let mut wb = Workbook::open("./tests/data/trailing_formatting.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        assert_eq!(ws.dimension(&mut wb).unwrap().last_row, 30);
+        assert_eq!(ws.last_row(&mut wb).unwrap(), 5);
+        let mut wb = Workbook::open("./tests/data/stale_dimension.xlsx").unwrap();
+        let sheets = wb.sheets();
+        assert_eq!(sheets.get(1).unwrap().last_row(&mut wb).unwrap(), 4);
+        let mut wb = Workbook::open("./tests/data/empty_sheets.xlsx").unwrap();
+        let sheets = wb.sheets();
+        assert_eq!(
+            sheets.get("Formatted").unwrap().last_row(&mut wb).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_formula_error_results() {
+        let mut wb = Workbook::open("./tests/data/formula_errors.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let row = ws.rows(&mut wb).unwrap().next().unwrap();
+        assert_eq!(row[1].value, ExcelValue::Error("#N/A".to_string()));
+        assert_eq!(row[1].formula, "VLOOKUP(5,A1:A1,1,FALSE)");
+        assert_eq!(row[1].raw_value, "#N/A");
+        assert_eq!(row[2].value, ExcelValue::Error("#DIV/0!".to_string()));
+        assert_eq!(row[2].formula, "A1/0");
+        let csv = String::from_utf8(ws.read_to_buffer(&mut wb).unwrap()).unwrap();
+        assert_eq!(csv, "\"1\",#N/A,#DIV/0!\n");
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let errors = [
+            ("#NULL!", 2000),
+            ("#DIV/0!", 2007),
+            ("#VALUE!", 2015),
+            ("#REF!", 2023),
+            ("#NAME?", 2029),
+            ("#NUM!", 2036),
+            ("#N/A", 2042),
+        ];
+        for (text, code) in errors.iter() {
+            let error = ExcelError::from(*text);
+            assert_eq!(error.code(), *code);
+            assert_eq!(error.to_string(), *text);
+        }
+        let spill = ExcelError::from("#SPILL!");
+        assert_eq!(spill, ExcelError::Other("#SPILL!".to_string()));
+        assert_eq!(spill.code(), 0);
+        assert_eq!(spill.to_string(), "#SPILL!");
+        // the errors read from a sheet
+        let mut wb = Workbook::open("./tests/data/formula_errors.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let row = ws.rows(&mut wb).unwrap().next().unwrap();
+        let codes: Vec<u16> = row
+            .0
+            .iter()
+            .filter_map(|c| match &c.value {
+                ExcelValue::Error(e) => Some(ExcelError::from(&e[..]).code()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(codes, vec![2042, 2007]);
+    }
+
+    #[test]
+    fn test_rows_from() {
+        let mut wb = Workbook::open("./tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let all: Vec<_> = ws
+            .rows(&mut wb)
+            .unwrap()
+            .map(|row| {
+                (
+                    row.1,
+                    row.0
+                        .iter()
+                        .map(|c| c.raw_value.clone())
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect();
+        let from: Vec<_> = ws
+            .rows_from(&mut wb, 40)
+            .unwrap()
+            .map(|row| {
+                (
+                    row.1,
+                    row.0
+                        .iter()
+                        .map(|c| c.raw_value.clone())
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect();
+        assert_eq!(from, all[39..]);
+        assert_eq!(ws.rows_from(&mut wb, 0).unwrap().next().unwrap().1, 1);
+        assert!(ws.rows_from(&mut wb, 1000).unwrap().next().is_none());
+        // starting on an empty row in the middle of the sheet
+        let mut wb = Workbook::open("./tests/data/7_nulls.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let expected: Vec<_> = ws.rows(&mut wb).unwrap().map(|row| row.1).collect();
+        let rows: Vec<_> = ws.rows_from(&mut wb, 3).unwrap().map(|row| row.1).collect();
+        assert_eq!(rows, expected[2..]);
+    }
+
+    #[test]
+    fn test_inline_strings_only() {
+        let mut wb = Workbook::open("./tests/data/inline_strings.xlsx").unwrap();
+        assert!(wb.shared_strings().is_empty());
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let rows = ws.to_vec(&mut wb).unwrap();
+        assert_eq!(rows[0][0].value, ExcelValue::String(Cow::Borrowed("Name")));
+        assert_eq!(
+            rows[1][0].value,
+            ExcelValue::String(Cow::Borrowed("Ann & Bob"))
+        );
+        assert_eq!(
+            rows[1][1].value,
+            ExcelValue::String(Cow::Borrowed("say \"hi\""))
+        );
+        assert_eq!(rows[1][2].value, ExcelValue::Number(3.0));
+        // there is no shared string table to look the index up in, so the raw value is kept
+        assert_eq!(rows[2][0].value, ExcelValue::String(Cow::Borrowed("0")));
+        let csv = String::from_utf8(ws.read_to_buffer(&mut wb).unwrap()).unwrap();
+        assert_eq!(
+            csv,
+            "\"Name\",\"Quote\",\n\"Ann & Bob\",\"say \"\"hi\"\"\",\"3\"\n\"0\",,\n"
+        );
+    }
+
+    #[test]
+    fn test_last_column() {
+        let mut wb = Workbook::open("./tests/data/wide.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let rows = ws.to_vec(&mut wb).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].0.len(), 16384);
+        assert_eq!(rows[0][16383].reference, "XFD1");
+        assert_eq!(rows[0][16383].value, ExcelValue::Number(16384.0));
+        // XFE2 is not on the sheet, so it is dropped rather than wrapping around to column A
+        assert_eq!(rows[1].0.len(), 16384);
+        assert_eq!(rows[1][0].value, ExcelValue::None);
+        assert_eq!(rows[1][1].value, ExcelValue::Number(2.0));
+        assert_eq!(rows[1].populated_count(), 1);
+        let csv = String::from_utf8(ws.read_to_buffer(&mut wb).unwrap()).unwrap();
+        assert!(csv.starts_with("\"1\","));
+        assert!(csv.lines().next().unwrap().ends_with(",\"16384\""));
+        // a dimension past the last column is ignored
+        let ws = sheets.get("Past XFD").unwrap();
+        let rows = ws.to_vec(&mut wb).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].0.len(), 1);
+    }
 
-impl<'a> Index<u16> for Row<'a> {
-    type Output = Cell<'a>;
+    #[test]
+    fn test_explicit_number_type() {
+        let mut wb = Workbook::open("./tests/data/explicit_numbers.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let row = ws.rows(&mut wb).unwrap().next().unwrap();
+        assert_eq!(row[0].value, ExcelValue::Number(42.0));
+        // t="n" cells still become dates when they have a date format...
+        assert_eq!(
+            row[1].value,
+            ExcelValue::Date(NaiveDate::from_ymd(2020, 6, 18))
+        );
+        // ...but not when they have any other format
+        assert_eq!(row[2].value, ExcelValue::Number(1.5));
+        assert_eq!(row[3].value, ExcelValue::Number(-0.25));
+    }
 
-    fn index(&self, column_index: u16) -> &Self::Output {
-        &self.0[column_index as usize]
+    #[test]
+    fn test_whole_numbers() {
+        let mut wb = Workbook::open("./tests/data/whole_numbers.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let integers: Vec<bool> = ws
+            .rows(&mut wb)
+            .unwrap()
+            .map(|r| r[0].is_integer())
+            .collect();
+        assert_eq!(integers, vec![true, true, true, false, true, true]);
+        let csv = String::from_utf8(ws.read_to_buffer(&mut wb).unwrap()).unwrap();
+        // the second column has a "0.00" format, so it is left as is
+        assert_eq!(
+            csv,
+            concat!(
+                "\"3\",\"3.0\"\n",
+                "\"10\",\"10.00\"\n",
+                "\"-2\",\"-2.0\"\n",
+                "\"2.5\",\"2.5\"\n",
+                "\"6.022E23\",\"6.022E23\"\n",
+                "\"7\",\"7\"\n",
+            )
+        );
     }
-}
 
-impl fmt::Display for Row<'_> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let vec = &self.0;
-        for (count, v) in vec.iter().enumerate() {
-            if count != 0 {
-                write!(f, ",")?;
+    #[test]
+    fn test_view_settings() {
+        let mut wb = Workbook::open("./tests/data/sheet_view.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        assert_eq!(
+            ws.view_settings(&mut wb).unwrap(),
+            ViewSettings {
+                show_grid_lines: false,
+                show_row_col_headers: false,
+                zoom_scale: 85,
             }
-            write!(f, "{}", v)?;
-        }
-        write!(f, "")
+        );
+        let mut wb = Workbook::open("./tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        assert_eq!(ws.view_settings(&mut wb).unwrap(), ViewSettings::default());
     }
-}
 
-impl fmt::Display for Cell<'_> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.value)
+    #[test]
+    fn test_page_setup() {
+        let mut wb = Workbook::open("./tests/data/page_setup.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        assert_eq!(
+            ws.page_setup(&mut wb).unwrap(),
+            PageSetup {
+                orientation: Orientation::Landscape,
+                paper_size: 9,
+                scale: 75,
+                left_margin: 0.25,
+                right_margin: 0.3,
+                top_margin: 1.0,
+                bottom_margin: 1.5,
+            }
+        );
+        // no print settings at all
+        let mut wb = Workbook::open("./tests/data/sheet_view.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        assert_eq!(ws.page_setup(&mut wb).unwrap(), PageSetup::default());
     }
-}
 
-pub struct RowIter<'a> {
-    worksheet_reader: SheetReader<'a>,
-    want_row: usize,
-    next_row: Option<Row<'a>>,
-    num_rows: u32,
-    num_cols: u16,
-    done_file: bool,
-}
+    #[test]
+    fn test_print_area() {
+        let mut wb = Workbook::open("./tests/data/print_area.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let area = |name: &str, wb: &mut Workbook<_>| sheets.get(name).unwrap().print_area(wb);
+        assert_eq!(area("Report", &mut wb), Some(("A1".into(), "C3".into())));
+        assert_eq!(
+            area("Other Data", &mut wb),
+            Some(("B2".into(), "D4".into()))
+        );
+        assert_eq!(area("Single", &mut wb), Some(("E5".into(), "E5".into())));
+        assert_eq!(area("None", &mut wb), None);
+    }
 
-fn new_cell() -> Cell<'static> {
-    Cell {
-        value: ExcelValue::None,
-        formula: "".to_string(),
-        reference: "".to_string(),
-        style: "".to_string(),
-        cell_type: "".to_string(),
-        raw_value: "".to_string(),
+    #[test]
+    fn test_byte_position() {
+        let mut wb = Workbook::open("./tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let part = format!("xl/{}", ws.target());
+        let size = wb
+            .part_info()
+            .into_iter()
+            .find(|p| p.name == part)
+            .unwrap()
+            .uncompressed_size as usize;
+        let mut rows = ws.rows(&mut wb).unwrap();
+        let mut last = rows.byte_position();
+        assert_eq!(last, 0);
+        while rows.next().is_some() {
+            let position = rows.byte_position();
+            assert!(position > last);
+            assert!(position <= size);
+            last = position;
+        }
+        assert_eq!(rows.byte_position(), size);
     }
-}
 
-fn empty_row(num_cols: u16, this_row: usize) -> Option<Row<'static>> {
-    let mut row = vec![];
-    for n in 0..num_cols {
-        let mut c = new_cell();
-        c.reference.push_str(&utils::num2col(n + 1).unwrap());
-        c.reference.push_str(&this_row.to_string());
-        row.push(c);
+    #[test]
+    fn test_to_array() {
+        let mut wb = Workbook::open("./tests/data/7_nulls.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let rows = ws.to_vec(&mut wb).unwrap();
+        let [a, b, c, d, e, f] = rows[2].to_array().unwrap();
+        assert_eq!(a, ExcelValue::Number(1.0));
+        assert_eq!(b, ExcelValue::None);
+        assert_eq!(c, ExcelValue::Number(0.6363620246706366));
+        assert_eq!(d, ExcelValue::String(Cow::Borrowed("baz")));
+        assert_eq!(e, ExcelValue::String(Cow::Borrowed("foo")));
+        assert_eq!(f, rows[2][5].value);
+        assert!(rows[2].to_array::<0>().is_none());
+        assert!(rows[2].to_array::<7>().is_none());
+        // every row is as wide as the sheet, including the empty ones
+        let arrays: Vec<_> = rows.iter().filter_map(|row| row.to_array::<6>()).collect();
+        assert_eq!(arrays.len(), rows.len());
     }
-    Some(Row(row, this_row))
-}
 
-impl<'a> Iterator for RowIter<'a> {
-    type Item = Row<'a>;
+    #[test]
+    fn test_row_styles() {
+        // row 1 is formatted as a date (with customFormat="1"), which applies to its cells that do
+        // not have a style of their own; row 2 has a style but does not ask for it to be applied
+        let mut wb = Workbook::open("./tests/data/row_styles.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let rows = ws.to_vec(&mut wb).unwrap();
+        let date = NaiveDate::from_ymd(2020, 6, 18);
+        assert_eq!(rows[0][0].value, ExcelValue::Date(date));
+        assert_eq!(rows[0][0].style, "mm-dd-yy");
+        assert_eq!(rows[0][0].style_index(), Some(1));
+        assert_eq!(rows[0][1].value, ExcelValue::Number(3.5));
+        assert_eq!(rows[0][1].style, "0.00");
+        assert_eq!(rows[0][2].value, ExcelValue::String(Cow::Borrowed("x")));
+        assert_eq!(rows[1][0].value, ExcelValue::Number(44000.0));
+        assert_eq!(rows[1][0].style_index(), None);
+        assert_eq!(rows[2][0].value, ExcelValue::Number(44000.0));
+        let csv = String::from_utf8(ws.read_to_buffer(&mut wb).unwrap()).unwrap();
+        assert_eq!(csv, "2020-06-18,\"3.5\",\"x\"\n\"44000\",,\n\"44000\",,\n");
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        // the xml in the xlsx file will not contain elements for empty rows. So
-        // we need to "simulate" the empty rows since the user expects to see
-        // them when they iterate over the worksheet.
-        if let Some(Row(_, row_num)) = &self.next_row {
-            // since we are currently buffering a row, we know we will either return it or a
-            // "simulated" (i.e., emtpy) row. So we grab the current row and update the fact that
-            // we will soon want a new row. We then figure out if we have the row we want or if we
-            // need to keep spitting out empty rows.
-            let current_row = self.want_row;
-            self.want_row += 1;
-            if *row_num == current_row {
-                // we finally hit the row we were looking for, so we reset the buffer and return
-                // the row that was sitting in it.
-                let mut r = None;
-                mem::swap(&mut r, &mut self.next_row);
-                return r;
-            } else {
-                // otherwise, we must still be sitting behind the row we want. So we return an
-                // empty row to simulate the row that exists in the spreadsheet.
-                return empty_row(self.num_cols, current_row);
-            }
-        } else if self.done_file && self.want_row < self.num_rows as usize {
-            self.want_row += 1;
-            return empty_row(self.num_cols, self.want_row - 1);
-        }
-        let mut buf = Vec::new();
-        let reader = &mut self.worksheet_reader.reader;
-        let strings = self.worksheet_reader.strings;
-        let styles = self.worksheet_reader.styles;
-        let date_system = self.worksheet_reader.date_system;
-        let next_row = {
-            let mut row: Vec<Cell> = Vec::with_capacity(self.num_cols as usize);
-            let mut in_cell = false;
-            let mut in_value = false;
-            let mut c = new_cell();
-            let mut this_row: usize = 0;
-            loop {
-                match reader.read_event(&mut buf) {
-                    /* may be able to get a better estimate for the used area */
-                    Ok(Event::Empty(ref e)) if e.name() == b"dimension" => {
-                        if let Some(used_area_range) = utils::get(e.attributes(), b"ref") {
-                            if used_area_range != "A1" {
-                                let (rows, cols) = used_area(&used_area_range);
-                                self.num_cols = cols;
-                                self.num_rows = rows;
-                            }
-                        }
-                    }
-                    /* -- end search for used area */
-                    Ok(Event::Start(ref e)) if e.name() == b"row" => {
-                        this_row = utils::get(e.attributes(), b"r").unwrap().parse().unwrap();
-                    }
-                    Ok(Event::Start(ref e)) if e.name() == b"c" => {
-                        in_cell = true;
-                        e.attributes().for_each(|a| {
-                            let a = a.unwrap();
-                            if a.key == b"r" {
-                                c.reference = utils::attr_value(&a);
-                            }
-                            if a.key == b"t" {
-                                c.cell_type = utils::attr_value(&a);
-                            }
-                            if a.key == b"s" {
-                                if let Ok(num) = utils::attr_value(&a).parse::<usize>() {
-                                    if let Some(style) = styles.get(num) {
-                                        c.style = style.to_string();
-                                    }
-                                }
-                            }
-                        });
-                    }
-                    Ok(Event::Start(ref e)) if e.name() == b"v" || e.name() == b"t" => {
-                        in_value = true;
-                    }
-                    // note: because v elements are children of c elements,
-                    // need this check to go before the 'in_cell' check
-                    Ok(Event::Text(ref e)) if in_value => {
-                        c.raw_value = e.unescape_and_decode(reader).unwrap();
-                        c.value = match &c.cell_type[..] {
-                            "s" => {
-                                if let Ok(pos) = c.raw_value.parse::<usize>() {
-                                    let s = &strings[pos]; // .to_string()
-                                    ExcelValue::String(Cow::Borrowed(s))
-                                } else {
-                                    ExcelValue::String(Cow::Owned(c.raw_value.clone()))
-                                }
-                            }
-                            "str" | "inlineStr" => {
-                                ExcelValue::String(Cow::Owned(c.raw_value.clone()))
-                            }
-                            "b" => {
-                                if c.raw_value == "0" {
-                                    ExcelValue::Bool(false)
-                                } else {
-                                    ExcelValue::Bool(true)
-                                }
-                            }
-                            "bl" => ExcelValue::None,
-                            "e" => ExcelValue::Error(c.raw_value.to_string()),
-                            _ if is_date(&c.style) => {
-                                let num = c.raw_value.parse::<f64>().unwrap();
-                                match utils::excel_number_to_date(num, date_system) {
-                                    utils::DateConversion::Date(date) => ExcelValue::Date(date),
-                                    utils::DateConversion::DateTime(date) => {
-                                        ExcelValue::DateTime(date)
-                                    }
-                                    utils::DateConversion::Time(time) => ExcelValue::Time(time),
-                                    utils::DateConversion::Number(num) => {
-                                        ExcelValue::Number(num as f64)
-                                    }
-                                }
-                            }
-                            _ => ExcelValue::Number(c.raw_value.parse::<f64>().unwrap()),
-                        };
-                    }
-                    Ok(Event::Text(ref e)) if in_cell => {
-                        let txt = e.unescape_and_decode(reader).unwrap();
-                        c.formula.push_str(&txt)
-                    }
-                    Ok(Event::End(ref e)) if e.name() == b"v" || e.name() == b"t" => {
-                        in_value = false;
-                    }
-                    Ok(Event::End(ref e)) if e.name() == b"c" => {
-                        if let Some(prev) = row.last() {
-                            let (mut last_col, _) = prev.coordinates();
-                            let (this_col, this_row) = c.coordinates();
-                            while this_col > last_col + 1 {
-                                let mut cell = new_cell();
-                                cell.reference
-                                    .push_str(&utils::num2col(last_col + 1).unwrap());
-                                cell.reference.push_str(&this_row.to_string());
-                                row.push(cell);
-                                last_col += 1;
-                            }
-                            row.push(c);
-                        } else {
-                            let (this_col, this_row) = c.coordinates();
-                            for n in 1..this_col {
-                                let mut cell = new_cell();
-                                cell.reference.push_str(&utils::num2col(n).unwrap());
-                                cell.reference.push_str(&this_row.to_string());
-                                row.push(cell);
-                            }
-                            row.push(c);
-                        }
-                        c = new_cell();
-                        in_cell = false;
-                    }
-                    Ok(Event::End(ref e)) if e.name() == b"row" => {
-                        self.num_cols = cmp::max(self.num_cols, row.len() as u16);
-                        while row.len() < self.num_cols as usize {
-                            let mut cell = new_cell();
-                            cell.reference
-                                .push_str(&utils::num2col(row.len() as u16 + 1).unwrap());
-                            cell.reference.push_str(&this_row.to_string());
-                            row.push(cell);
-                        }
-                        let next_row = Some(Row(row, this_row));
-                        if this_row == self.want_row {
-                            break next_row;
-                        } else {
-                            self.next_row = next_row;
-                            break empty_row(self.num_cols, self.want_row);
-                        }
-                    }
-                    Ok(Event::Eof) => break None,
-                    Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
-                    _ => (),
+    #[test]
+    fn test_to_string_grid() {
+        let mut wb = Workbook::open("./tests/data/7_nulls.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let grid = ws.to_string_grid(&mut wb).unwrap();
+        assert_eq!(grid.len(), 8);
+        assert!(grid.iter().all(|row| row.len() == 6));
+        assert_eq!(grid[0], vec!["", "0", "1", "2", "3", "4"]);
+        assert_eq!(
+            grid[1],
+            vec![
+                "0",
+                "foo",
+                "0.466474380029249",
+                "",
+                "0.937341933384455",
+                "0.387097140837212"
+            ]
+        );
+        assert_eq!(grid[5][..3], ["", "baz", "foo"]);
+        assert_eq!(grid[7][5], "");
+        // dates, booleans and errors
+        let mut wb = Workbook::open("./tests/data/mixed_types.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let grid = ws.to_string_grid(&mut wb).unwrap();
+        let rows = ws.to_vec(&mut wb).unwrap();
+        for (row, texts) in rows.iter().zip(grid.iter()) {
+            for (cell, text) in row.0.iter().zip(texts.iter()) {
+                assert!(!text.starts_with('"'), "{}", text);
+                match &cell.value {
+                    ExcelValue::String(s) => assert_eq!(text, s),
+                    ExcelValue::None => assert_eq!(text, ""),
+                    value => assert_eq!(*text, value.to_string().trim_matches('"')),
                 }
-                buf.clear();
             }
-        };
-        self.want_row += 1;
-        if next_row.is_none() && self.want_row - 1 < self.num_rows as usize {
-            self.done_file = true;
-            return empty_row(self.num_cols, self.want_row - 1);
         }
-        next_row
     }
-}
 
-fn is_date(style: &String) -> bool {
-    let is_d = style == "d";
-    let is_like_d_and_not_like_red = style.contains('d') && !style.contains("Red");
-    let is_like_m = style.contains('m');
-    if is_d || is_like_d_and_not_like_red || is_like_m {
-        true
-    } else {
-        style.contains('y')
+    #[test]
+    fn test_split_values() {
+        // every value in this sheet is interrupted by a comment, so it is read in two pieces
+        let mut wb = Workbook::open("./tests/data/split_values.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let row1 = ws.rows(&mut wb).unwrap().next().unwrap();
+        assert_eq!(row1[0].value, ExcelValue::String(Cow::Borrowed("A&B")));
+        assert_eq!(row1[0].raw_value, "A&B");
+        assert_eq!(row1[0].formula, "\"A\"&\"B\"");
+        assert_eq!(row1[1].value, ExcelValue::Number(1234.0));
+        assert_eq!(row1[2].value, ExcelValue::Error("#N/A".to_string()));
+        assert_eq!(row1[3].value, ExcelValue::String(Cow::Borrowed("x & y")));
+        let csv = String::from_utf8(ws.read_to_buffer(&mut wb).unwrap()).unwrap();
+        assert_eq!(csv, "\"A&B\",\"1234\",#N/A,\"x & y\"\n");
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::{ExcelValue, Workbook};
-    use std::{
-        borrow::Cow,
-        fs,
-        io::{Cursor, Read},
-    };
+    #[test]
+    fn test_general_format() {
+        use super::general_format;
+        let cases = [
+            (3.0, "3"),
+            (-3.5, "-3.5"),
+            (0.0, "0"),
+            (-0.0, "0"),
+            (0.1 + 0.2, "0.3"),
+            (1.0 / 3.0, "0.333333333333333"),
+            (123456789012345.0, "123456789012345"),
+            (999999999999999.9, "1E+15"),
+            (1e15, "1E+15"),
+            (1234567890123456789.0, "1.23456789012346E+18"),
+            (1e20, "1E+20"),
+            (-1.5e100, "-1.5E+100"),
+            (0.000000001, "0.000000001"),
+            (0.00000000012, "1.2E-10"),
+            (5e-324, "4.94065645841247E-324"),
+            (f64::INFINITY, "inf"),
+        ];
+        for (n, text) in cases.iter() {
+            assert_eq!(general_format(*n), *text, "{:e}", n);
+        }
+        // csv output from `write_csv_map` keeps every digit instead
+        let mut wb = Workbook::open("./tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let mut csv = vec![];
+        ws.write_csv_map(&mut wb, &mut csv, |_| {
+            vec![ExcelValue::Number(1e20), ExcelValue::Number(0.1 + 0.2)]
+        })
+        .unwrap();
+        assert!(csv.starts_with(b"\"100000000000000000000\",\"0.30000000000000004\"\n"));
+    }
 
     #[test]
-    fn test_ups() {
-        let mut file = fs::File::open("./tests/data/UPS.Galaxy.VS.PX.xlsx").unwrap();
-        let mut buff = vec![];
-        file.read_to_end(&mut buff).unwrap();
-        let mut wb = Workbook::new(Cursor::new(buff)).unwrap();
+    fn test_format_category() {
+        use super::format_category;
+        let builtin = [
+            (0, FormatCategory::General),
+            (3, FormatCategory::Number),
+            (6, FormatCategory::Currency),
+            (9, FormatCategory::Percentage),
+            (11, FormatCategory::Scientific),
+            (12, FormatCategory::Fraction),
+            (15, FormatCategory::Date),
+            (22, FormatCategory::Date),
+            (18, FormatCategory::Time),
+            (46, FormatCategory::Time),
+            (38, FormatCategory::Number),
+            (42, FormatCategory::Accounting),
+            (48, FormatCategory::Scientific),
+            (49, FormatCategory::Text),
+        ];
+        for (id, category) in builtin.iter() {
+            // the id wins over the format code
+            assert_eq!(format_category(Some(*id), ""), *category, "numFmtId {}", id);
+        }
+        let custom = [
+            ("", FormatCategory::General),
+            ("0.000", FormatCategory::Number),
+            ("#,##0_);[Red](#,##0)", FormatCategory::Number),
+            ("\"$\"#,##0.00", FormatCategory::Currency),
+            ("[$€-407] #,##0.00", FormatCategory::Currency),
+            ("[$-409]#,##0", FormatCategory::Number),
+            (
+                "_-* #,##0.00\\ \"€\"_-;\\-* #,##0.00\\ \"€\"_-",
+                FormatCategory::Accounting,
+            ),
+            ("dd/mm/yyyy", FormatCategory::Date),
+            ("[$-409]mmmm d, yyyy;@", FormatCategory::Date),
+            ("yyyy-mm-dd hh:mm", FormatCategory::Date),
+            ("hh:mm AM/PM", FormatCategory::Time),
+            ("[mm]:ss", FormatCategory::Time),
+            ("0.0%", FormatCategory::Percentage),
+            ("0.0\"%\"", FormatCategory::Number),
+            ("0.000E+00", FormatCategory::Scientific),
+            ("# ??/16", FormatCategory::Fraction),
+            ("@", FormatCategory::Text),
+            (";;;", FormatCategory::Custom),
+            ("\"Week \"0", FormatCategory::Number),
+            ("0 \"days\";\"none\"", FormatCategory::Number),
+            ("\"Total: \"@", FormatCategory::Custom),
+        ];
+        for (code, category) in custom.iter() {
+            assert_eq!(format_category(Some(164), code), *category, "{}", code);
+        }
+        let mut wb = Workbook::open("./tests/data/number_formats.xlsx").unwrap();
         let sheets = wb.sheets();
-        let ws = sheets.get("Table001 (Page 1-19)").unwrap();
-        let mut row_iter = ws.rows(&mut wb);
-        let row2 = row_iter.nth(1).unwrap();
-        assert_eq!(row2[3].value, ExcelValue::Number(0.0));
-        let row3 = row_iter.next().unwrap();
-        assert_eq!(row3[4].value, ExcelValue::String(Cow::Borrowed("Bit")));
+        let ws = sheets.get(1).unwrap();
+        let row1 = ws.rows(&mut wb).unwrap().next().unwrap();
+        let categories: Vec<_> = row1.0.iter().map(|c| c.format_category()).collect();
+        assert_eq!(
+            categories,
+            vec![
+                FormatCategory::General,
+                FormatCategory::Number,
+                FormatCategory::Currency,
+                FormatCategory::Percentage,
+                FormatCategory::Scientific,
+                FormatCategory::Fraction,
+                FormatCategory::Date,
+                FormatCategory::Time,
+                FormatCategory::Accounting,
+                FormatCategory::Text,
+                FormatCategory::Currency,
+                FormatCategory::Date,
+                FormatCategory::Time,
+                FormatCategory::Custom,
+                // no style at all
+                FormatCategory::General,
+            ]
+        );
     }
 
     #[test]
-    fn test_read_to_buffer() {
-        /* This spreadsheet has a combination of null values and missing cells to put the method
-         * through its paces */
-        let mut file = fs::File::open("./tests/data/7_nulls.xlsx").unwrap();
-        let mut buff = vec![];
-        file.read_to_end(&mut buff).unwrap();
-        let mut wb = Workbook::new(Cursor::new(buff)).unwrap();
+    fn test_no_trailing_fill() {
+        let mut wb = Workbook::open("./tests/data/stale_dimension.xlsx").unwrap();
         let sheets = wb.sheets();
         let ws = sheets.get(1).unwrap();
-        let byte_buffer = ws.read_to_buffer(&mut wb);
-        let byte_buffer_as_string = String::from_utf8(byte_buffer).unwrap();
-        let expected = ",0,1,2,3,4\n0,foo,0.4664743800292485,,0.9373419333844548,0.3870971408372121\n1,,0.6363620246706366,baz,foo,0.4664743800292485\n2,,0.08179075658393076,bar,,0.6363620246706366\n3,,0.9373419333844548,0.3870971408372121,,0.08179075658393076\n,baz,foo,0.4664743800292485,,0.9373419333844548\n5,bar,,0.6363620246706366,baz,foo\n6,0.3870971408372121,,0.08179075658393076,bar,\n";
+        assert_eq!(ws.rows(&mut wb).unwrap().count(), 20);
+        let mut rows = ws.rows(&mut wb).unwrap().no_trailing_fill();
+        assert_eq!(rows.nth(3).unwrap().to_string(), "4,40,,,,");
+        assert!(rows.next().is_none());
+        assert!(rows.next().is_none());
+        // empty rows between rows are still filled in (the dimension is A1:C12, the last row 9)
+        let mut wb = Workbook::open("./tests/data/sparse_rows.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let rows: Vec<_> = ws
+            .rows(&mut wb)
+            .unwrap()
+            .no_trailing_fill()
+            .map(|r| r.1)
+            .collect();
+        assert_eq!(rows, (1..=9).collect::<Vec<_>>());
+    }
 
-        assert_eq!(byte_buffer_as_string, expected);
+    #[test]
+    fn test_strict_dimension() {
+        let mut wb = Workbook::open("./tests/data/stray_cells.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get(1).unwrap();
+        let rows = ws.to_vec(&mut wb).unwrap();
+        assert_eq!(rows.len(), 50);
+        assert_eq!(rows[1].0.len(), 5);
+        assert_eq!(rows[49].0.len(), 26);
+        let rows: Vec<_> = ws.rows(&mut wb).unwrap().strict_dimension().collect();
+        assert_eq!(rows.len(), 10);
+        assert!(rows.iter().all(|r| r.0.len() == 3));
+        assert_eq!(rows[1].to_string(), "4,5,6");
+        assert_eq!(rows[9].1, 10);
+        // the same when skipping ahead
+        let mut rows = ws.rows(&mut wb).unwrap().strict_dimension();
+        assert_eq!(rows.nth(8).unwrap().1, 9);
+        assert_eq!(rows.next().unwrap().1, 10);
+        assert!(rows.next().is_none());
     }
 
     #[test]
-    fn test_read_to_buffer_with_dates() {
-        /* This spreadsheet has a combination of null values and missing cells to put the method
-         * through its paces */
-        let mut file = fs::File::open("./tests/data/dates2.xlsx").unwrap();
-        let mut buff = vec![];
-        file.read_to_end(&mut buff).unwrap();
-        let mut wb = Workbook::new(Cursor::new(buff)).unwrap();
+    fn test_alignment() {
+        let mut wb = Workbook::open("./tests/data/dates2.xlsx").unwrap();
         let sheets = wb.sheets();
         let ws = sheets.get(1).unwrap();
-        let byte_buffer = ws.read_to_buffer(&mut wb);
-        let byte_buffer_as_string = String::from_utf8(byte_buffer).unwrap();
-        println!("{:?}", byte_buffer_as_string);
-        let expected = "\"Line\",\"Date1\",\"String1\",\"String2\",\"Date2\",\"Float1\",\"String3\"\n\"11\",2022-03-13,\"S1_Line1\",\"S2_Line1\",2021-07-22,\"55401.4834901147\",\"S3L1\"\n\"12\",2022-05-06,\"S1_Line (2)\",\"S2_Line2\",2021-09-14,\"59895.0195440879\",\"S3L2\"\n\"13\",2022-10-01,\"S1, Line3\",\"S2_Line3\",2022-02-09,\"73563.1850302802\",\"S3L3\"\n\"14\",2022-11-24,\"S1 \"\"Line 4\"\"\",\"S2_Line4\",2022-04-04,\"81245.2187551785\",\"S3L4\"\n\"15\",2022-12-01,\"S1_Line5\",\"S2_Line5\",2022-04-11,\"82692.7459436702\",\"S3L5\"\n\"17\",2023-01-24,\"S1_Line6\",\"S2_Line6\",2022-06-04,\"98603.829483607406\",\"S3L6\"\n\"17\",2023-01-24,\"Ele \"\"Line 4\"\"\",\"test ws::tests::test_read_to_buffer_with_dates ... ok\n\ntest result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 24 filtered out; finished in 0.01s\",2022-06-04,\"98603.829483607406\",\"S3L6\"\n,,,,,,\n,,,,,,\n";
+        let mut rows = ws.rows(&mut wb).unwrap();
+        let row1 = rows.next().unwrap();
+        let row8 = rows.nth(6).unwrap();
+        let wrapped = row8[3].alignment();
+        assert!(wrapped.wrap_text);
+        assert_eq!(wrapped.vertical, Some("bottom".to_string()));
+        assert_eq!(wrapped.horizontal, None);
+        assert!(!row1[0].alignment().wrap_text);
+    }
 
-        assert_eq!(byte_buffer_as_string, expected);
+    #[test]
+    fn test_default_alignment() {
+        let mut wb = Workbook::open("./tests/data/Book1.xlsx").unwrap();
+        let sheets = wb.sheets();
+        let ws = sheets.get("Sheet1").unwrap();
+        let row1 = ws.rows(&mut wb).unwrap().next().unwrap();
+        assert_eq!(row1[0].alignment(), Alignment::default());
     }
 }